@@ -2,7 +2,8 @@ use codegen_rs::structures::gen_enum::NamedComponentSignature;
 use codegen_rs::structures::visibility::Visibility;
 use codegen_rs::structures::{ComponentSignature, Ownership, RustType, Signature};
 use codegen_rs::{
-    FileBuilder, FunctionBuilder, ImplBuilder, MethodBuilder, ModuleBuilder, StructBuilder,
+    CrateBuilder, FileBuilder, FunctionBuilder, ImplBuilder, MethodBuilder, ModuleBuilder,
+    StructBuilder,
 };
 
 fn main() {
@@ -14,7 +15,7 @@ fn main() {
     let my_val = "my_val";
     let my_val_type = RustType::in_scope("i32");
     let my_val_value = 5;
-    ModuleBuilder::new(
+    let main = ModuleBuilder::new(
         FileBuilder::new("main")
             .add_function(FunctionBuilder::new("main")
                 .set_body(format!("let {my_val} = {}{{ {my_val}: {my_val_value} }};\nassert_eq!({my_val_value}, {my_val}.get_val());\nprintln!(\"{my_val} is {my_val_value}!\");", my_struct.format()))
@@ -33,6 +34,9 @@ fn main() {
                     .set_body(format!("return self.{};", my_val))
                 )
             )
-    ))
-        .write_to_disk("examples").unwrap();
+    ));
+    CrateBuilder::new("example_project", "0.1.0")
+        .add_bin("example_project", main)
+        .write_to_disk("examples/example_project")
+        .unwrap();
 }
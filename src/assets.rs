@@ -0,0 +1,397 @@
+use crate::errors::{Error, Result};
+use crate::structures::gen_enum::NamedComponentSignature;
+use crate::structures::method::Argument;
+use crate::structures::visibility::Visibility;
+use crate::structures::{Annotation, ComponentSignature, Ownership, RustType, Signature};
+use crate::util::casing::RustCase;
+use crate::{Cfg, ConstantBuilder, FileBuilder, FunctionBuilder};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Generates a module embedding every file under a directory. Each file gets a `static`, a
+/// `&str` through `include_str!` for text files and a `&[u8]` through `include_bytes!` for
+/// anything else, named after its path, ex: `CSS_SITE_CSS` for `css/site.css`.
+///
+/// The module also gets `PATHS`, every path relative to the directory with `/` separators,
+/// `get`, looking a path up, and `mime`, the MIME type guessed from the extension.
+/// Hidden files and directories are skipped.
+///
+/// With `load_from_disk_when`, builds matching the predicate read the files on every `get`
+/// instead, so edits show up without rebuilding. The statics only exist in the other builds and
+/// `get` returns a `Cow` in both
+pub struct Assets {
+    dir: PathBuf,
+    root: Root,
+    dev: Option<Cfg>,
+}
+
+enum Root {
+    Absolute,
+    Manifest(String),
+}
+
+struct Asset {
+    path: String,
+    name: String,
+    mime: &'static str,
+    text: bool,
+}
+
+impl Assets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            root: Root::Absolute,
+            dev: None,
+        }
+    }
+
+    /// Refers to the files relative to `CARGO_MANIFEST_DIR` rather than by absolute path, so the
+    /// generated module can be checked in. `dir` is the asset directory relative to the manifest
+    pub fn relative_to_manifest(mut self, dir: impl Into<String>) -> Self {
+        self.root = Root::Manifest(dir.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Builds matching `cfg` read the files from disk, ex: `Cfg::raw("debug_assertions")`
+    pub fn load_from_disk_when(mut self, cfg: Cfg) -> Self {
+        self.dev = Some(cfg);
+        self
+    }
+
+    pub fn into_file_builder(self, file_name: impl Into<String>) -> Result<FileBuilder> {
+        let mut files = vec![];
+        walk(&self.dir, "", &mut files)?;
+        // `PATHS` is generated alongside the statics
+        let mut taken = BTreeSet::from(["PATHS".to_string()]);
+        let assets = files
+            .into_iter()
+            .map(|(path, bytes)| {
+                let base = RustCase::convert_lossy(&path, RustCase::Scream);
+                let mut name = base.clone();
+                let mut n = 2;
+                while !taken.insert(name.clone()) {
+                    name = format!("{base}_{n}");
+                    n += 1;
+                }
+                let mime = mime_type(&path);
+                let text = is_text(mime) && std::str::from_utf8(&bytes).is_ok();
+                Asset {
+                    path,
+                    name,
+                    mime,
+                    text,
+                }
+            })
+            .collect::<Vec<Asset>>();
+        let base = match self.root {
+            Root::Absolute => Some(
+                self.dir
+                    .canonicalize()?
+                    .to_str()
+                    .ok_or_else(|| {
+                        Error::Schema(
+                            self.dir.display().to_string(),
+                            "the path isn't valid UTF-8".to_string(),
+                        )
+                    })?
+                    .replace('\\', "/"),
+            ),
+            Root::Manifest(_) => None,
+        };
+        let location = |asset: &Asset| match (&base, &self.root) {
+            (Some(base), _) => format!("{:?}", format!("{base}/{}", asset.path)),
+            (None, Root::Manifest(dir)) => format!(
+                "concat!(env!(\"CARGO_MANIFEST_DIR\"), {:?})",
+                format!("/{dir}/{}", asset.path)
+            ),
+            (None, Root::Absolute) => unreachable!(),
+        };
+
+        let mut fb = FileBuilder::new(file_name);
+        let embedded = self.dev.clone().map(Cfg::not);
+        for asset in &assets {
+            let (rust_type, include) = if asset.text {
+                ("&str", "include_str")
+            } else {
+                ("&[u8]", "include_bytes")
+            };
+            let mut constant = ConstantBuilder::static_builder(
+                &asset.name,
+                RustType::in_scope(rust_type),
+                format!("{include}!({})", location(asset)),
+            )
+            .set_visibility(Visibility::Public)
+            .add_annotation(Annotation::new(format!("doc = {:?}", asset.path)));
+            if let Some(cfg) = &embedded {
+                constant = constant.add_cfg(cfg.clone());
+            }
+            fb = fb.add_const(constant);
+        }
+        fb = fb.add_const(
+            ConstantBuilder::static_builder(
+                "PATHS",
+                RustType::in_scope("&[&str]"),
+                format!(
+                    "&[{}]",
+                    assets
+                        .iter()
+                        .map(|a| format!("{:?}", a.path))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+            )
+            .set_visibility(Visibility::Public),
+        );
+
+        let arms = |value: &dyn Fn(&Asset) -> String, fallback: &str| {
+            let mut body = String::new();
+            for asset in &assets {
+                body.push_str(&format!("        {:?} => {},\n", asset.path, value(asset)));
+            }
+            body.push_str(&format!("        _ => {fallback},\n"));
+            body
+        };
+        let embedded_bytes = |asset: &Asset| {
+            if asset.text {
+                format!("{}.as_bytes()", asset.name)
+            } else {
+                asset.name.clone()
+            }
+        };
+        match &self.dev {
+            None => {
+                fb = fb.add_function(lookup("get", "Option<&'static [u8]>").set_body(format!(
+                    "    match path {{\n{}    }}",
+                    arms(&|a| format!("Some({})", embedded_bytes(a)), "None")
+                )));
+            }
+            Some(cfg) => {
+                fb = fb
+                    .add_function(
+                        lookup("get", "Option<std::borrow::Cow<'static, [u8]>>")
+                            .add_cfg(Cfg::not(cfg.clone()))
+                            .set_body(format!(
+                                "    match path {{\n{}    }}",
+                                arms(
+                                    &|a| format!(
+                                        "Some(std::borrow::Cow::Borrowed({}))",
+                                        embedded_bytes(a)
+                                    ),
+                                    "None"
+                                )
+                            )),
+                    )
+                    .add_function(
+                        lookup("get", "Option<std::borrow::Cow<'static, [u8]>>")
+                            .add_cfg(cfg.clone())
+                            .set_body(format!(
+                                "    let file = match path {{\n{}    }};\n    \
+                                std::fs::read(file).ok().map(std::borrow::Cow::Owned)",
+                                arms(&location, "return None")
+                            )),
+                    );
+            }
+        }
+        fb = fb.add_function(lookup("mime", "Option<&'static str>").set_body(format!(
+            "    match path {{\n{}    }}",
+            arms(&|a| format!("Some({:?})", a.mime), "None")
+        )));
+        Ok(fb)
+    }
+}
+
+/// A public `fn name(path: &str) -> returns`
+fn lookup(name: &str, returns: &str) -> FunctionBuilder {
+    FunctionBuilder::new(name)
+        .set_visibility(Visibility::Public)
+        .add_argument(Argument::new(
+            Ownership::Owned,
+            NamedComponentSignature::new_simple_type("path", RustType::in_scope("&str")),
+        ))
+        .set_return_type(ComponentSignature::Signature(Signature::simple(
+            RustType::in_scope(returns),
+        )))
+}
+
+/// Collects every file under `dir`, sorted by path
+fn walk(dir: &Path, prefix: &str, files: &mut Vec<(String, Vec<u8>)>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            return Err(Error::Schema(
+                entry.path().display().to_string(),
+                "the path isn't valid UTF-8".to_string(),
+            ));
+        };
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let path = format!("{prefix}{file_name}");
+        if entry.file_type()?.is_dir() {
+            walk(&entry.path(), &format!("{path}/"), files)?;
+        } else {
+            files.push((path, std::fs::read(entry.path())?));
+        }
+    }
+    Ok(())
+}
+
+/// Guessed from the extension, `application/octet-stream` if unknown
+fn mime_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, e)| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" | "map" => "application/json",
+        "xml" => "application/xml",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+fn is_text(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json"
+                | "application/xml"
+                | "application/toml"
+                | "application/yaml"
+                | "image/svg+xml"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assets::Assets;
+    use crate::Cfg;
+
+    fn asset_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("codegen-rs-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("css")).unwrap();
+        std::fs::write(dir.join("index.html"), "<h1>hi</h1>").unwrap();
+        std::fs::write(dir.join("css").join("site.css"), "h1 {}").unwrap();
+        std::fs::write(dir.join("logo.png"), [0x89, b'P', b'N', b'G']).unwrap();
+        std::fs::write(dir.join(".hidden"), "skipped").unwrap();
+        dir
+    }
+
+    #[test]
+    fn embeds_files() {
+        let dir = asset_dir("assets");
+        let rendered = Assets::new(&dir)
+            .relative_to_manifest("static")
+            .into_file_builder("assets")
+            .unwrap()
+            .format_file();
+        assert_eq!(
+            "#[doc = \"css/site.css\"]\n\
+            pub static CSS_SITE_CSS: &str = include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/static/css/site.css\"));\n\
+            #[doc = \"index.html\"]\n\
+            pub static INDEX_HTML: &str = include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/static/index.html\"));\n\
+            #[doc = \"logo.png\"]\n\
+            pub static LOGO_PNG: &[u8] = include_bytes!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/static/logo.png\"));\n\
+            pub static PATHS: &[&str] = &[\"css/site.css\", \"index.html\", \"logo.png\"];\n\
+            pub fn get(path: &str) -> Option<&'static [u8]> {\n    \
+            match path {\n        \
+            \"css/site.css\" => Some(CSS_SITE_CSS.as_bytes()),\n        \
+            \"index.html\" => Some(INDEX_HTML.as_bytes()),\n        \
+            \"logo.png\" => Some(LOGO_PNG),\n        \
+            _ => None,\n    \
+            }\n\
+            }\n\
+            pub fn mime(path: &str) -> Option<&'static str> {\n    \
+            match path {\n        \
+            \"css/site.css\" => Some(\"text/css\"),\n        \
+            \"index.html\" => Some(\"text/html\"),\n        \
+            \"logo.png\" => Some(\"image/png\"),\n        \
+            _ => None,\n    \
+            }\n\
+            }\n",
+            rendered
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn loads_from_disk_in_dev_builds() {
+        let dir = asset_dir("assets-dev");
+        let absolute = dir.canonicalize().unwrap().display().to_string();
+        let rendered = Assets::new(&dir)
+            .load_from_disk_when(Cfg::raw("debug_assertions"))
+            .into_file_builder("assets")
+            .unwrap()
+            .format_file();
+        assert!(rendered.contains(&format!(
+            "#[cfg(not(debug_assertions))]\n\
+            #[doc = \"logo.png\"]\n\
+            pub static LOGO_PNG: &[u8] = include_bytes!(\"{absolute}/logo.png\");\n"
+        )));
+        assert!(rendered.contains(
+            "#[cfg(not(debug_assertions))]\n\
+            pub fn get(path: &str) -> Option<std::borrow::Cow<'static, [u8]>> {\n"
+        ));
+        assert!(rendered.contains("\"logo.png\" => Some(std::borrow::Cow::Borrowed(LOGO_PNG)),\n"));
+        assert!(rendered.contains(&format!(
+            "#[cfg(debug_assertions)]\n\
+            pub fn get(path: &str) -> Option<std::borrow::Cow<'static, [u8]>> {{\n    \
+            let file = match path {{\n        \
+            \"css/site.css\" => \"{absolute}/css/site.css\",\n"
+        )));
+        assert!(rendered.contains(
+            "        _ => return None,\n    \
+            };\n    \
+            std::fs::read(file).ok().map(std::borrow::Cow::Owned)\n}\n"
+        ));
+        assert!(Assets::new(dir.join("missing"))
+            .into_file_builder("assets")
+            .is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_names_apart() {
+        let dir = asset_dir("assets-names");
+        std::fs::write(dir.join("paths"), "a").unwrap();
+        std::fs::write(dir.join("css-site.css"), "p {}").unwrap();
+        let rendered = Assets::new(&dir)
+            .relative_to_manifest("static")
+            .into_file_builder("assets")
+            .unwrap()
+            .format_file();
+        assert!(rendered.contains("pub static CSS_SITE_CSS_2: &str = include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/static/css-site.css\"));\n"));
+        assert!(rendered.contains("pub static CSS_SITE_CSS: &str = include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/static/css/site.css\"));\n"));
+        assert!(rendered.contains("pub static PATHS_2: &[u8] = "));
+        assert!(rendered.contains("\"paths\" => Some(PATHS_2),\n"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
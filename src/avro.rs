@@ -0,0 +1,666 @@
+use crate::errors::{Error, Result};
+use crate::model::{Model, ModelField, ModelKind, ModelType, TypeRef, RESERVED_NAMES};
+use crate::util::casing::RustCase;
+use crate::FileBuilder;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Generates serde models from Avro schemas, ex: `.avsc` files.
+/// Records become structs, enums become enums and fixed types aliases of `Vec<u8>`.
+/// Unions of `null` and one type become `Option`s, other unions enums tagged with the Avro
+/// name of each branch, named after the record and field they're declared in.
+///
+/// Named types can be referred to from any schema added, short names are resolved in the
+/// enclosing namespace first. Logical types are their underlying type unless mapped, see
+/// `map_logical_type`
+pub struct Avro {
+    schemas: Vec<(String, Value)>,
+    logical: BTreeMap<String, String>,
+}
+
+/// Where a type is used, unions are named after it and references to it may need a `Box`
+struct Holder<'h> {
+    full_name: &'h str,
+    rust_name: &'h str,
+    field: &'h str,
+}
+
+impl Default for Avro {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Avro {
+    pub fn new() -> Self {
+        Self {
+            schemas: vec![],
+            logical: BTreeMap::new(),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        Self::new().add_schema(source)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new().add_schema_file(path)
+    }
+
+    pub fn add_schema(self, source: &str) -> Result<Self> {
+        self.add(String::from("input.avsc"), source)
+    }
+
+    pub fn add_schema_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        self.add(path.display().to_string(), &std::fs::read_to_string(path)?)
+    }
+
+    fn add(mut self, location: String, source: &str) -> Result<Self> {
+        let schema = serde_json::from_str(source)
+            .map_err(|e| Error::Schema(location.clone(), e.to_string()))?;
+        self.schemas.push((location, schema));
+        Ok(self)
+    }
+
+    /// Uses a Rust type for a logical type, ex: `timestamp-millis` to
+    /// `chrono::DateTime<chrono::Utc>`. The type must deserialize from the underlying type
+    pub fn map_logical_type(
+        mut self,
+        logical_type: impl Into<String>,
+        rust_type: impl Into<String>,
+    ) -> Self {
+        self.logical.insert(logical_type.into(), rust_type.into());
+        self
+    }
+
+    pub fn into_file_builder(self, file_name: impl Into<String>) -> Result<FileBuilder> {
+        let mut generator = Generator {
+            logical: &self.logical,
+            named: BTreeMap::new(),
+            order: vec![],
+            types: vec![],
+            taken: BTreeSet::from(RESERVED_NAMES.map(String::from)),
+        };
+        for (location, schema) in &self.schemas {
+            generator.declare(location, schema, None)?;
+        }
+        for full_name in generator.order.clone() {
+            let local = full_name.rsplit('.').next().unwrap_or(&full_name);
+            let rust_name = generator.reserve(&RustCase::convert_lossy(local, RustCase::Pascal));
+            if let Some(named) = generator.named.get_mut(&full_name) {
+                named.rust_name = rust_name;
+            }
+        }
+        for full_name in generator.order.clone() {
+            generator.generate(&full_name)?;
+        }
+        let model = Model {
+            types: generator.types,
+            rename_all: true,
+        };
+        Ok(model.into_file_builder(file_name))
+    }
+}
+
+fn primitive(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "null" => "()",
+        "boolean" => "bool",
+        "int" => "i32",
+        "long" => "i64",
+        "float" => "f32",
+        "double" => "f64",
+        "bytes" => "Vec<u8>",
+        "string" => "String",
+        _ => return None,
+    })
+}
+
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace.filter(|ns| !ns.is_empty() && !name.contains('.')) {
+        Some(namespace) => format!("{namespace}.{name}"),
+        None => name.to_string(),
+    }
+}
+
+fn namespace_of(full_name: &str) -> Option<&str> {
+    full_name.rsplit_once('.').map(|(namespace, _)| namespace)
+}
+
+fn is_null(schema: &Value) -> bool {
+    match schema {
+        Value::String(name) => name == "null",
+        Value::Object(object) => object.get("type").is_some_and(is_null),
+        _ => false,
+    }
+}
+
+/// The fields of a record, nothing for other types
+fn fields(schema: &Map<String, Value>) -> impl Iterator<Item = &Map<String, Value>> {
+    schema
+        .get("fields")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object)
+}
+
+/// Only defaults that are what the Rust type defaults to can be left to serde. Types mapped
+/// with `map_logical_type` may not implement `Default`, only primitives are trusted
+fn fills_default(default: &Value, ty: &TypeRef) -> bool {
+    let builtin = match ty {
+        TypeRef::Builtin(name) => name.as_str(),
+        _ => "",
+    };
+    match (default, ty) {
+        (Value::Null, TypeRef::Option(_)) => true,
+        (Value::Array(items), TypeRef::Vec(_)) => items.is_empty(),
+        (Value::Object(entries), TypeRef::Map(_)) => entries.is_empty(),
+        (Value::Bool(value), _) => builtin == "bool" && !value,
+        (Value::Number(value), _) => {
+            matches!(builtin, "i32" | "i64" | "f32" | "f64") && value.as_f64() == Some(0.0)
+        }
+        (Value::String(value), _) => matches!(builtin, "String" | "Vec<u8>") && value.is_empty(),
+        _ => false,
+    }
+}
+
+struct Named<'a> {
+    rust_name: String,
+    schema: &'a Map<String, Value>,
+}
+
+struct Generator<'a> {
+    logical: &'a BTreeMap<String, String>,
+    /// Named types by full name
+    named: BTreeMap<String, Named<'a>>,
+    /// Full names in the order they're defined
+    order: Vec<String>,
+    types: Vec<ModelType>,
+    taken: BTreeSet<String>,
+}
+
+impl<'a> Generator<'a> {
+    /// Finds the named types, wherever they're defined
+    fn declare(
+        &mut self,
+        location: &str,
+        schema: &'a Value,
+        namespace: Option<&str>,
+    ) -> Result<()> {
+        match schema {
+            Value::Array(branches) => {
+                for branch in branches {
+                    self.declare(location, branch, namespace)?;
+                }
+            }
+            Value::Object(object) => match object.get("type") {
+                Some(Value::String(kind)) => match kind.as_str() {
+                    "record" | "error" | "enum" | "fixed" => {
+                        let name = object.get("name").and_then(Value::as_str).ok_or_else(|| {
+                            Error::Schema(location.to_string(), format!("{kind} without a name"))
+                        })?;
+                        let namespace = object
+                            .get("namespace")
+                            .and_then(Value::as_str)
+                            .or(namespace);
+                        let full_name = full_name(name, namespace);
+                        if self.named.contains_key(&full_name) {
+                            return Err(Error::Schema(full_name, "defined twice".to_string()));
+                        }
+                        self.order.push(full_name.clone());
+                        self.named.insert(
+                            full_name.clone(),
+                            Named {
+                                rust_name: String::new(),
+                                schema: object,
+                            },
+                        );
+                        for field in fields(object) {
+                            if let Some(ty) = field.get("type") {
+                                self.declare(location, ty, namespace_of(&full_name))?;
+                            }
+                        }
+                    }
+                    "array" => {
+                        if let Some(items) = object.get("items") {
+                            self.declare(location, items, namespace)?;
+                        }
+                    }
+                    "map" => {
+                        if let Some(values) = object.get("values") {
+                            self.declare(location, values, namespace)?;
+                        }
+                    }
+                    _ => {}
+                },
+                Some(nested) => self.declare(location, nested, namespace)?,
+                None => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn generate(&mut self, full_name: &str) -> Result<()> {
+        let named = &self.named[full_name];
+        let schema = named.schema;
+        let rust_name = named.rust_name.clone();
+        let doc = schema.get("doc").and_then(Value::as_str).map(String::from);
+        let index = self.types.len();
+        self.types.push(ModelType {
+            name: rust_name.clone(),
+            doc,
+            kind: ModelKind::Struct(vec![]),
+        });
+        let kind = match schema.get("type").and_then(Value::as_str) {
+            Some("enum") => ModelKind::Enum(
+                schema
+                    .get("symbols")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect(),
+            ),
+            Some("fixed") => ModelKind::Alias(self.logical_type(schema, "bytes")),
+            _ => {
+                let mut model_fields = vec![];
+                for field in fields(schema) {
+                    let name = field.get("name").and_then(Value::as_str).ok_or_else(|| {
+                        Error::Schema(full_name.to_string(), "field without a name".to_string())
+                    })?;
+                    let ty = field.get("type").ok_or_else(|| {
+                        Error::Schema(
+                            format!("{full_name}.{name}"),
+                            "field without a type".to_string(),
+                        )
+                    })?;
+                    let holder = Holder {
+                        full_name,
+                        rust_name: &rust_name,
+                        field: name,
+                    };
+                    let ty = self.type_ref(ty, namespace_of(full_name), &holder, false)?;
+                    model_fields.push(ModelField {
+                        name: name.to_string(),
+                        default: field.get("default").is_some_and(|d| fills_default(d, &ty)),
+                        ty,
+                        optional: false,
+                        doc: field.get("doc").and_then(Value::as_str).map(String::from),
+                    });
+                }
+                ModelKind::Struct(model_fields)
+            }
+        };
+        self.types[index].kind = kind;
+        Ok(())
+    }
+
+    /// `contained` types are held in a `Vec` or a map, which breaks cycles without a `Box`
+    fn type_ref(
+        &mut self,
+        schema: &Value,
+        namespace: Option<&str>,
+        holder: &Holder,
+        contained: bool,
+    ) -> Result<TypeRef> {
+        let object = match schema {
+            Value::String(name) => return self.reference(name, namespace, holder, contained),
+            Value::Array(branches) => return self.union(branches, namespace, holder),
+            Value::Object(object) => object,
+            _ => {
+                return Err(Error::Schema(
+                    format!("{}.{}", holder.full_name, holder.field),
+                    "invalid schema".to_string(),
+                ))
+            }
+        };
+        let kind = match object.get("type") {
+            Some(Value::String(kind)) => kind.as_str(),
+            Some(nested) => return self.type_ref(nested, namespace, holder, contained),
+            None => {
+                return Err(Error::Schema(
+                    format!("{}.{}", holder.full_name, holder.field),
+                    "schema without a type".to_string(),
+                ))
+            }
+        };
+        let inner = |key: &str| {
+            object.get(key).ok_or_else(|| {
+                Error::Schema(
+                    format!("{}.{}", holder.full_name, holder.field),
+                    format!("{kind} without {key}"),
+                )
+            })
+        };
+        match kind {
+            "array" => Ok(TypeRef::vec(self.type_ref(
+                inner("items")?,
+                namespace,
+                holder,
+                true,
+            )?)),
+            "map" => Ok(TypeRef::map(self.type_ref(
+                inner("values")?,
+                namespace,
+                holder,
+                true,
+            )?)),
+            "record" | "error" | "enum" | "fixed" => {
+                let name = inner("name")?.as_str().unwrap_or_default();
+                let own = object.get("namespace").and_then(Value::as_str);
+                let full_name = full_name(name, own.or(namespace));
+                self.reference(&full_name, namespace, holder, contained)
+            }
+            _ if object.contains_key("logicalType") => Ok(self.logical_type(object, kind)),
+            _ => self.reference(kind, namespace, holder, contained),
+        }
+    }
+
+    fn logical_type(&self, schema: &Map<String, Value>, underlying: &str) -> TypeRef {
+        let mapped = schema
+            .get("logicalType")
+            .and_then(Value::as_str)
+            .and_then(|logical| self.logical.get(logical));
+        match mapped {
+            Some(rust_type) => TypeRef::builtin(rust_type),
+            None => TypeRef::builtin(primitive(underlying).unwrap_or("Vec<u8>")),
+        }
+    }
+
+    fn reference(
+        &self,
+        name: &str,
+        namespace: Option<&str>,
+        holder: &Holder,
+        contained: bool,
+    ) -> Result<TypeRef> {
+        if let Some(primitive) = primitive(name) {
+            return Ok(TypeRef::builtin(primitive));
+        }
+        let full_name = self.resolve(name, namespace)?;
+        let ty = TypeRef::Named(self.named[&full_name].rust_name.clone());
+        match !contained && self.reaches(&full_name, holder.full_name, &mut BTreeSet::new()) {
+            true => Ok(TypeRef::boxed(ty)),
+            false => Ok(ty),
+        }
+    }
+
+    /// Short names are looked up in the enclosing namespace, then in the null namespace
+    fn resolve(&self, name: &str, namespace: Option<&str>) -> Result<String> {
+        [full_name(name, namespace), name.to_string()]
+            .into_iter()
+            .find(|candidate| self.named.contains_key(candidate))
+            .ok_or_else(|| Error::Schema(name.to_string(), "unknown type".to_string()))
+    }
+
+    fn union(
+        &mut self,
+        branches: &[Value],
+        namespace: Option<&str>,
+        holder: &Holder,
+    ) -> Result<TypeRef> {
+        let others = branches.iter().filter(|b| !is_null(b)).collect::<Vec<_>>();
+        let nullable = others.len() < branches.len();
+        let ty = match others.as_slice() {
+            [] => TypeRef::builtin("()"),
+            [single] => self.type_ref(single, namespace, holder, false)?,
+            _ => {
+                let field = RustCase::convert_lossy(holder.field, RustCase::Pascal);
+                let rust_name = self.reserve(&format!("{}{field}", holder.rust_name));
+                let index = self.types.len();
+                self.types.push(ModelType {
+                    name: rust_name.clone(),
+                    doc: None,
+                    kind: ModelKind::Struct(vec![]),
+                });
+                let mut variants = vec![];
+                for branch in others {
+                    let wire = self.branch_name(branch, namespace)?;
+                    variants.push((wire, self.type_ref(branch, namespace, holder, false)?));
+                }
+                self.types[index].kind = ModelKind::Tagged(None, variants);
+                TypeRef::Named(rust_name)
+            }
+        };
+        match nullable {
+            true => Ok(TypeRef::option(ty)),
+            false => Ok(ty),
+        }
+    }
+
+    /// The name a union branch is tagged with in Avro's JSON encoding, ex: `com.acme.Money`
+    fn branch_name(&self, branch: &Value, namespace: Option<&str>) -> Result<String> {
+        let object = match branch {
+            Value::String(name) if primitive(name).is_some() => return Ok(name.clone()),
+            Value::String(name) => return self.resolve(name, namespace),
+            Value::Object(object) => object,
+            _ => {
+                return Err(Error::Schema(
+                    branch.to_string(),
+                    "unions can't hold unions".to_string(),
+                ))
+            }
+        };
+        match object.get("type") {
+            Some(Value::String(kind))
+                if matches!(kind.as_str(), "record" | "error" | "enum" | "fixed") =>
+            {
+                let name = object
+                    .get("name")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let own = object.get("namespace").and_then(Value::as_str);
+                Ok(full_name(name, own.or(namespace)))
+            }
+            Some(Value::String(kind)) => Ok(kind.clone()),
+            Some(nested) => self.branch_name(nested, namespace),
+            None => Err(Error::Schema(
+                branch.to_string(),
+                "schema without a type".to_string(),
+            )),
+        }
+    }
+
+    /// Whether a value of the type can hold the holder without going through a `Vec` or a map
+    fn reaches(&self, from: &str, to: &str, seen: &mut BTreeSet<String>) -> bool {
+        if from == to {
+            return true;
+        }
+        if !seen.insert(from.to_string()) {
+            return false;
+        }
+        let Some(named) = self.named.get(from) else {
+            return false;
+        };
+        let mut refs = vec![];
+        for field in fields(named.schema) {
+            if let Some(ty) = field.get("type") {
+                self.direct_refs(ty, namespace_of(from), &mut refs);
+            }
+        }
+        refs.iter().any(|r| self.reaches(r, to, seen))
+    }
+
+    fn direct_refs(&self, schema: &Value, namespace: Option<&str>, refs: &mut Vec<String>) {
+        match schema {
+            Value::String(name) => refs.extend(self.resolve(name, namespace).ok()),
+            Value::Array(branches) => {
+                for branch in branches {
+                    self.direct_refs(branch, namespace, refs);
+                }
+            }
+            Value::Object(object) => match object.get("type") {
+                Some(Value::String(kind)) if matches!(kind.as_str(), "record" | "error") => {
+                    let name = object
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    let own = object.get("namespace").and_then(Value::as_str);
+                    refs.push(full_name(name, own.or(namespace)));
+                }
+                Some(nested @ (Value::Object(_) | Value::Array(_))) => {
+                    self.direct_refs(nested, namespace, refs)
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn reserve(&mut self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut counter = 2;
+        while !self.taken.insert(candidate.clone()) {
+            candidate = format!("{name}{counter}");
+            counter += 1;
+        }
+        candidate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::avro::Avro;
+
+    const EVENT: &str = r#"{
+  "type": "record",
+  "name": "OrderPlaced",
+  "namespace": "com.acme.events",
+  "doc": "Emitted once per order",
+  "fields": [
+    {"name": "orderId", "type": {"type": "string", "logicalType": "uuid"}},
+    {"name": "placedAt", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+    {"name": "note", "type": ["null", "string"], "default": null},
+    {"name": "status", "type": {"type": "enum", "name": "Status", "symbols": ["PLACED", "SHIPPED"]}},
+    {"name": "tags", "type": {"type": "array", "items": "string"}, "default": []},
+    {"name": "totals", "type": {"type": "map", "values": "double"}},
+    {"name": "payment", "type": [
+      {"type": "record", "name": "Card", "fields": [{"name": "number", "type": "string"}]},
+      "string"
+    ]},
+    {"name": "parent", "type": ["null", "OrderPlaced"]}
+  ]
+}"#;
+
+    #[test]
+    fn generates_records() {
+        let file = Avro::parse(EVENT)
+            .unwrap()
+            .map_logical_type("timestamp-millis", "Timestamp")
+            .into_file_builder("events")
+            .unwrap()
+            .format_file();
+        let expected = r#"use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+#[doc = "Emitted once per order"]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderPlaced {
+pub order_id: String,
+pub placed_at: Timestamp,
+#[serde(default)]
+pub note: Option<String>,
+pub status: Status,
+#[serde(default)]
+pub tags: Vec<String>,
+pub totals: HashMap<String, f64>,
+pub payment: OrderPlacedPayment,
+pub parent: Option<Box<OrderPlaced>>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OrderPlacedPayment {
+#[serde(rename = "com.acme.events.Card")]
+
+Card(Card),
+#[serde(rename = "string")]
+
+String(String),
+}
+#[derive(Copy, Eq, Hash, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Status {
+#[serde(rename = "PLACED")]
+
+Placed,
+#[serde(rename = "SHIPPED")]
+
+Shipped,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+pub number: String,
+}
+"#;
+        assert_eq!(file, expected);
+    }
+
+    #[test]
+    fn resolves_names_across_schemas() {
+        let money = r#"{"type": "fixed", "name": "com.acme.common.Money", "size": 16,
+            "logicalType": "decimal", "precision": 20, "scale": 2}"#;
+        let invoice = r#"{"type": "record", "name": "Invoice", "namespace": "com.acme.common",
+            "fields": [{"name": "total", "type": "Money"}, {"name": "paid", "type": "boolean"}]}"#;
+        let file = Avro::parse(invoice)
+            .unwrap()
+            .add_schema(money)
+            .unwrap()
+            .map_logical_type("decimal", "rust_decimal::Decimal")
+            .into_file_builder("billing")
+            .unwrap()
+            .format_file();
+        assert!(file.contains("pub struct Invoice {\npub total: Money,\npub paid: bool,\n}"));
+        assert!(file.contains("pub type Money = rust_decimal::Decimal;"));
+
+        let unknown = r#"{"type": "record", "name": "A", "fields": [{"name": "b", "type": "B"}]}"#;
+        let error = Avro::parse(unknown).unwrap().into_file_builder("a");
+        assert_eq!(
+            error.err().unwrap().to_string(),
+            "Invalid schema B: unknown type"
+        );
+    }
+
+    #[test]
+    fn keeps_variants_and_defaults_compilable() {
+        let schema = r#"{"type": "record", "name": "Payment", "fields": [
+            {"name": "amount", "type": [
+                {"type": "record", "name": "a.Money", "fields": [{"name": "units", "type": "long"}]},
+                {"type": "record", "name": "b.Money", "fields": [{"name": "cents", "type": "long"}]}
+            ]},
+            {"name": "at", "type": {"type": "long", "logicalType": "timestamp-millis"}, "default": 0},
+            {"name": "count", "type": "int", "default": 0}
+        ]}"#;
+        let file = Avro::parse(schema)
+            .unwrap()
+            .map_logical_type("timestamp-millis", "chrono::DateTime<chrono::Utc>")
+            .into_file_builder("payments")
+            .unwrap()
+            .format_file();
+        assert!(file.contains(
+            "#[serde(rename = \"a.Money\")]\n\nMoney(Money),\n\
+            #[serde(rename = \"b.Money\")]\n\nMoney2(Money2),\n"
+        ));
+        assert!(file.contains(
+            "pub at: chrono::DateTime<chrono::Utc>,\n#[serde(default)]\npub count: i32,\n"
+        ));
+    }
+
+    #[test]
+    fn keeps_clear_of_used_names() {
+        let schema = r#"{"type": "record", "name": "Option", "fields": [
+            {"name": "label", "type": ["null", "string"]},
+            {"name": "next", "type": ["null", "Option"]}
+        ]}"#;
+        let file = Avro::parse(schema)
+            .unwrap()
+            .into_file_builder("options")
+            .unwrap()
+            .format_file();
+        assert!(file.contains(
+            "pub struct Option2 {\npub label: Option<String>,\npub next: Option<Box<Option2>>,\n}\n"
+        ));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::errors::Result;
+use crate::output::{write_rendered, FileSystemSink, ModuleLayout, RenderedTree, WriteReport};
+use crate::structures::{Annotation, Module};
+use crate::{FileBuilder, ModuleBuilder};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Writes generated code from a build script into `OUT_DIR`.
+/// Everything ends up reachable from a single entry file, so the consuming crate only needs
+/// `include!(concat!(env!("OUT_DIR"), "/codegen.rs"));`
+pub struct BuildOutput {
+    out_dir: PathBuf,
+    entry_file: String,
+    inputs: Vec<PathBuf>,
+}
+
+impl BuildOutput {
+    /// Reads `OUT_DIR`, which cargo sets when running a build script
+    pub fn from_env() -> Result<Self> {
+        let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "OUT_DIR is not set, BuildOutput::from_env only works in a build script",
+            )
+        })?;
+        Ok(Self::new(out_dir))
+    }
+
+    pub fn new(out_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            out_dir: out_dir.into(),
+            entry_file: "codegen.rs".to_string(),
+            inputs: vec![],
+        }
+    }
+
+    /// Name of the file to `include!`, submodules are written to a directory named like it
+    pub fn set_entry_file(mut self, file_name: impl Into<String>) -> Self {
+        self.entry_file = file_name.into();
+        self
+    }
+
+    /// A file the generator reads, cargo reruns the build script when it changes.
+    /// Declaring any input turns off cargo's default of rerunning on every change in the package,
+    /// so declare `build.rs` too if the generator itself should trigger a rerun
+    pub fn declare_input(mut self, path: impl Into<PathBuf>) -> Self {
+        self.inputs.push(path.into());
+        self
+    }
+
+    pub fn rerun_if_changed_lines(&self) -> Vec<String> {
+        self.inputs
+            .iter()
+            .map(|p| format!("cargo:rerun-if-changed={}", p.display()))
+            .collect()
+    }
+
+    fn entry_stem(&self) -> &str {
+        self.entry_file
+            .strip_suffix(".rs")
+            .unwrap_or(&self.entry_file)
+    }
+
+    /// The root file's items go into the entry file, its modules are pulled in with absolute
+    /// `#[path]` attributes since `include!` resolves `mod` relative to the including file.
+    /// Returns the path of the entry file
+    pub fn write_module(self, mut module: ModuleBuilder) -> Result<PathBuf> {
+        module.check_module_names()?;
+        // Files loaded through `#[path]` resolve their own modules like `mod.rs` files do
+        module.layout = Some(ModuleLayout::ModRs);
+        module.inherit_settings();
+        let header = module.header.clone();
+        let with_header = |body: String| match &header {
+            Some(header) => header.apply(&body),
+            None => body,
+        };
+        let edition = module.edition();
+        let dir = PathBuf::from(self.entry_stem());
+        let mut rendered = RenderedTree::new();
+        let mut modules = vec![];
+        for file in &module.module_files {
+            let mod_name = module.escape_name(&file.builder.name, edition);
+            let path = dir.join(format!("{}.rs", mod_name.trim_start_matches("r#")));
+            modules.push(self.path_module(
+                file.visibility,
+                &mod_name,
+                file.annotations.clone(),
+                &path,
+            ));
+            rendered.insert(path, with_header(file.builder.format_file()));
+        }
+        for mut submod in std::mem::take(&mut module.submodules) {
+            let mod_name = module.escape_name(&submod.builder.mod_file.name, edition);
+            let sub_dir = dir.join(mod_name.trim_start_matches("r#"));
+            submod.builder.mod_file.name = "mod".to_string();
+            modules.push(self.path_module(
+                submod.visibility,
+                &mod_name,
+                submod.annotations.clone(),
+                &sub_dir.join("mod.rs"),
+            ));
+            rendered.extend(
+                submod
+                    .builder
+                    .render()
+                    .into_iter()
+                    .map(|(p, c)| (sub_dir.join(p), c)),
+            );
+        }
+        let entry = PathBuf::from(&self.entry_file);
+        rendered.insert(
+            entry.clone(),
+            with_header(module.mod_file.format_submodule(&modules)),
+        );
+        self.write(rendered)?;
+        Ok(self.out_dir.join(entry))
+    }
+
+    /// Writes a single file as the entry file, returns its path
+    pub fn write_file(self, file: FileBuilder) -> Result<PathBuf> {
+        let entry = PathBuf::from(&self.entry_file);
+        let mut rendered = RenderedTree::new();
+        rendered.insert(entry.clone(), file.format_file());
+        self.write(rendered)?;
+        Ok(self.out_dir.join(entry))
+    }
+
+    fn path_module(
+        &self,
+        visibility: crate::Visibility,
+        name: &str,
+        mut annotations: crate::Annotations,
+        relative: &Path,
+    ) -> Module {
+        let absolute = self.out_dir.join(relative);
+        annotations.annotations.push(Annotation::new(format!(
+            "path = {:?}",
+            absolute.to_string_lossy()
+        )));
+        Module::new(visibility, name, annotations)
+    }
+
+    /// Unchanged files are left alone so the consuming crate isn't rebuilt needlessly
+    fn write(&self, rendered: RenderedTree) -> Result<WriteReport> {
+        for line in self.rerun_if_changed_lines() {
+            println!("{line}");
+        }
+        write_rendered(
+            &mut FileSystemSink::new(&self.out_dir),
+            rendered,
+            &BTreeMap::new(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build_script::BuildOutput;
+    use crate::structures::visibility::Visibility;
+    use crate::{Annotations, FileBuilder, FunctionBuilder, ModuleBuilder, StructBuilder};
+
+    #[test]
+    fn writes_includable_entry() {
+        let out_dir = std::env::temp_dir().join(format!("codegen-rs-out-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out_dir);
+        let output = BuildOutput::new(&out_dir)
+            .declare_input("api.json")
+            .declare_input("build.rs");
+        assert_eq!(
+            vec![
+                "cargo:rerun-if-changed=api.json",
+                "cargo:rerun-if-changed=build.rs"
+            ],
+            output.rerun_if_changed_lines()
+        );
+        let module = ModuleBuilder::new(
+            FileBuilder::new("lib").add_function(FunctionBuilder::new("root_fn")),
+        )
+        .add_module_file(
+            Visibility::Public,
+            FileBuilder::new("models").add_struct(StructBuilder::new("Model")),
+            Annotations::empty(),
+        )
+        .add_submodule(
+            Visibility::Public,
+            ModuleBuilder::new(FileBuilder::new("api")).add_module_file(
+                Visibility::Public,
+                FileBuilder::new("v1"),
+                Annotations::empty(),
+            ),
+        );
+        let entry = output.write_module(module).unwrap();
+        assert_eq!(out_dir.join("codegen.rs"), entry);
+        let models = out_dir.join("codegen").join("models.rs");
+        let api = out_dir.join("codegen").join("api").join("mod.rs");
+        assert_eq!(
+            format!(
+                "#[path = {:?}]\npub mod models;\n#[path = {:?}]\npub mod api;\nfn root_fn() {{\n\n}}\n",
+                models.to_string_lossy(),
+                api.to_string_lossy()
+            ),
+            std::fs::read_to_string(&entry).unwrap()
+        );
+        assert_eq!(
+            "struct Model {\n}\n",
+            std::fs::read_to_string(models).unwrap()
+        );
+        assert_eq!("pub mod v1;\n", std::fs::read_to_string(api).unwrap());
+        assert!(out_dir.join("codegen").join("api").join("v1.rs").exists());
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
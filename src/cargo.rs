@@ -0,0 +1,635 @@
+use crate::errors::{Error, Result};
+use crate::output::{write_rendered, FileSystemSink, OutputSink, RenderedTree, WriteReport};
+use crate::util::casing::{is_valid_identifier, Edition};
+use crate::{GeneratedHeader, ModuleBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// A dependency entry in `Cargo.toml`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Dependency {
+    name: String,
+    version: Option<String>,
+    path: Option<String>,
+    git: Option<String>,
+    features: Vec<String>,
+    optional: bool,
+    default_features: bool,
+}
+
+impl Dependency {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            version: Some(version.into()),
+            ..Self::unversioned(name)
+        }
+    }
+
+    pub fn from_path(name: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::unversioned(name)
+        }
+    }
+
+    pub fn from_git(name: impl Into<String>, git: impl Into<String>) -> Self {
+        Self {
+            git: Some(git.into()),
+            ..Self::unversioned(name)
+        }
+    }
+
+    fn unversioned(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: None,
+            path: None,
+            git: None,
+            features: vec![],
+            optional: false,
+            default_features: true,
+        }
+    }
+
+    pub fn add_feature(mut self, feature: impl Into<String>) -> Self {
+        self.features.push(feature.into());
+        self
+    }
+
+    pub fn set_optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    pub fn disable_default_features(mut self) -> Self {
+        self.default_features = false;
+        self
+    }
+
+    fn format(&self) -> String {
+        let is_simple = self.path.is_none()
+            && self.git.is_none()
+            && self.features.is_empty()
+            && !self.optional
+            && self.default_features;
+        match (&self.version, is_simple) {
+            (Some(version), true) => format!("{} = {}\n", self.name, toml_string(version)),
+            _ => {
+                let mut keys = vec![];
+                if let Some(version) = &self.version {
+                    keys.push(format!("version = {}", toml_string(version)));
+                }
+                if let Some(path) = &self.path {
+                    keys.push(format!("path = {}", toml_string(path)));
+                }
+                if let Some(git) = &self.git {
+                    keys.push(format!("git = {}", toml_string(git)));
+                }
+                if !self.default_features {
+                    keys.push("default-features = false".to_string());
+                }
+                if !self.features.is_empty() {
+                    keys.push(format!("features = {}", toml_array(&self.features)));
+                }
+                if self.optional {
+                    keys.push("optional = true".to_string());
+                }
+                format!("{} = {{ {} }}\n", self.name, keys.join(", "))
+            }
+        }
+    }
+}
+
+/// A whole crate, `Cargo.toml` plus the module trees of its lib and bin targets
+pub struct CrateBuilder {
+    name: String,
+    version: String,
+    edition: Edition,
+    description: Option<String>,
+    license: Option<String>,
+    repository: Option<String>,
+    authors: Vec<String>,
+    dependencies: Vec<Dependency>,
+    dev_dependencies: Vec<Dependency>,
+    features: Vec<(String, Vec<String>)>,
+    lib: Option<ModuleBuilder>,
+    bins: Vec<(String, ModuleBuilder)>,
+}
+
+impl CrateBuilder {
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            edition: Edition::default(),
+            description: None,
+            license: None,
+            repository: None,
+            authors: vec![],
+            dependencies: vec![],
+            dev_dependencies: vec![],
+            features: vec![],
+            lib: None,
+            bins: vec![],
+        }
+    }
+
+    /// Also decides keyword escaping for every module in the crate
+    pub fn set_edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    pub fn set_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn set_license(mut self, license: impl Into<String>) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    pub fn set_repository(mut self, repository: impl Into<String>) -> Self {
+        self.repository = Some(repository.into());
+        self
+    }
+
+    pub fn add_author(mut self, author: impl Into<String>) -> Self {
+        self.authors.push(author.into());
+        self
+    }
+
+    pub fn add_dependency(mut self, dependency: Dependency) -> Self {
+        self.dependencies.push(dependency);
+        self
+    }
+
+    pub fn add_dev_dependency(mut self, dependency: Dependency) -> Self {
+        self.dev_dependencies.push(dependency);
+        self
+    }
+
+    /// `enables` are written as is, ex: `dep:serde` or `other-feature`
+    pub fn add_feature(
+        mut self,
+        name: impl Into<String>,
+        enables: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.features
+            .push((name.into(), enables.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// The root file of the module is written as `src/lib.rs` whatever its name
+    pub fn set_lib(mut self, root: ModuleBuilder) -> Self {
+        self.lib = Some(root);
+        self
+    }
+
+    /// A bin named like the crate is written as `src/main.rs`, others as `src/bin/{name}/main.rs`
+    pub fn add_bin(mut self, name: impl Into<String>, root: ModuleBuilder) -> Self {
+        self.bins.push((name.into(), root));
+        self
+    }
+
+    /// Every feature the lib and bins refer to
+    pub fn used_features(&self) -> BTreeSet<String> {
+        let mut features = BTreeSet::new();
+        for root in self
+            .lib
+            .iter()
+            .chain(self.bins.iter().map(|(_, root)| root))
+        {
+            root.collect_features(&mut features);
+        }
+        features
+    }
+
+    /// Adds an empty `[features]` entry for each used feature that isn't declared yet.
+    /// Optional dependencies already act as features and are left out
+    pub fn declare_used_features(mut self) -> Self {
+        for feature in self.used_features() {
+            let declared = self.features.iter().any(|(name, _)| *name == feature)
+                || self
+                    .dependencies
+                    .iter()
+                    .any(|dep| dep.optional && dep.name == feature);
+            if !declared {
+                self.features.push((feature, vec![]));
+            }
+        }
+        self
+    }
+
+    fn bin_dir(&self, bin_name: &str) -> PathBuf {
+        if bin_name == self.name {
+            PathBuf::from("src")
+        } else {
+            Path::new("src").join("bin").join(bin_name)
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        let context = format!("crate {}", self.name);
+        // Cargo only accepts ASCII in package and target names
+        let is_package_name = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        if !is_package_name(&self.name) {
+            return Err(Error::InvalidIdentifier(self.name.clone(), context));
+        }
+        for (bin_name, root) in &self.bins {
+            if !is_package_name(bin_name) {
+                return Err(Error::InvalidIdentifier(bin_name.clone(), context));
+            }
+            root.check_module_names()?;
+        }
+        if let Some(lib) = &self.lib {
+            lib.check_module_names()?;
+        }
+        let mut bin_names = BTreeSet::new();
+        for (bin_name, _) in &self.bins {
+            if !bin_names.insert(bin_name) {
+                return Err(Error::Duplicate(
+                    "bin".to_string(),
+                    bin_name.clone(),
+                    context,
+                ));
+            }
+        }
+        // The lib and a bin named like the crate share `src`, their modules can't overlap
+        let main = self
+            .bins
+            .iter()
+            .find(|(bin_name, _)| *bin_name == self.name);
+        if let (Some(lib), Some((_, main))) = (&self.lib, main) {
+            let lib_modules = lib.child_module_names(self.edition);
+            for module in main.child_module_names(self.edition) {
+                if lib_modules.contains(&module) {
+                    return Err(Error::Duplicate(
+                        "module".to_string(),
+                        module,
+                        format!("{context}, shared by the lib and the main bin"),
+                    ));
+                }
+            }
+        }
+        // The lib target is imported as the crate name with dashes replaced
+        let lib_name = self.name.replace('-', "_");
+        if self.lib.is_some() && !is_valid_identifier(&lib_name, self.edition) {
+            return Err(Error::InvalidIdentifier(lib_name, context));
+        }
+        Ok(())
+    }
+
+    pub fn format_manifest(&self) -> String {
+        let mut out = String::from("[package]\n");
+        let _ = writeln!(out, "name = {}", toml_string(&self.name));
+        let _ = writeln!(out, "version = {}", toml_string(&self.version));
+        let _ = writeln!(out, "edition = {}", toml_string(self.edition.as_str()));
+        if let Some(description) = &self.description {
+            let _ = writeln!(out, "description = {}", toml_string(description));
+        }
+        if let Some(license) = &self.license {
+            let _ = writeln!(out, "license = {}", toml_string(license));
+        }
+        if let Some(repository) = &self.repository {
+            let _ = writeln!(out, "repository = {}", toml_string(repository));
+        }
+        if !self.authors.is_empty() {
+            let _ = writeln!(out, "authors = {}", toml_array(&self.authors));
+        }
+        if self.lib.is_some() {
+            out.push_str("\n[lib]\npath = \"src/lib.rs\"\n");
+        }
+        for (bin_name, _) in &self.bins {
+            let path = self.bin_dir(bin_name).join("main.rs");
+            let _ = write!(
+                out,
+                "\n[[bin]]\nname = {}\npath = {}\n",
+                toml_string(bin_name),
+                toml_string(&path.to_string_lossy().replace('\\', "/"))
+            );
+        }
+        if !self.features.is_empty() {
+            out.push_str("\n[features]\n");
+            for (name, enables) in &self.features {
+                let _ = writeln!(out, "{} = {}", toml_key(name), toml_array(enables));
+            }
+        }
+        for (table, dependencies) in [
+            ("dependencies", &self.dependencies),
+            ("dev-dependencies", &self.dev_dependencies),
+        ] {
+            if !dependencies.is_empty() {
+                let _ = write!(out, "\n[{table}]\n");
+                for dependency in dependencies {
+                    out.push_str(&dependency.format());
+                }
+            }
+        }
+        out
+    }
+
+    /// Paths are relative to the crate root
+    pub fn render(self) -> RenderedTree {
+        self.render_guarded().0
+    }
+
+    fn render_guarded(self) -> (RenderedTree, BTreeMap<PathBuf, GeneratedHeader>) {
+        let mut rendered = RenderedTree::new();
+        let mut guards = BTreeMap::new();
+        rendered.insert(PathBuf::from("Cargo.toml"), self.format_manifest());
+        let edition = self.edition;
+        let mut targets = self
+            .bins
+            .iter()
+            .map(|(bin_name, _)| (self.bin_dir(bin_name), "main"))
+            .collect::<Vec<(PathBuf, &str)>>();
+        if self.lib.is_some() {
+            targets.insert(0, (PathBuf::from("src"), "lib"));
+        }
+        let roots = self
+            .lib
+            .into_iter()
+            .chain(self.bins.into_iter().map(|(_, root)| root));
+        for ((dir, file_name), mut root) in targets.into_iter().zip(roots) {
+            root.mod_file.name = file_name.to_string();
+            let root = root.set_edition(edition);
+            let (root_rendered, root_guards) = root.render_guarded();
+            rendered.extend(root_rendered.into_iter().map(|(p, c)| (dir.join(p), c)));
+            guards.extend(root_guards.into_iter().map(|(p, h)| (dir.join(p), h)));
+        }
+        (rendered, guards)
+    }
+
+    pub fn write_to_sink(self, sink: &mut impl OutputSink) -> Result<WriteReport> {
+        self.check()?;
+        let (rendered, guards) = self.render_guarded();
+        write_rendered(sink, rendered, &guards)
+    }
+
+    /// Writes the crate with `root` as the directory containing `Cargo.toml`
+    pub fn write_to_disk(self, root: impl AsRef<Path>) -> Result<WriteReport> {
+        self.write_to_sink(&mut FileSystemSink::new(root.as_ref()))
+    }
+}
+
+/// A workspace `Cargo.toml` with member crates in directories named after them
+pub struct WorkspaceBuilder {
+    members: Vec<CrateBuilder>,
+    resolver: String,
+}
+
+impl Default for WorkspaceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkspaceBuilder {
+    pub fn new() -> Self {
+        Self {
+            members: vec![],
+            resolver: "2".to_string(),
+        }
+    }
+
+    pub fn add_member(mut self, member: CrateBuilder) -> Self {
+        self.members.push(member);
+        self
+    }
+
+    pub fn set_resolver(mut self, resolver: impl Into<String>) -> Self {
+        self.resolver = resolver.into();
+        self
+    }
+
+    pub fn format_manifest(&self) -> String {
+        let names = self
+            .members
+            .iter()
+            .map(|m| m.name.clone())
+            .collect::<Vec<String>>();
+        format!(
+            "[workspace]\nmembers = {}\nresolver = {}\n",
+            toml_array(&names),
+            toml_string(&self.resolver)
+        )
+    }
+
+    pub fn render(self) -> RenderedTree {
+        self.render_guarded().0
+    }
+
+    fn render_guarded(self) -> (RenderedTree, BTreeMap<PathBuf, GeneratedHeader>) {
+        let mut rendered = RenderedTree::new();
+        let mut guards = BTreeMap::new();
+        rendered.insert(PathBuf::from("Cargo.toml"), self.format_manifest());
+        for member in self.members {
+            let dir = PathBuf::from(&member.name);
+            let (member_rendered, member_guards) = member.render_guarded();
+            rendered.extend(member_rendered.into_iter().map(|(p, c)| (dir.join(p), c)));
+            guards.extend(member_guards.into_iter().map(|(p, h)| (dir.join(p), h)));
+        }
+        (rendered, guards)
+    }
+
+    pub fn write_to_sink(self, sink: &mut impl OutputSink) -> Result<WriteReport> {
+        let mut seen = std::collections::HashSet::new();
+        for member in &self.members {
+            member.check()?;
+            if !seen.insert(member.name.as_str()) {
+                return Err(Error::Duplicate(
+                    "member".to_string(),
+                    member.name.clone(),
+                    "workspace".to_string(),
+                ));
+            }
+        }
+        let (rendered, guards) = self.render_guarded();
+        write_rendered(sink, rendered, &guards)
+    }
+
+    pub fn write_to_disk(self, root: impl AsRef<Path>) -> Result<WriteReport> {
+        self.write_to_sink(&mut FileSystemSink::new(root.as_ref()))
+    }
+}
+
+fn toml_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04X}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_string()
+    } else {
+        toml_string(key)
+    }
+}
+
+fn toml_array(values: &[String]) -> String {
+    format!(
+        "[{}]",
+        values
+            .iter()
+            .map(|v| toml_string(v))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cargo::{CrateBuilder, Dependency, WorkspaceBuilder};
+    use crate::errors::Error;
+    use crate::structures::visibility::Visibility;
+    use crate::{Annotations, Edition, FileBuilder, FunctionBuilder, InMemorySink, ModuleBuilder};
+    use std::path::Path;
+
+    fn sdk() -> CrateBuilder {
+        CrateBuilder::new("my-sdk", "0.1.0")
+            .set_edition(Edition::E2018)
+            .set_description("Generated \"sdk\"")
+            .set_license("MIT")
+            .add_dependency(Dependency::new("thiserror", "1.0"))
+            .add_dependency(
+                Dependency::new("serde", "1.0")
+                    .add_feature("derive")
+                    .set_optional(),
+            )
+            .add_feature("default", ["serde"])
+            .add_feature("serde", ["dep:serde"])
+            .set_lib(
+                ModuleBuilder::new(FileBuilder::new("anything")).add_module_file(
+                    Visibility::Public,
+                    FileBuilder::new("models").add_function(FunctionBuilder::new("async")),
+                    Annotations::empty(),
+                ),
+            )
+            .add_bin(
+                "my-sdk",
+                ModuleBuilder::new(
+                    FileBuilder::new("main").add_function(FunctionBuilder::new("main")),
+                ),
+            )
+            .add_bin("tool", ModuleBuilder::new(FileBuilder::new("tool")))
+    }
+
+    #[test]
+    fn renders_crate() {
+        let rendered = sdk().render();
+        let paths = rendered
+            .keys()
+            .map(|p| p.to_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "Cargo.toml",
+                "src/bin/tool/main.rs",
+                "src/lib.rs",
+                "src/main.rs",
+                "src/models.rs"
+            ],
+            paths
+        );
+        assert_eq!(
+            "[package]\nname = \"my-sdk\"\nversion = \"0.1.0\"\nedition = \"2018\"\n\
+            description = \"Generated \\\"sdk\\\"\"\nlicense = \"MIT\"\n\n\
+            [lib]\npath = \"src/lib.rs\"\n\n\
+            [[bin]]\nname = \"my-sdk\"\npath = \"src/main.rs\"\n\n\
+            [[bin]]\nname = \"tool\"\npath = \"src/bin/tool/main.rs\"\n\n\
+            [features]\ndefault = [\"serde\"]\nserde = [\"dep:serde\"]\n\n\
+            [dependencies]\nthiserror = \"1.0\"\n\
+            serde = { version = \"1.0\", features = [\"derive\"], optional = true }\n",
+            rendered[Path::new("Cargo.toml")]
+        );
+        assert_eq!("pub mod models;\n", rendered[Path::new("src/lib.rs")]);
+        // The crate edition drives escaping
+        assert_eq!(
+            "fn r#async() {\n\n}\n",
+            rendered[Path::new("src/models.rs")]
+        );
+    }
+
+    #[test]
+    fn renders_workspace() {
+        let rendered = WorkspaceBuilder::new()
+            .add_member(sdk())
+            .add_member(
+                CrateBuilder::new("other", "0.2.0")
+                    .set_lib(ModuleBuilder::new(FileBuilder::new("lib"))),
+            )
+            .render();
+        assert_eq!(
+            "[workspace]\nmembers = [\"my-sdk\", \"other\"]\nresolver = \"2\"\n",
+            rendered[Path::new("Cargo.toml")]
+        );
+        assert!(rendered.contains_key(Path::new("my-sdk/src/models.rs")));
+        assert!(rendered.contains_key(Path::new("other/src/lib.rs")));
+    }
+
+    #[test]
+    fn rejects_overlapping_targets() {
+        let models = || {
+            ModuleBuilder::new(FileBuilder::new("root")).add_module_file(
+                Visibility::Public,
+                FileBuilder::new("models"),
+                Annotations::empty(),
+            )
+        };
+        let error = CrateBuilder::new("app", "0.1.0")
+            .set_lib(models())
+            .add_bin("app", models())
+            .write_to_sink(&mut InMemorySink::new());
+        assert_eq!(
+            "Duplicate module \"models\" in crate app, shared by the lib and the main bin",
+            error.err().unwrap().to_string()
+        );
+        let error = CrateBuilder::new("app", "0.1.0")
+            .add_bin("tool", models())
+            .add_bin("tool", models())
+            .write_to_sink(&mut InMemorySink::new());
+        assert!(error.is_err());
+        let separate = CrateBuilder::new("app", "0.1.0")
+            .set_lib(models())
+            .add_bin("tool", models())
+            .write_to_sink(&mut InMemorySink::new());
+        assert!(separate.is_ok());
+    }
+
+    #[test]
+    fn rejects_non_ascii_names() {
+        let error = CrateBuilder::new("crâte", "0.1.0")
+            .set_lib(ModuleBuilder::new(FileBuilder::new("lib")))
+            .write_to_sink(&mut InMemorySink::new());
+        assert!(matches!(error, Err(Error::InvalidIdentifier(name, _)) if name == "crâte"));
+        let error = CrateBuilder::new("app", "0.1.0")
+            .add_bin("tööl", ModuleBuilder::new(FileBuilder::new("main")))
+            .write_to_sink(&mut InMemorySink::new());
+        assert!(matches!(error, Err(Error::InvalidIdentifier(name, _)) if name == "tööl"));
+    }
+}
@@ -0,0 +1,325 @@
+use crate::output::{read_manifest, DEFAULT_MANIFEST};
+use crate::{ModuleBuilder, Result};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+const CONTEXT_LINES: usize = 3;
+/// Above this many cells of the lcs table the changed region is shown as one replacement
+const MAX_LCS_CELLS: usize = 16_000_000;
+
+/// A file whose content on disk differs from what would be generated
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    /// Unified diff from the file on disk to the generated file
+    pub diff: String,
+}
+
+/// Result of comparing a rendered module tree against a directory, see `ModuleBuilder::check_against`
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CheckReport {
+    /// Generated but not on disk
+    pub missing: Vec<PathBuf>,
+    /// On disk and listed in the manifest of a tracked write, but no longer generated
+    pub extra: Vec<PathBuf>,
+    pub differing: Vec<FileDiff>,
+}
+
+impl CheckReport {
+    pub fn is_up_to_date(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.differing.is_empty()
+    }
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_up_to_date() {
+            return writeln!(f, "Generated code is up to date");
+        }
+        for path in &self.missing {
+            writeln!(f, "Missing {}", path.display())?;
+        }
+        for path in &self.extra {
+            writeln!(f, "Extra {}", path.display())?;
+        }
+        for diff in &self.differing {
+            writeln!(f, "Differs {}", diff.path.display())?;
+            f.write_str(&diff.diff)?;
+        }
+        Ok(())
+    }
+}
+
+impl ModuleBuilder {
+    /// Renders the tree in memory and compares it to what's below `root` without writing anything.
+    /// Files listed in the manifest `write_to_disk_tracked` keeps that wouldn't be generated
+    /// anymore count as extra
+    pub fn check_against(self, root: impl AsRef<Path>) -> Result<CheckReport> {
+        self.check_against_manifest(root, DEFAULT_MANIFEST)
+    }
+
+    /// Like `check_against` for trees written by a sink tracking the manifest `file_name`,
+    /// see `FileSystemSink::track_manifest`
+    pub fn check_against_manifest(
+        self,
+        root: impl AsRef<Path>,
+        file_name: &str,
+    ) -> Result<CheckReport> {
+        self.check_module_names()?;
+        let root = root.as_ref();
+        let rendered = self.render();
+        let mut report = CheckReport::default();
+        for (path, generated) in &rendered {
+            match std::fs::read_to_string(root.join(path)) {
+                Ok(existing) if &existing == generated => {}
+                Ok(existing) => report.differing.push(FileDiff {
+                    path: path.clone(),
+                    diff: unified_diff(&existing, generated, path),
+                }),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    report.missing.push(path.clone())
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        report.extra = read_manifest(&root.join(file_name))?
+            .into_iter()
+            .filter(|p| !rendered.contains_key(p) && root.join(p).is_file())
+            .collect();
+        report.extra.sort();
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Line based unified diff from `old` to `new`
+pub(crate) fn unified_diff(old: &str, new: &str, path: &Path) -> String {
+    let old_lines = old.lines().collect::<Vec<&str>>();
+    let new_lines = new.lines().collect::<Vec<&str>>();
+    let ops = diff_ops(&old_lines, &new_lines);
+    let display = path.to_string_lossy().replace('\\', "/");
+    let mut out = format!("--- a/{display}\n+++ b/{display}\n");
+    let mut ind = 0;
+    while ind < ops.len() {
+        if matches!(ops[ind], Op::Equal(..)) {
+            ind += 1;
+            continue;
+        }
+        // Extend the hunk while changes are close enough for their context to overlap
+        let start = ind.saturating_sub(CONTEXT_LINES);
+        let mut end = ind;
+        let mut equal_run = 0;
+        while end < ops.len() && equal_run <= CONTEXT_LINES * 2 {
+            if matches!(ops[end], Op::Equal(..)) {
+                equal_run += 1;
+            } else {
+                equal_run = 0;
+            }
+            end += 1;
+        }
+        let end = (end - equal_run + CONTEXT_LINES.min(equal_run)).min(ops.len());
+        write_hunk(&mut out, &ops[start..end], &old_lines, &new_lines);
+        ind = end;
+    }
+    out
+}
+
+fn write_hunk(out: &mut String, ops: &[Op], old_lines: &[&str], new_lines: &[&str]) {
+    let old_start = ops.iter().find_map(|op| match op {
+        Op::Equal(o, _) | Op::Delete(o) => Some(*o),
+        Op::Insert(_) => None,
+    });
+    let new_start = ops.iter().find_map(|op| match op {
+        Op::Equal(_, n) | Op::Insert(n) => Some(*n),
+        Op::Delete(_) => None,
+    });
+    let old_count = ops.iter().filter(|op| !matches!(op, Op::Insert(_))).count();
+    let new_count = ops.iter().filter(|op| !matches!(op, Op::Delete(_))).count();
+    out.push_str(&format!(
+        "@@ -{} +{} @@\n",
+        hunk_range(old_start, old_count, ops, true),
+        hunk_range(new_start, new_count, ops, false)
+    ));
+    for op in ops {
+        match op {
+            Op::Equal(o, _) => out.push_str(&format!(" {}\n", old_lines[*o])),
+            Op::Delete(o) => out.push_str(&format!("-{}\n", old_lines[*o])),
+            Op::Insert(n) => out.push_str(&format!("+{}\n", new_lines[*n])),
+        }
+    }
+}
+
+/// An empty range is written as the line before it, `0,0` at the start of a file
+fn hunk_range(start: Option<usize>, count: usize, ops: &[Op], old: bool) -> String {
+    match start {
+        Some(start) => format!("{},{count}", start + 1),
+        None => {
+            let before = ops
+                .iter()
+                .filter_map(|op| match (op, old) {
+                    (Op::Insert(n), true) => Some(*n),
+                    (Op::Delete(o), false) => Some(*o),
+                    _ => None,
+                })
+                .next()
+                .unwrap_or_default();
+            format!("{before},0")
+        }
+    }
+}
+
+fn diff_ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    let mut ops = (0..prefix).map(|i| Op::Equal(i, i)).collect::<Vec<Op>>();
+    if (old_mid.len() + 1) * (new_mid.len() + 1) > MAX_LCS_CELLS {
+        ops.extend((0..old_mid.len()).map(|i| Op::Delete(prefix + i)));
+        ops.extend((0..new_mid.len()).map(|i| Op::Insert(prefix + i)));
+    } else {
+        // lcs[i][j] is the longest common subsequence of old_mid[i..] and new_mid[j..]
+        let width = new_mid.len() + 1;
+        let mut lcs = vec![0u32; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() || j < new_mid.len() {
+            if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+                ops.push(Op::Equal(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if j < new_mid.len()
+                && (i == old_mid.len() || lcs[i * width + j + 1] > lcs[(i + 1) * width + j])
+            {
+                ops.push(Op::Insert(prefix + j));
+                j += 1;
+            } else {
+                ops.push(Op::Delete(prefix + i));
+                i += 1;
+            }
+        }
+    }
+    let old_tail = old.len() - suffix;
+    let new_tail = new.len() - suffix;
+    ops.extend((0..suffix).map(|i| Op::Equal(old_tail + i, new_tail + i)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::check::unified_diff;
+    use crate::errors::Error;
+    use crate::structures::visibility::Visibility;
+    use crate::{Annotations, FileBuilder, ModuleBuilder, StructBuilder, DEFAULT_MANIFEST};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn diffs_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\nk\n";
+        assert_eq!(
+            "--- a/x.rs\n+++ b/x.rs\n@@ -2,9 +2,10 @@\n b\n c\n d\n-e\n+E\n f\n g\n h\n i\n j\n+k\n",
+            unified_diff(old, new, Path::new("x.rs"))
+        );
+        assert_eq!(
+            "--- a/x.rs\n+++ b/x.rs\n@@ -0,0 +1,1 @@\n+a\n",
+            unified_diff("", "a\n", Path::new("x.rs"))
+        );
+    }
+
+    #[test]
+    fn checks_against_disk() {
+        let tree = || {
+            ModuleBuilder::new(FileBuilder::new("lib")).add_module_file(
+                Visibility::Public,
+                FileBuilder::new("models").add_struct(StructBuilder::new("Model")),
+                Annotations::empty(),
+            )
+        };
+        let root = std::env::temp_dir().join(format!("codegen-rs-check-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let report = tree().check_against(&root).unwrap();
+        assert_eq!(
+            vec![PathBuf::from("lib.rs"), PathBuf::from("models.rs")],
+            report.missing
+        );
+        tree().write_to_disk(&root).unwrap();
+        assert!(tree().check_against(&root).unwrap().is_up_to_date());
+        std::fs::write(root.join("models.rs"), "struct Old {\n}\n").unwrap();
+        std::fs::write(root.join("leftover.rs"), "").unwrap();
+        let report = tree().check_against(&root).unwrap();
+        assert!(report.missing.is_empty());
+        assert!(report.extra.is_empty());
+        assert_eq!(1, report.differing.len());
+        assert_eq!(
+            "--- a/models.rs\n+++ b/models.rs\n@@ -1,2 +1,2 @@\n-struct Old {\n+struct Model {\n }\n",
+            report.differing[0].diff
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn reports_tracked_files_as_extra() {
+        let tree = |extra: bool| {
+            let tree = ModuleBuilder::new(FileBuilder::new("lib")).add_module_file(
+                Visibility::Public,
+                FileBuilder::new("models"),
+                Annotations::empty(),
+            );
+            match extra {
+                true => tree.add_module_file(
+                    Visibility::Public,
+                    FileBuilder::new("old"),
+                    Annotations::empty(),
+                ),
+                false => tree,
+            }
+        };
+        let root =
+            std::env::temp_dir().join(format!("codegen-rs-check-extra-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        tree(true).write_to_disk_tracked(&root).unwrap();
+        std::fs::write(root.join("handwritten.rs"), "").unwrap();
+        let report = tree(false).check_against(&root).unwrap();
+        assert_eq!(vec![PathBuf::from("old.rs")], report.extra);
+        assert_eq!(1, report.differing.len());
+        // The manifest of another name isn't read
+        std::fs::rename(root.join(DEFAULT_MANIFEST), root.join("generated.list")).unwrap();
+        let report = tree(false).check_against(&root).unwrap();
+        assert!(report.extra.is_empty());
+        let report = tree(false)
+            .check_against_manifest(&root, "generated.list")
+            .unwrap();
+        assert_eq!(vec![PathBuf::from("old.rs")], report.extra);
+
+        let clashing = tree(false).add_module_file(
+            Visibility::Public,
+            FileBuilder::new("models"),
+            Annotations::empty(),
+        );
+        assert!(matches!(
+            clashing.check_against(&root),
+            Err(Error::Duplicate(..))
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
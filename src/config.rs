@@ -0,0 +1,857 @@
+use crate::errors::{Error, Result};
+use crate::structures::gen_enum::NamedComponentSignature;
+use crate::structures::method::Argument;
+use crate::structures::visibility::Visibility;
+use crate::structures::{
+    Annotation, Annotations, ComponentSignature, Import, Ownership, RustType, Signature,
+};
+use crate::util::casing::{singular, RustCase};
+use crate::{FileBuilder, ImplBuilder, MethodBuilder, StructBuilder};
+use std::collections::BTreeSet;
+use std::path::Path;
+use toml_edit::{ImDocument, Item, RawString};
+
+/// Generates config structs from an annotated sample config, so the structs can't drift from
+/// the sample. Every table becomes a struct whose `Default` is the sample and whose
+/// `#[serde(default)]` fills keys missing from a loaded file with it. Tables in a list share
+/// a struct, its `Default` is derived since every table has its own values. The comment lines
+/// right above a key, or after its value, become its doc comment.
+///
+/// Every struct gets an `apply_env` method overriding its fields with environment variables
+/// named after their path, ex: `APP_DATABASE_URL` for `database.url` with the prefix `APP`.
+/// Lists are read comma separated, lists of tables aren't overridden.
+/// The generated code needs `serde` with `derive`
+pub struct ConfigSample {
+    root_name: String,
+    format: ConfigFormat,
+    source: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+}
+
+impl ConfigSample {
+    /// The root struct is named `root_name`
+    pub fn new(
+        root_name: impl Into<String>,
+        format: ConfigFormat,
+        source: impl Into<String>,
+    ) -> Self {
+        Self {
+            root_name: root_name.into(),
+            format,
+            source: source.into(),
+        }
+    }
+
+    pub fn toml(root_name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self::new(root_name, ConfigFormat::Toml, source)
+    }
+
+    pub fn yaml(root_name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self::new(root_name, ConfigFormat::Yaml, source)
+    }
+
+    /// The format is picked from the extension, `.toml`, `.yaml` or `.yml`
+    pub fn from_file(root_name: impl Into<String>, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => {
+                return Err(Error::Schema(
+                    path.display().to_string(),
+                    "unknown config format".to_string(),
+                ))
+            }
+        };
+        Ok(Self::new(root_name, format, std::fs::read_to_string(path)?))
+    }
+
+    pub fn into_file_builder(self, file_name: impl Into<String>) -> Result<FileBuilder> {
+        let entries = match self.format {
+            ConfigFormat::Toml => read_toml(&self.root_name, &self.source)?,
+            ConfigFormat::Yaml => read_yaml(&self.root_name, &self.source)?,
+        };
+        let mut emitter = Emitter {
+            structs: vec![],
+            taken: BTreeSet::from(RESERVED.map(String::from)),
+        };
+        let root_name =
+            emitter.reserve(&RustCase::convert_lossy(&self.root_name, RustCase::Pascal));
+        emitter.table(root_name, &entries)?;
+        let mut fb =
+            FileBuilder::new(file_name).add_import(Import::spec("serde::{Deserialize, Serialize}"));
+        for config_struct in &emitter.structs {
+            fb = emitter.write_struct(fb, config_struct);
+        }
+        Ok(fb)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Sample {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Vec<Sample>),
+    Table(Vec<Entry>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    key: String,
+    value: Sample,
+    doc: Option<String>,
+}
+
+fn read_toml(root_name: &str, source: &str) -> Result<Vec<Entry>> {
+    let doc = ImDocument::parse(source)
+        .map_err(|e| Error::Schema(root_name.to_string(), e.to_string()))?;
+    toml_table(source, doc.as_table())
+}
+
+fn toml_table(source: &str, table: &toml_edit::Table) -> Result<Vec<Entry>> {
+    table
+        .iter()
+        .map(|(key, item)| {
+            let header = match item {
+                Item::Table(table) => Some(table.decor()),
+                Item::ArrayOfTables(tables) => tables.get(0).map(toml_edit::Table::decor),
+                _ => None,
+            };
+            let doc = table
+                .key(key)
+                .and_then(|k| comment_block(raw(source, k.leaf_decor().prefix())))
+                .or_else(|| header.and_then(|decor| comment_block(raw(source, decor.prefix()))))
+                .or_else(|| {
+                    let suffix = item.as_value()?.decor().suffix();
+                    trailing_comment(raw(source, suffix))
+                });
+            Ok(Entry {
+                key: key.to_string(),
+                value: toml_item(source, key, item)?,
+                doc,
+            })
+        })
+        .collect()
+}
+
+fn toml_item(source: &str, key: &str, item: &Item) -> Result<Sample> {
+    match item {
+        Item::None => Ok(Sample::Null),
+        Item::Value(value) => toml_value(key, value),
+        Item::Table(table) => Ok(Sample::Table(toml_table(source, table)?)),
+        Item::ArrayOfTables(tables) => Ok(Sample::List(
+            tables
+                .iter()
+                .map(|table| toml_table(source, table).map(Sample::Table))
+                .collect::<Result<_>>()?,
+        )),
+    }
+}
+
+fn toml_value(key: &str, value: &toml_edit::Value) -> Result<Sample> {
+    Ok(match value {
+        toml_edit::Value::String(s) => Sample::Str(s.value().clone()),
+        toml_edit::Value::Integer(i) => Sample::Int(*i.value()),
+        toml_edit::Value::Float(f) => Sample::Float(*f.value()),
+        toml_edit::Value::Boolean(b) => Sample::Bool(*b.value()),
+        toml_edit::Value::Datetime(_) => {
+            return Err(Error::Schema(
+                key.to_string(),
+                "datetimes are not supported, quote them".to_string(),
+            ))
+        }
+        toml_edit::Value::Array(array) => Sample::List(
+            array
+                .iter()
+                .map(|item| toml_value(key, item))
+                .collect::<Result<_>>()?,
+        ),
+        toml_edit::Value::InlineTable(table) => Sample::Table(
+            table
+                .iter()
+                .map(|(key, value)| {
+                    Ok(Entry {
+                        key: key.to_string(),
+                        value: toml_value(key, value)?,
+                        doc: None,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
+
+/// The text of a decor, spans point into the source
+fn raw<'s>(source: &'s str, raw: Option<&'s RawString>) -> &'s str {
+    raw.and_then(|raw| raw.as_str().or_else(|| source.get(raw.span()?)))
+        .unwrap_or_default()
+}
+
+/// The comment lines right above a key, a blank line ends a block.
+/// The last line is the indentation of the key itself
+fn comment_block(prefix: &str) -> Option<String> {
+    let mut lines = prefix.split('\n').collect::<Vec<_>>();
+    lines.pop();
+    let mut block = vec![];
+    for line in lines {
+        match line.trim().strip_prefix('#') {
+            Some(comment) => block.push(comment.trim()),
+            None => block.clear(),
+        }
+    }
+    Some(block.join("\n")).filter(|block| !block.is_empty())
+}
+
+fn trailing_comment(suffix: &str) -> Option<String> {
+    let comment = suffix.trim().strip_prefix('#')?.trim();
+    Some(comment.to_string()).filter(|comment| !comment.is_empty())
+}
+
+fn read_yaml(root_name: &str, source: &str) -> Result<Vec<Entry>> {
+    let value: serde_yaml::Value = serde_yaml::from_str(source)
+        .map_err(|e| Error::Schema(root_name.to_string(), e.to_string()))?;
+    let comments = yaml_comments(source);
+    match yaml_value(&value, &[], &comments)? {
+        Sample::Table(entries) => Ok(entries),
+        _ => Err(Error::Schema(
+            root_name.to_string(),
+            "the config isn't a mapping".to_string(),
+        )),
+    }
+}
+
+fn yaml_value(
+    value: &serde_yaml::Value,
+    path: &[String],
+    comments: &[(Vec<String>, String)],
+) -> Result<Sample> {
+    use serde_yaml::Value;
+    Ok(match value {
+        Value::Null => Sample::Null,
+        Value::Bool(b) => Sample::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Sample::Int(i),
+            None => Sample::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => Sample::Str(s.clone()),
+        Value::Sequence(items) => Sample::List(
+            items
+                .iter()
+                .map(|item| yaml_value(item, path, comments))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Mapping(mapping) => Sample::Table(
+            mapping
+                .iter()
+                .map(|(key, value)| {
+                    let key = key.as_str().ok_or_else(|| {
+                        Error::Schema(path.join("."), "keys must be strings".to_string())
+                    })?;
+                    let mut path = path.to_vec();
+                    path.push(key.to_string());
+                    let doc = comments
+                        .iter()
+                        .find(|(commented, _)| *commented == path)
+                        .map(|(_, doc)| doc.clone());
+                    Ok(Entry {
+                        key: key.to_string(),
+                        value: yaml_value(value, &path, comments)?,
+                        doc,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        ),
+        Value::Tagged(tagged) => yaml_value(&tagged.value, path, comments)?,
+    })
+}
+
+/// The comments of the keys by their path, the items of a list share their keys' paths.
+/// The YAML parser drops comments, so they're matched to keys by indentation
+fn yaml_comments(source: &str) -> Vec<(Vec<String>, String)> {
+    let mut comments = vec![];
+    let mut block = vec![];
+    let mut parents: Vec<(usize, String)> = vec![];
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        let mut indent = line.len() - trimmed.len();
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            block.push(comment.trim().to_string());
+            continue;
+        }
+        let mut rest = trimmed;
+        while let Some(item) = rest.strip_prefix("- ") {
+            indent += 2 + item.len() - item.trim_start().len();
+            rest = item.trim_start();
+        }
+        let key = rest
+            .split_once(':')
+            .filter(|(_, value)| value.is_empty() || value.starts_with(' '))
+            .map(|(key, value)| (key.trim().trim_matches(['"', '\'']), value));
+        let Some((key, value)) = key else {
+            block.clear();
+            continue;
+        };
+        parents.retain(|(parent, _)| *parent < indent);
+        let mut path = parents.iter().map(|(_, k)| k.clone()).collect::<Vec<_>>();
+        path.push(key.to_string());
+        let trailing = value
+            .split_once(" #")
+            .filter(|(value, _)| !value.contains(['"', '\'']))
+            .and_then(|(_, comment)| trailing_comment(&format!("#{comment}")));
+        match block.is_empty() {
+            false => comments.push((path, block.join("\n"))),
+            true => comments.extend(trailing.map(|comment| (path, comment))),
+        }
+        block.clear();
+        parents.push((indent, key.to_string()));
+    }
+    comments
+}
+
+/// How a field is read from an environment variable
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Env {
+    Parsed,
+    Optional,
+    List,
+    Nested,
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+struct ConfigField {
+    key: String,
+    rust_name: String,
+    ty: String,
+    default: String,
+    env: Env,
+    doc: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ConfigStruct {
+    name: String,
+    fields: Vec<ConfigField>,
+    /// The `Default` is the sample rather than derived
+    sampled: bool,
+}
+
+/// Names the generated code refers to, a struct named after one would shadow it
+const RESERVED: [&str; 11] = [
+    "Default",
+    "Deserialize",
+    "Err",
+    "None",
+    "Ok",
+    "Option",
+    "Result",
+    "Serialize",
+    "Some",
+    "String",
+    "Vec",
+];
+
+struct Emitter {
+    structs: Vec<ConfigStruct>,
+    taken: BTreeSet<String>,
+}
+
+impl Emitter {
+    /// Structs are listed before the ones nested in them
+    fn table(&mut self, name: String, entries: &[Entry]) -> Result<()> {
+        let index = self.structs.len();
+        self.structs.push(ConfigStruct {
+            name,
+            fields: vec![],
+            sampled: true,
+        });
+        let mut fields = vec![];
+        for entry in entries {
+            let (ty, env) = self.type_of(&entry.key, &entry.value)?;
+            let default = match &entry.value {
+                Sample::Table(_) => format!("{ty}::default()"),
+                value => self.expr(value, &ty),
+            };
+            fields.push(ConfigField {
+                key: entry.key.clone(),
+                rust_name: RustCase::convert_lossy(&entry.key, RustCase::Snake),
+                ty,
+                default,
+                env,
+                doc: entry.doc.clone(),
+            });
+        }
+        self.structs[index].fields = fields;
+        Ok(())
+    }
+
+    fn type_of(&mut self, key: &str, sample: &Sample) -> Result<(String, Env)> {
+        Ok(match sample {
+            Sample::Null => ("Option<String>".to_string(), Env::Optional),
+            Sample::Bool(_) => ("bool".to_string(), Env::Parsed),
+            Sample::Int(_) => ("i64".to_string(), Env::Parsed),
+            Sample::Float(_) => ("f64".to_string(), Env::Parsed),
+            Sample::Str(_) => ("String".to_string(), Env::Parsed),
+            Sample::Table(entries) => {
+                let name = self.reserve(&RustCase::convert_lossy(key, RustCase::Pascal));
+                self.table(name.clone(), entries)?;
+                (name, Env::Nested)
+            }
+            Sample::List(items) => {
+                let (item, env) = self.item_type(key, items)?;
+                (format!("Vec<{item}>"), env)
+            }
+        })
+    }
+
+    /// Tables in a list share one struct with all of their keys, integers mixed with floats
+    /// are floats
+    fn item_type(&mut self, key: &str, items: &[Sample]) -> Result<(String, Env)> {
+        let mixed = || Error::Schema(key.to_string(), "list of mixed types".to_string());
+        if items.iter().all(|item| matches!(item, Sample::Table(_))) && !items.is_empty() {
+            let mut merged: Vec<Entry> = vec![];
+            let mut nullable = BTreeSet::new();
+            for item in items {
+                if let Sample::Table(entries) = item {
+                    merge(key, &mut merged, entries, &[], &mut nullable)?;
+                }
+            }
+            let name = singular(&RustCase::convert_lossy(key, RustCase::Pascal));
+            let name = self.reserve(&name);
+            let first = self.structs.len();
+            self.table(name.clone(), &merged)?;
+            // The merged values mix the tables, missing keys of one would take another's
+            for config_struct in &mut self.structs[first..] {
+                config_struct.sampled = false;
+            }
+            // Nested keys first, their struct is found through the parent's field type
+            for path in nullable.iter().rev() {
+                let Some((last, parents)) = path.split_last() else {
+                    continue;
+                };
+                let mut struct_name = name.clone();
+                for parent in parents {
+                    let field = self
+                        .structs
+                        .iter()
+                        .find(|s| s.name == struct_name)
+                        .and_then(|s| s.fields.iter().find(|f| &f.key == parent));
+                    let Some(field) = field else {
+                        break;
+                    };
+                    struct_name = field
+                        .ty
+                        .strip_prefix("Option<")
+                        .and_then(|t| t.strip_suffix('>'))
+                        .unwrap_or(&field.ty)
+                        .to_string();
+                }
+                let config_struct = self.structs.iter_mut().find(|s| s.name == struct_name);
+                let field =
+                    config_struct.and_then(|s| s.fields.iter_mut().find(|f| &f.key == last));
+                if let Some(field) = field.filter(|f| !f.ty.starts_with("Option<")) {
+                    field.ty = format!("Option<{}>", field.ty);
+                    field.default = format!("Some({})", field.default);
+                    field.env = Env::Optional;
+                }
+            }
+            return Ok((name, Env::Skipped));
+        }
+        let mut item_type: Option<(String, Env)> = None;
+        for item in items {
+            let (ty, env) = match item {
+                Sample::List(nested) => {
+                    let (ty, _) = self.item_type(key, nested)?;
+                    (format!("Vec<{ty}>"), Env::Skipped)
+                }
+                Sample::Table(_) | Sample::Null => return Err(mixed()),
+                scalar => (self.type_of(key, scalar)?.0, Env::List),
+            };
+            item_type = match item_type {
+                Some((seen, env)) if seen == ty => Some((seen, env)),
+                Some((seen, env)) if is_number(&seen) && is_number(&ty) => {
+                    Some(("f64".to_string(), env))
+                }
+                Some(_) => return Err(mixed()),
+                None => Some((ty, env)),
+            };
+        }
+        Ok(item_type.unwrap_or_else(|| ("String".to_string(), Env::List)))
+    }
+
+    /// An expression building the sample value
+    fn expr(&self, sample: &Sample, ty: &str) -> String {
+        if let Some(inner) = ty.strip_prefix("Option<").and_then(|t| t.strip_suffix('>')) {
+            return match sample {
+                Sample::Null => "None".to_string(),
+                sample => format!("Some({})", self.expr(sample, inner)),
+            };
+        }
+        match sample {
+            Sample::Null => "None".to_string(),
+            Sample::Bool(b) => b.to_string(),
+            Sample::Int(i) if ty == "f64" => float_literal(*i as f64),
+            Sample::Int(i) => i.to_string(),
+            Sample::Float(f) => float_literal(*f),
+            Sample::Str(s) => format!("String::from({s:?})"),
+            Sample::List(items) => {
+                let inner = ty
+                    .strip_prefix("Vec<")
+                    .and_then(|t| t.strip_suffix('>'))
+                    .unwrap_or(ty);
+                let items = items
+                    .iter()
+                    .map(|item| self.expr(item, inner))
+                    .collect::<Vec<_>>();
+                format!("vec![{}]", items.join(", "))
+            }
+            Sample::Table(entries) => {
+                let fields = self
+                    .structs
+                    .iter()
+                    .find(|s| s.name == ty)
+                    .map(|s| s.fields.as_slice())
+                    .unwrap_or_default();
+                let mut values = entries
+                    .iter()
+                    .filter_map(|entry| {
+                        let field = fields.iter().find(|f| f.key == entry.key)?;
+                        let value = self.expr(&entry.value, &field.ty);
+                        Some(format!("{}: {value}", field.rust_name))
+                    })
+                    .collect::<Vec<_>>();
+                if values.len() < fields.len() {
+                    values.push("..Default::default()".to_string());
+                }
+                format!("{ty} {{ {} }}", values.join(", "))
+            }
+        }
+    }
+
+    fn write_struct(&self, fb: FileBuilder, config_struct: &ConfigStruct) -> FileBuilder {
+        let derives = match config_struct.sampled {
+            true => "derive(Debug, Clone, PartialEq, Serialize, Deserialize)",
+            false => "derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)",
+        };
+        let mut sb = StructBuilder::new(&config_struct.name)
+            .set_visibility(Visibility::Public)
+            .add_simple_annotation(derives)
+            .add_simple_annotation("serde(default)");
+        let mut defaults = vec![];
+        let mut overrides = vec![];
+        for field in &config_struct.fields {
+            let mut annotations = vec![];
+            if let Some(doc) = &field.doc {
+                annotations.push(Annotation::new(format!("doc = {doc:?}")));
+            }
+            let name = field.rust_name.trim_start_matches("r#");
+            if name != field.key {
+                annotations.push(Annotation::new(format!("serde(rename = {:?})", field.key)));
+            }
+            sb = sb.add_field_with_annotations(
+                Visibility::Public,
+                NamedComponentSignature::new_simple_type(
+                    &field.rust_name,
+                    RustType::in_scope(&field.ty),
+                ),
+                Annotations::new(annotations),
+            );
+            defaults.push(format!("        {}: {},", field.rust_name, field.default));
+            overrides.extend(env_override(field));
+        }
+        overrides.push("    Ok(())".to_string());
+        let self_type = Signature::simple(RustType::in_scope(&config_struct.name));
+        let default = ImplBuilder::new(self_type.clone())
+            .implement_for(Signature::simple(RustType::in_scope("Default")))
+            .add_method(
+                MethodBuilder::new("default")
+                    .set_return_type(simple_signature("Self"))
+                    .set_body(format!("    Self {{\n{}\n    }}", defaults.join("\n"))),
+            );
+        let apply_env = ImplBuilder::new(self_type).add_method(
+            MethodBuilder::new("apply_env")
+                .add_simple_annotation(
+                    "doc = \"Overrides fields with the `{prefix}_{FIELD}` environment variables\"",
+                )
+                .set_visibility(Visibility::Public)
+                .set_self_ownership(Ownership::MutRef)
+                .add_argument(Argument::new(
+                    Ownership::Ref,
+                    NamedComponentSignature::new_simple_type("prefix", RustType::in_scope("str")),
+                ))
+                .set_return_type(simple_signature("Result<(), String>"))
+                .set_body(overrides.join("\n")),
+        );
+        let fb = fb.add_struct(sb);
+        match config_struct.sampled {
+            true => fb.add_impl(default).add_impl(apply_env),
+            false => fb.add_impl(apply_env),
+        }
+    }
+
+    fn reserve(&mut self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut counter = 2;
+        while !self.taken.insert(candidate.clone()) {
+            candidate = format!("{name}{counter}");
+            counter += 1;
+        }
+        candidate
+    }
+}
+
+/// Merges the keys of tables sharing a struct, nested tables key by key. `nullable` collects
+/// the paths of keys that are null in some of them
+fn merge(
+    key: &str,
+    merged: &mut Vec<Entry>,
+    entries: &[Entry],
+    path: &[String],
+    nullable: &mut BTreeSet<Vec<String>>,
+) -> Result<()> {
+    let mixed = || Error::Schema(key.to_string(), "list of mixed types".to_string());
+    for entry in entries {
+        let mut entry_path = path.to_vec();
+        entry_path.push(entry.key.clone());
+        if entry.value == Sample::Null {
+            nullable.insert(entry_path.clone());
+        }
+        let m = match merged.iter_mut().position(|m| m.key == entry.key) {
+            Some(index) => &mut merged[index],
+            None => {
+                merged.push(Entry {
+                    value: Sample::Null,
+                    ..entry.clone()
+                });
+                merged.last_mut().expect("pushed above")
+            }
+        };
+        m.doc = m.doc.take().or_else(|| entry.doc.clone());
+        // Walked like the tables after it, for the nulls nested in it
+        if m.value == Sample::Null && matches!(entry.value, Sample::Table(_)) {
+            m.value = Sample::Table(vec![]);
+        }
+        match (&mut m.value, &entry.value) {
+            (Sample::Table(seen), Sample::Table(more)) => {
+                merge(key, seen, more, &entry_path, nullable)?
+            }
+            (Sample::Null, _) | (Sample::Int(_), Sample::Float(_)) => m.value = entry.value.clone(),
+            (_, Sample::Null) | (Sample::Float(_), Sample::Int(_)) => {}
+            (seen, value) if std::mem::discriminant(&*seen) != std::mem::discriminant(value) => {
+                return Err(mixed());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn env_override(field: &ConfigField) -> Option<String> {
+    let name = &field.rust_name;
+    let var = format!(
+        "{{prefix}}_{}",
+        name.trim_start_matches("r#").to_uppercase()
+    );
+    let error = format!(".map_err(|e| format!(\"{var}: {{e}}\"))?");
+    let assign = match field.env {
+        Env::Parsed => format!("self.{name} = value.parse(){error};"),
+        Env::Optional => format!("self.{name} = Some(value.parse(){error});"),
+        Env::List => format!(
+            "self.{name} = value\n            .split(',')\n            \
+            .map(|v| v.trim().parse())\n            .collect::<Result<_, _>>()\n            \
+            {error};"
+        ),
+        Env::Nested => return Some(format!("    self.{name}.apply_env(&format!(\"{var}\"))?;")),
+        Env::Skipped => return None,
+    };
+    Some(format!(
+        "    if let Ok(value) = std::env::var(format!(\"{var}\")) {{\n        {assign}\n    }}"
+    ))
+}
+
+fn is_number(ty: &str) -> bool {
+    matches!(ty, "i64" | "f64")
+}
+
+fn float_literal(value: f64) -> String {
+    match value {
+        v if v.is_nan() => "f64::NAN".to_string(),
+        v if v == f64::INFINITY => "f64::INFINITY".to_string(),
+        v if v == f64::NEG_INFINITY => "f64::NEG_INFINITY".to_string(),
+        v => format!("{v:?}"),
+    }
+}
+
+fn simple_signature(rust_type: &str) -> ComponentSignature {
+    ComponentSignature::Signature(Signature::simple(RustType::in_scope(rust_type)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ConfigSample;
+
+    const SAMPLE: &str = r#"# Service name
+name = "api"
+port = 8080 # Port to listen on
+hosts = ["a", "b"]
+ratio = 1
+
+# Primary database
+[database]
+# Connection string
+url = "postgres://localhost/app"
+pool-size = 10
+
+[[servers]]
+host = "alpha"
+weight = 0.5
+
+[[servers]]
+host = "beta"
+"#;
+
+    #[test]
+    fn generates_toml_config() {
+        let file = ConfigSample::toml("config", SAMPLE)
+            .into_file_builder("config")
+            .unwrap()
+            .format_file();
+        assert!(file.contains(
+            "#[serde(default)]\npub struct Config {\n#[doc = \"Service name\"]\npub name: String,\n\
+            #[doc = \"Port to listen on\"]\npub port: i64,\n"
+        ));
+        assert!(file.contains("#[doc = \"Primary database\"]\npub database: Database,\n"));
+        assert!(file.contains("#[serde(rename = \"pool-size\")]\npub pool_size: i64,\n"));
+        assert!(file.contains(
+            "        servers: vec![Server { host: String::from(\"alpha\"), weight: 0.5 }, \
+            Server { host: String::from(\"beta\"), ..Default::default() }],\n"
+        ));
+        assert!(file.contains(
+            "    if let Ok(value) = std::env::var(format!(\"{prefix}_PORT\")) {\n        \
+            self.port = value.parse().map_err(|e| format!(\"{prefix}_PORT: {e}\"))?;\n    }\n"
+        ));
+        assert!(file.contains("    self.database.apply_env(&format!(\"{prefix}_DATABASE\"))?;\n"));
+        assert!(!file.contains("_SERVERS"));
+    }
+
+    #[test]
+    fn generates_yaml_config() {
+        let sample = r#"
+# Log settings
+logging:
+  # One of error, warn, info
+  level: info
+  file: ~
+limits:
+  - name: uploads
+    # Bytes per second
+    rate: 100
+  - name: downloads
+    rate: 2.5
+"#;
+        let file = ConfigSample::yaml("settings", sample)
+            .into_file_builder("settings")
+            .unwrap()
+            .format_file();
+        assert!(file.contains("#[doc = \"Log settings\"]\npub logging: Logging,\n"));
+        assert!(file.contains(
+            "#[doc = \"One of error, warn, info\"]\npub level: String,\npub file: Option<String>,\n"
+        ));
+        assert!(file.contains("#[doc = \"Bytes per second\"]\npub rate: f64,\n"));
+        assert!(file.contains(
+            "        limits: vec![Limit { name: String::from(\"uploads\"), rate: 100.0 }, \
+            Limit { name: String::from(\"downloads\"), rate: 2.5 }],\n"
+        ));
+        assert!(file.contains(
+            "        self.file = Some(value.parse().map_err(|e| format!(\"{prefix}_FILE: {e}\"))?);\n"
+        ));
+    }
+
+    #[test]
+    fn merges_nested_tables_and_avoids_used_names() {
+        let sample = r#"
+[default]
+level = "info"
+
+[[servers]]
+host = "alpha"
+[servers.tls]
+cert = "a.pem"
+ca = "ca.pem"
+
+[[servers]]
+host = "beta"
+[servers.tls]
+key = "b.key"
+ca = 1
+"#;
+        assert!(ConfigSample::toml("app", sample)
+            .into_file_builder("config")
+            .is_err());
+        let sample = sample.replace("ca = 1\n", "");
+        let rendered = ConfigSample::toml("app", sample)
+            .into_file_builder("config")
+            .unwrap()
+            .format_file();
+        assert!(rendered.contains("pub default: Default2,\n"));
+        assert!(rendered.contains("pub struct Default2 {\n"));
+        assert!(rendered.contains(
+            "pub struct Tls {\npub cert: String,\npub ca: String,\npub key: String,\n}\n"
+        ));
+        assert!(rendered.contains(
+            "Server { host: String::from(\"beta\"), tls: Tls { key: String::from(\"b.key\"), \
+            ..Default::default() } }"
+        ));
+        let yaml = "servers:\n  - tls:\n      ca: null\n  - tls:\n      ca: ca.pem\n";
+        let rendered = ConfigSample::yaml("app", yaml)
+            .into_file_builder("config")
+            .unwrap()
+            .format_file();
+        assert!(rendered.contains("pub struct Tls {\npub ca: Option<String>,\n}\n"));
+        assert!(rendered.contains("Server { tls: Tls { ca: None } }"));
+    }
+
+    #[test]
+    fn keeps_list_tables_apart() {
+        let sample = r#"
+[[servers]]
+host = "a"
+[servers.tls]
+cert = "a.pem"
+
+[[servers]]
+host = "b"
+port = 2
+[servers.tls]
+key = "b.key"
+"#;
+        let rendered = ConfigSample::toml("app", sample)
+            .into_file_builder("config")
+            .unwrap()
+            .format_file();
+        assert!(rendered.contains(
+            "        servers: vec![Server { host: String::from(\"a\"), tls: Tls { cert: \
+            String::from(\"a.pem\"), ..Default::default() }, ..Default::default() }, \
+            Server { host: String::from(\"b\"), port: 2, tls: Tls { key: String::from(\"b.key\"), \
+            ..Default::default() } }],\n"
+        ));
+        assert!(rendered.contains(
+            "#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]\n\
+            #[serde(default)]\npub struct Server {\n"
+        ));
+        assert!(rendered.contains(
+            "#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]\n\
+            #[serde(default)]\npub struct Tls {\n"
+        ));
+        assert!(!rendered.contains("impl Default for Server"));
+        assert!(!rendered.contains("impl Default for Tls"));
+    }
+}
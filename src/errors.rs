@@ -5,4 +5,20 @@ pub enum Error {
     CaseConvert(String, String, String),
     #[error("Failed to derive {0} reason: {1}")]
     CaseDerive(String, String),
+    #[error("Invalid identifier {0:?} for {1}")]
+    InvalidIdentifier(String, String),
+    #[error("Duplicate {0} {1:?} in {2}")]
+    Duplicate(String, String, String),
+    #[error("Unsupported signature {0} for {1}")]
+    UnsupportedSignature(String, String),
+    #[error("Conflicting generic {0} in {1}, {2}")]
+    ConflictingGenerics(String, String, String),
+    #[error("Failed to parse source: {0}")]
+    Parse(String),
+    #[error("Invalid schema {0}: {1}")]
+    Schema(String, String),
+    #[error("Refusing to overwrite {0}, {1}")]
+    Tampered(String, String),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
 }
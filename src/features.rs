@@ -0,0 +1,214 @@
+use crate::structures::{Annotations, Import};
+use crate::{
+    ConstantBuilder, EnumBuilder, FileBuilder, ImplBuilder, MethodBuilder, ModuleBuilder,
+    StructBuilder, TraitBuilder,
+};
+use std::collections::BTreeSet;
+
+impl Annotations {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        if let Some(cfg) = &self.cfg {
+            cfg.collect_features(features);
+        }
+    }
+}
+
+impl Import {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        if let Import::Gated(cfg, import) = self {
+            cfg.collect_features(features);
+            import.collect_features(features);
+        }
+    }
+}
+
+impl ConstantBuilder {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.annotations.collect_features(features);
+    }
+}
+
+impl MethodBuilder {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.annotations.collect_features(features);
+    }
+}
+
+impl EnumBuilder {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.annotations.collect_features(features);
+        for member in &self.members {
+            member.annotations.collect_features(features);
+        }
+    }
+}
+
+impl StructBuilder {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.annotations.collect_features(features);
+        for field in &self.fields {
+            field.annotations.collect_features(features);
+        }
+    }
+}
+
+impl TraitBuilder {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.annotations.collect_features(features);
+        for method in &self.methods {
+            method.collect_features(features);
+        }
+    }
+}
+
+impl ImplBuilder {
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.annotations.collect_features(features);
+        for constant in &self.consts {
+            constant.collect_features(features);
+        }
+        for method in &self.methods {
+            method.collect_features(features);
+        }
+    }
+}
+
+impl FileBuilder {
+    /// Every feature named in a cfg anywhere in the file
+    pub fn features(&self) -> BTreeSet<String> {
+        let mut features = BTreeSet::new();
+        self.collect_features(&mut features);
+        features
+    }
+
+    fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.annotations.collect_features(features);
+        for import in &self.imports {
+            import.value.collect_features(features);
+        }
+        for constant in &self.constants {
+            constant.value.collect_features(features);
+        }
+        for function in &self.functions {
+            function.value.annotations.collect_features(features);
+        }
+        for enum_builder in &self.enums {
+            enum_builder.value.collect_features(features);
+        }
+        for struct_builder in &self.structs {
+            struct_builder.value.collect_features(features);
+        }
+        for trait_builder in &self.traits {
+            trait_builder.value.collect_features(features);
+        }
+        for container in &self.container_structs {
+            container.value.annotations.collect_features(features);
+        }
+        for implementation in &self.implementations {
+            implementation.value.collect_features(features);
+        }
+    }
+}
+
+impl ModuleBuilder {
+    /// Every feature named in a cfg anywhere in the module tree, including on `mod` declarations
+    pub fn features(&self) -> BTreeSet<String> {
+        let mut features = BTreeSet::new();
+        self.collect_features(&mut features);
+        features
+    }
+
+    pub(crate) fn collect_features(&self, features: &mut BTreeSet<String>) {
+        self.mod_file.collect_features(features);
+        for file in &self.module_files {
+            file.annotations.collect_features(features);
+            file.builder.collect_features(features);
+        }
+        for submodule in &self.submodules {
+            submodule.annotations.collect_features(features);
+            submodule.builder.collect_features(features);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::cfg::Cfg;
+    use crate::structures::gen_enum::NamedComponentSignature;
+    use crate::structures::visibility::Visibility;
+    use crate::structures::{Annotations, Import, RustType};
+    use crate::{
+        CrateBuilder, Dependency, EnumBuilder, FileBuilder, FunctionBuilder, ImplBuilder,
+        ModuleBuilder, Signature, StructBuilder,
+    };
+
+    #[test]
+    fn collects_features() {
+        let file = FileBuilder::new("lib")
+            .add_import(Import::spec("serde::Serialize").with_cfg(Cfg::feature("serde")))
+            .add_struct(
+                StructBuilder::new("Config")
+                    .add_cfg(Cfg::not(Cfg::feature("minimal")))
+                    .add_field_with_annotations(
+                        Visibility::Public,
+                        NamedComponentSignature::new_simple_type(
+                            "path",
+                            RustType::in_scope("String"),
+                        ),
+                        Annotations::empty().with_cfg(Cfg::feature("fs")),
+                    ),
+            )
+            .add_enum(
+                EnumBuilder::new("Backend").add_type_member_with_annotations(
+                    "Tokio",
+                    Signature::simple(RustType::in_scope("Runtime")),
+                    Annotations::empty()
+                        .with_cfg(Cfg::any([Cfg::feature("tokio"), Cfg::target_os("linux")])),
+                ),
+            )
+            .add_function(FunctionBuilder::new("helper").add_cfg(Cfg::test()))
+            .add_impl(
+                ImplBuilder::new(Signature::simple(RustType::in_scope("Config")))
+                    .add_cfg(Cfg::feature("fs")),
+            );
+        assert_eq!(
+            "#[cfg(feature = \"serde\")]\nuse serde::Serialize;\n\
+            #[cfg(not(feature = \"minimal\"))]\nstruct Config {\n#[cfg(feature = \"fs\")]\npub path: String,\n}\n\
+            enum Backend {\n#[cfg(any(feature = \"tokio\", target_os = \"linux\"))]\n\nTokio(Runtime),\n}\n\
+            #[cfg(test)]\nfn helper() {\n\n}\n\
+            #[cfg(feature = \"fs\")]\nimpl Config {\n}\n",
+            file.format_file()
+        );
+    }
+
+    #[test]
+    fn declares_used_features() {
+        let lib = ModuleBuilder::new(
+            FileBuilder::new("lib").add_function(
+                FunctionBuilder::new("load")
+                    .add_cfg(Cfg::feature("serde"))
+                    .add_cfg(Cfg::feature("json")),
+            ),
+        )
+        .add_submodule_with_annotations(
+            Visibility::Public,
+            ModuleBuilder::new(FileBuilder::new("async_api")),
+            Annotations::empty().with_cfg(Cfg::feature("async")),
+        );
+        assert_eq!(
+            vec!["async", "json", "serde"],
+            lib.features()
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<&str>>()
+        );
+        let krate = CrateBuilder::new("gen", "0.1.0")
+            .add_dependency(Dependency::new("serde", "1").set_optional())
+            .add_feature("json", ["serde"])
+            .set_lib(lib)
+            .declare_used_features();
+        assert!(krate
+            .format_manifest()
+            .contains("[features]\njson = [\"serde\"]\nasync = []\n\n[dependencies]\n"));
+    }
+}
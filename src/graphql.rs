@@ -0,0 +1,1358 @@
+use crate::errors::{Error, Result};
+use crate::model::{Model, ModelField, ModelKind, ModelType, TypeRef, RESERVED_NAMES};
+use crate::structures::visibility::Visibility;
+use crate::structures::{Annotations, RustType};
+use crate::util::casing::RustCase;
+use crate::{ConstantBuilder, ModuleBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Generates serde types from a GraphQL schema in SDL and the operations run against it.
+/// The schema's types go in the root file:
+/// - objects, interfaces without implementations and inputs become structs
+/// - unions and implemented interfaces become enums tagged by `__typename`
+/// - enums become enums, custom scalars aliases of their mapped type, see `map_scalar`
+///
+/// Every operation gets a `{Name}Data` struct shaped like its selection set, a
+/// `{Name}Variables` struct if it has variables, and its document with the fragments it uses
+/// as a constant. These go in an `operations` module file. Selections on unions or interfaces
+/// with type conditions become enums of the possible types and must select `__typename`.
+///
+/// The generated code needs `serde` with `derive`, and `serde_json` for unmapped scalars
+pub struct GraphQl {
+    schema: Schema,
+    operations: Vec<Operation>,
+    fragments: BTreeMap<String, Fragment>,
+    scalars: BTreeMap<String, String>,
+}
+
+impl GraphQl {
+    pub fn from_schema_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::parse_named(&path.display().to_string(), &std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse_schema(sdl: &str) -> Result<Self> {
+        Self::parse_named("schema.graphql", sdl)
+    }
+
+    fn parse_named(name: &str, sdl: &str) -> Result<Self> {
+        Ok(Self {
+            schema: Parser::new(name, sdl)?.schema()?,
+            operations: vec![],
+            fragments: BTreeMap::new(),
+            scalars: BTreeMap::new(),
+        })
+    }
+
+    /// Fragments are shared between all added documents
+    pub fn add_operations_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        self.add_operations_named(&path.display().to_string(), &std::fs::read_to_string(path)?)
+    }
+
+    pub fn add_operations(self, document: &str) -> Result<Self> {
+        self.add_operations_named("operations.graphql", document)
+    }
+
+    fn add_operations_named(mut self, name: &str, document: &str) -> Result<Self> {
+        let (operations, fragments) = Parser::new(name, document)?.executable()?;
+        self.operations.extend(operations);
+        self.fragments.extend(fragments);
+        Ok(self)
+    }
+
+    /// Uses a Rust type for a scalar, ex: `DateTime` to `chrono::DateTime<chrono::Utc>`.
+    /// Custom scalars are `serde_json::Value`s unless mapped, built-in ones can be remapped too
+    pub fn map_scalar(mut self, name: impl Into<String>, rust_type: impl Into<String>) -> Self {
+        self.scalars.insert(name.into(), rust_type.into());
+        self
+    }
+
+    /// `name` is the file of the schema's types
+    pub fn into_module(self, name: impl Into<String>) -> Result<ModuleBuilder> {
+        let mut taken = BTreeSet::from(RESERVED_NAMES.map(String::from));
+        let mut names = BTreeMap::new();
+        for definition in &self.schema.definitions {
+            let mut name = definition.name.clone();
+            let mut counter = 2;
+            while !taken.insert(name.clone()) {
+                name = format!("{}{counter}", definition.name);
+                counter += 1;
+            }
+            names.insert(definition.name.clone(), name);
+        }
+        let generator = Generator {
+            schema: &self.schema,
+            fragments: &self.fragments,
+            scalars: &self.scalars,
+            names,
+        };
+        let types = generator.schema_model()?.into_file_builder(name);
+        let mut module = ModuleBuilder::new(types);
+        if !self.operations.is_empty() {
+            let mut operations = OperationWriter {
+                generator: &generator,
+                types: vec![],
+                taken: BTreeSet::from(RESERVED_NAMES.map(String::from)),
+            };
+            let mut constants = vec![];
+            for operation in &self.operations {
+                constants.push(operations.write(operation)?);
+            }
+            let mut fb = Model {
+                types: operations.types,
+                rename_all: true,
+            }
+            .into_file_builder("operations");
+            for constant in constants {
+                fb = fb.add_const(constant);
+            }
+            module = module.add_module_file(Visibility::Public, fb, Annotations::empty());
+        }
+        Ok(module)
+    }
+}
+
+struct Generator<'a> {
+    schema: &'a Schema,
+    fragments: &'a BTreeMap<String, Fragment>,
+    scalars: &'a BTreeMap<String, String>,
+    /// Rust names of schema types, suffixed where the name would shadow one the code uses
+    names: BTreeMap<String, String>,
+}
+
+impl Generator<'_> {
+    fn definition(&self, name: &str) -> Result<&Definition> {
+        self.schema
+            .definitions
+            .iter()
+            .find(|d| d.name == name)
+            .ok_or_else(|| Error::Schema(name.to_string(), "unknown type".to_string()))
+    }
+
+    /// Objects that can be behind a type, itself for objects
+    fn possible_types(&self, name: &str) -> Vec<String> {
+        let Ok(definition) = self.definition(name) else {
+            return vec![];
+        };
+        match &definition.kind {
+            Kind::Union(members) => members.clone(),
+            Kind::Interface { .. } => self
+                .schema
+                .definitions
+                .iter()
+                .filter(|d| matches!(&d.kind, Kind::Object { interfaces, .. } if interfaces.contains(&definition.name)))
+                .map(|d| d.name.clone())
+                .collect(),
+            _ => vec![name.to_string()],
+        }
+    }
+
+    fn fields(&self, name: &str) -> Result<&[FieldDefinition]> {
+        match &self.definition(name)?.kind {
+            Kind::Object { fields, .. } | Kind::Interface { fields, .. } | Kind::Input(fields) => {
+                Ok(fields)
+            }
+            _ => Ok(&[]),
+        }
+    }
+
+    fn is_composite(&self, name: &str) -> bool {
+        self.definition(name).is_ok_and(|d| {
+            matches!(
+                d.kind,
+                Kind::Object { .. } | Kind::Interface { .. } | Kind::Union(_)
+            )
+        })
+    }
+
+    fn rust_name(&self, name: &str) -> String {
+        self.names
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Scalars and enums, schema types are named as defined
+    fn leaf(&self, name: &str) -> Result<TypeRef> {
+        if let Some(mapped) = self.scalars.get(name) {
+            if self.definition(name).is_err() {
+                return Ok(TypeRef::builtin(mapped));
+            }
+        }
+        Ok(match name {
+            "Int" => TypeRef::builtin("i32"),
+            "Float" => TypeRef::builtin("f64"),
+            "String" | "ID" => TypeRef::builtin("String"),
+            "Boolean" => TypeRef::builtin("bool"),
+            _ => {
+                self.definition(name)?;
+                TypeRef::Named(self.rust_name(name))
+            }
+        })
+    }
+
+    fn used_fragments(&self, selections: &[Selection]) -> Result<BTreeSet<String>> {
+        let mut used = BTreeSet::new();
+        let mut pending = selections.iter().collect::<Vec<&Selection>>();
+        while let Some(selection) = pending.pop() {
+            match selection {
+                Selection::Field { selections, .. } | Selection::Inline { selections, .. } => {
+                    pending.extend(selections)
+                }
+                Selection::Spread(name) => {
+                    if used.insert(name.clone()) {
+                        pending.extend(&self.fragment(name)?.selections);
+                    }
+                }
+            }
+        }
+        Ok(used)
+    }
+
+    fn fragment(&self, name: &str) -> Result<&Fragment> {
+        self.fragments
+            .get(name)
+            .ok_or_else(|| Error::Schema(name.to_string(), "unknown fragment".to_string()))
+    }
+
+    /// Whether a type condition applies to selections on `on`
+    fn applies(&self, condition: Option<&str>, on: &str) -> bool {
+        condition.is_none_or(|condition| {
+            condition == on || self.possible_types(condition).iter().any(|t| t == on)
+        })
+    }
+
+    /// The fields selected on `on`, with fragments spread in
+    fn collect<'s>(
+        &'s self,
+        on: &str,
+        selections: &[&'s Selection],
+        collected: &mut Vec<Selected<'s>>,
+    ) -> Result<()> {
+        for selection in selections {
+            match selection {
+                Selection::Field {
+                    alias,
+                    name,
+                    selections,
+                } => {
+                    let key = alias.as_ref().unwrap_or(name);
+                    let index = match collected.iter().position(|s| s.key == *key) {
+                        Some(index) => index,
+                        None => {
+                            collected.push(Selected {
+                                key: key.clone(),
+                                name: name.clone(),
+                                selections: vec![],
+                            });
+                            collected.len() - 1
+                        }
+                    };
+                    collected[index].selections.extend(selections);
+                }
+                Selection::Inline {
+                    condition,
+                    selections,
+                } => {
+                    if self.applies(condition.as_deref(), on) {
+                        let selections = selections.iter().collect::<Vec<&Selection>>();
+                        self.collect(on, &selections, collected)?;
+                    }
+                }
+                Selection::Spread(name) => {
+                    let fragment = self.fragment(name)?;
+                    if self.applies(Some(&fragment.on), on) {
+                        let selections = fragment.selections.iter().collect::<Vec<&Selection>>();
+                        self.collect(on, &selections, collected)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a selection tells apart the possible types of an abstract type
+    fn has_type_conditions(&self, on: &str, selections: &[&Selection]) -> Result<bool> {
+        for selection in selections {
+            let (condition, nested) = match selection {
+                Selection::Field { .. } => continue,
+                Selection::Inline {
+                    condition,
+                    selections,
+                } => (condition.clone(), selections),
+                Selection::Spread(name) => {
+                    let fragment = self.fragment(name)?;
+                    (Some(fragment.on.clone()), &fragment.selections)
+                }
+            };
+            if condition.is_some_and(|c| c != on) {
+                return Ok(true);
+            }
+            if self.has_type_conditions(on, &nested.iter().collect::<Vec<&Selection>>())? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Types held directly by a type, a cycle through these needs a `Box`
+    fn held(&self, definition: &Definition) -> Vec<String> {
+        match &definition.kind {
+            Kind::Object { fields, .. } | Kind::Input(fields) => fields
+                .iter()
+                .filter(|f| !f.ty.is_list())
+                .map(|f| f.ty.name().to_string())
+                .collect(),
+            Kind::Interface { fields, .. } => match self.possible_types(&definition.name) {
+                implementations if implementations.is_empty() => fields
+                    .iter()
+                    .filter(|f| !f.ty.is_list())
+                    .map(|f| f.ty.name().to_string())
+                    .collect(),
+                implementations => implementations,
+            },
+            Kind::Union(members) => members.clone(),
+            Kind::Enum(_) | Kind::Scalar => vec![],
+        }
+    }
+
+    fn reaches(&self, from: &str, to: &str) -> bool {
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![from.to_string()];
+        while let Some(current) = pending.pop() {
+            if current == to {
+                return true;
+            }
+            if visited.insert(current.clone()) {
+                if let Ok(definition) = self.definition(&current) {
+                    pending.extend(self.held(definition));
+                }
+            }
+        }
+        false
+    }
+
+    /// `holder` is `None` in lists, which don't need a `Box` to hold their own type
+    fn schema_type(&self, holder: Option<&str>, ty: &GqlType) -> Result<TypeRef> {
+        Ok(match ty {
+            GqlType::NonNull(inner) => self.schema_type(holder, inner)?.non_null(),
+            GqlType::List(inner) => TypeRef::option(TypeRef::vec(self.schema_type(None, inner)?)),
+            GqlType::Named(name) => {
+                let leaf = self.leaf(name)?;
+                match holder.is_some_and(|holder| self.reaches(name, holder)) {
+                    true => TypeRef::option(TypeRef::boxed(leaf)),
+                    false => TypeRef::option(leaf),
+                }
+            }
+        })
+    }
+
+    fn schema_model(&self) -> Result<Model> {
+        let mut types = vec![];
+        for definition in &self.schema.definitions {
+            let fields = |fields: &[FieldDefinition], input: bool| {
+                fields
+                    .iter()
+                    .map(|f| {
+                        Ok(ModelField {
+                            name: f.name.clone(),
+                            ty: self.schema_type(Some(&definition.name), &f.ty)?,
+                            // Absent and null are the same to an input
+                            optional: input && !matches!(f.ty, GqlType::NonNull(_)),
+                            default: false,
+                            doc: f.doc.clone(),
+                        })
+                    })
+                    .collect::<Result<Vec<ModelField>>>()
+            };
+            let kind = match &definition.kind {
+                Kind::Object { fields: f, .. } => ModelKind::Struct(fields(f, false)?),
+                Kind::Input(f) => ModelKind::Struct(fields(f, true)?),
+                Kind::Interface { fields: f, .. } => match self.possible_types(&definition.name) {
+                    implementations if implementations.is_empty() => {
+                        ModelKind::Struct(fields(f, false)?)
+                    }
+                    implementations => self.tagged(implementations),
+                },
+                Kind::Union(members) => self.tagged(members.clone()),
+                Kind::Enum(values) => ModelKind::Enum(values.clone()),
+                Kind::Scalar => ModelKind::Alias(match self.scalars.get(&definition.name) {
+                    Some(mapped) => TypeRef::builtin(mapped),
+                    None => TypeRef::json_value(),
+                }),
+            };
+            types.push(ModelType {
+                name: self.rust_name(&definition.name),
+                doc: definition.doc.clone(),
+                kind,
+            });
+        }
+        Ok(Model {
+            types,
+            rename_all: true,
+        })
+    }
+
+    /// Variants are named after the types so they match `__typename`
+    fn tagged(&self, members: Vec<String>) -> ModelKind {
+        ModelKind::Tagged(
+            Some("__typename".to_string()),
+            members
+                .into_iter()
+                .map(|m| (m.clone(), TypeRef::Named(self.rust_name(&m))))
+                .collect(),
+        )
+    }
+}
+
+impl TypeRef {
+    /// GraphQL types are nullable unless marked otherwise
+    fn non_null(self) -> Self {
+        match self {
+            TypeRef::Option(inner) => *inner,
+            ty => ty,
+        }
+    }
+}
+
+/// A selected field, selections of the same response key are merged
+struct Selected<'a> {
+    key: String,
+    name: String,
+    selections: Vec<&'a Selection>,
+}
+
+struct OperationWriter<'a> {
+    generator: &'a Generator<'a>,
+    types: Vec<ModelType>,
+    taken: BTreeSet<String>,
+}
+
+impl OperationWriter<'_> {
+    fn reserve(&mut self, name: String) -> String {
+        let mut candidate = name.clone();
+        let mut counter = 2;
+        while !self.taken.insert(candidate.clone()) {
+            candidate = format!("{name}{counter}");
+            counter += 1;
+        }
+        candidate
+    }
+
+    fn write(&mut self, operation: &Operation) -> Result<ConstantBuilder> {
+        let name = RustCase::convert_lossy(&operation.name, RustCase::Pascal);
+        let root = match operation.kind.as_str() {
+            "query" => &self.generator.schema.query,
+            "mutation" => &self.generator.schema.mutation,
+            _ => &self.generator.schema.subscription,
+        };
+        if !operation.variables.is_empty() {
+            let fields = operation
+                .variables
+                .iter()
+                .map(|(variable, ty)| {
+                    Ok(ModelField {
+                        name: variable.clone(),
+                        ty: self.variable_type(ty)?,
+                        optional: !matches!(ty, GqlType::NonNull(_)),
+                        default: false,
+                        doc: None,
+                    })
+                })
+                .collect::<Result<Vec<ModelField>>>()?;
+            let variables = self.reserve(format!("{name}Variables"));
+            self.types.push(ModelType {
+                name: variables,
+                doc: None,
+                kind: ModelKind::Struct(fields),
+            });
+        }
+        let selections = operation.selections.iter().collect::<Vec<&Selection>>();
+        let start = self.types.len();
+        self.selection_type(root, &selections, format!("{name}Data"))?;
+        // Emitted innermost first, the data reads better on top
+        self.types[start..].reverse();
+        let mut document = operation.source.clone();
+        for fragment in self.generator.used_fragments(&operation.selections)? {
+            document = format!("{document}\n{}", self.generator.fragments[&fragment].source);
+        }
+        let constant = self.reserve(format!(
+            "{}_{}",
+            RustCase::convert_lossy(&operation.name, RustCase::Scream),
+            operation.kind.to_uppercase()
+        ));
+        Ok(ConstantBuilder::const_builder(
+            constant,
+            RustType::in_scope("&str"),
+            format!("{document:?}"),
+        )
+        .set_visibility(Visibility::Public))
+    }
+
+    fn variable_type(&self, ty: &GqlType) -> Result<TypeRef> {
+        Ok(match ty {
+            GqlType::NonNull(inner) => self.variable_type(inner)?.non_null(),
+            GqlType::List(inner) => TypeRef::option(TypeRef::vec(self.variable_type(inner)?)),
+            GqlType::Named(name) => TypeRef::option(self.generator.leaf(name)?.qualified("super")),
+        })
+    }
+
+    fn selection_type(
+        &mut self,
+        on: &str,
+        selections: &[&Selection],
+        name: String,
+    ) -> Result<TypeRef> {
+        let possible = self.generator.possible_types(on);
+        let is_abstract = possible != [on.to_string()];
+        let generator = self.generator;
+        if !is_abstract || !generator.has_type_conditions(on, selections)? {
+            return self.selection_struct(on, on, selections, name, false);
+        }
+        let mut shared = vec![];
+        generator.collect(on, selections, &mut shared)?;
+        if !shared.iter().any(|s| s.name == "__typename") {
+            return Err(Error::Schema(
+                name,
+                format!("select `__typename` to tell apart the types of {on}"),
+            ));
+        }
+        let name = self.reserve(name);
+        let mut variants = vec![];
+        for concrete in possible {
+            let variant = format!(
+                "{name}{}",
+                RustCase::convert_lossy(&concrete, RustCase::Pascal)
+            );
+            variants.push((
+                concrete.clone(),
+                self.selection_struct(on, &concrete, selections, variant, true)?,
+            ));
+        }
+        self.types.push(ModelType {
+            name: name.clone(),
+            doc: None,
+            kind: ModelKind::Tagged(Some("__typename".to_string()), variants),
+        });
+        Ok(TypeRef::Named(name))
+    }
+
+    /// `on` is the type fields are looked up in, `concrete` the one fragments are matched to.
+    /// Variants of a tagged enum leave the tag to the enum
+    fn selection_struct(
+        &mut self,
+        on: &str,
+        concrete: &str,
+        selections: &[&Selection],
+        name: String,
+        tagged: bool,
+    ) -> Result<TypeRef> {
+        let name = self.reserve(name);
+        let generator = self.generator;
+        let mut selected = vec![];
+        generator.collect(concrete, selections, &mut selected)?;
+        let mut fields = vec![];
+        for field in selected {
+            if field.name == "__typename" {
+                if !tagged {
+                    fields.push(ModelField {
+                        name: field.key,
+                        ty: TypeRef::builtin("String"),
+                        optional: false,
+                        default: false,
+                        doc: None,
+                    });
+                }
+                continue;
+            }
+            let definition = self
+                .generator
+                .fields(concrete)?
+                .iter()
+                .chain(self.generator.fields(on)?)
+                .find(|f| f.name == field.name)
+                .ok_or_else(|| {
+                    Error::Schema(name.clone(), format!("{on} has no field {}", field.name))
+                })?;
+            let hint = format!(
+                "{name}{}",
+                RustCase::convert_lossy(&field.key, RustCase::Pascal)
+            );
+            let ty = self.selected_type(&definition.ty, &field.selections, hint)?;
+            fields.push(ModelField {
+                name: field.key,
+                ty,
+                optional: false,
+                default: false,
+                doc: definition.doc.clone(),
+            });
+        }
+        self.types.push(ModelType {
+            name: name.clone(),
+            doc: None,
+            kind: ModelKind::Struct(fields),
+        });
+        Ok(TypeRef::Named(name))
+    }
+
+    fn selected_type(
+        &mut self,
+        ty: &GqlType,
+        selections: &[&Selection],
+        hint: String,
+    ) -> Result<TypeRef> {
+        Ok(match ty {
+            GqlType::NonNull(inner) => self.selected_type(inner, selections, hint)?.non_null(),
+            GqlType::List(inner) => {
+                TypeRef::option(TypeRef::vec(self.selected_type(inner, selections, hint)?))
+            }
+            GqlType::Named(name) if self.generator.is_composite(name) => {
+                TypeRef::option(self.selection_type(name, selections, hint)?)
+            }
+            GqlType::Named(name) => TypeRef::option(self.generator.leaf(name)?.qualified("super")),
+        })
+    }
+}
+
+struct Schema {
+    definitions: Vec<Definition>,
+    query: String,
+    mutation: String,
+    subscription: String,
+}
+
+struct Definition {
+    name: String,
+    doc: Option<String>,
+    kind: Kind,
+}
+
+enum Kind {
+    Object {
+        fields: Vec<FieldDefinition>,
+        interfaces: Vec<String>,
+    },
+    Interface {
+        fields: Vec<FieldDefinition>,
+    },
+    Input(Vec<FieldDefinition>),
+    Enum(Vec<String>),
+    Union(Vec<String>),
+    Scalar,
+}
+
+struct FieldDefinition {
+    name: String,
+    doc: Option<String>,
+    ty: GqlType,
+}
+
+#[derive(Debug, Clone)]
+enum GqlType {
+    Named(String),
+    List(Box<GqlType>),
+    NonNull(Box<GqlType>),
+}
+
+impl GqlType {
+    fn name(&self) -> &str {
+        match self {
+            GqlType::Named(name) => name,
+            GqlType::List(inner) | GqlType::NonNull(inner) => inner.name(),
+        }
+    }
+
+    fn is_list(&self) -> bool {
+        match self {
+            GqlType::Named(_) => false,
+            GqlType::List(_) => true,
+            GqlType::NonNull(inner) => inner.is_list(),
+        }
+    }
+}
+
+struct Operation {
+    /// `query`, `mutation` or `subscription`
+    kind: String,
+    name: String,
+    variables: Vec<(String, GqlType)>,
+    selections: Vec<Selection>,
+    /// The operation as written
+    source: String,
+}
+
+struct Fragment {
+    on: String,
+    selections: Vec<Selection>,
+    source: String,
+}
+
+enum Selection {
+    Field {
+        alias: Option<String>,
+        name: String,
+        selections: Vec<Selection>,
+    },
+    Spread(String),
+    Inline {
+        condition: Option<String>,
+        selections: Vec<Selection>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Punct(char),
+    Spread,
+    Str(String),
+    Number,
+}
+
+struct Parser<'a> {
+    name: &'a str,
+    source: &'a str,
+    /// With their start and end in the source
+    tokens: Vec<(Token, usize, usize)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(name: &'a str, source: &'a str) -> Result<Self> {
+        Ok(Self {
+            name,
+            source,
+            tokens: tokenize(name, source)?,
+            pos: 0,
+        })
+    }
+
+    fn error(&self, reason: impl Into<String>) -> Error {
+        let offset = self
+            .tokens
+            .get(self.pos)
+            .map_or(self.source.len(), |(_, start, _)| *start);
+        let line = self.source[..offset].matches('\n').count() + 1;
+        Error::Schema(format!("{}:{line}", self.name), reason.into())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _, _)| token)
+    }
+
+    fn peek_name(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Name(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(punct));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_name(&mut self, keyword: &str) -> bool {
+        let found = self.peek_name() == Some(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, punct: char) -> Result<()> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{punct}`, found {:?}", self.peek())))
+        }
+    }
+
+    fn name(&mut self) -> Result<String> {
+        match self.peek().cloned() {
+            Some(Token::Name(name)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            token => Err(self.error(format!("expected a name, found {token:?}"))),
+        }
+    }
+
+    fn description(&mut self) -> Option<String> {
+        match self.peek().cloned() {
+            Some(Token::Str(doc)) => {
+                self.pos += 1;
+                Some(doc)
+            }
+            _ => None,
+        }
+    }
+
+    fn ty(&mut self) -> Result<GqlType> {
+        let ty = if self.eat('[') {
+            let inner = self.ty()?;
+            self.expect(']')?;
+            GqlType::List(Box::new(inner))
+        } else {
+            GqlType::Named(self.name()?)
+        };
+        Ok(match self.eat('!') {
+            true => GqlType::NonNull(Box::new(ty)),
+            false => ty,
+        })
+    }
+
+    /// Skips a balanced group if the next token opens one
+    fn skip_group(&mut self, open: char, close: char) -> Result<()> {
+        if !self.eat(open) {
+            return Ok(());
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            match self.peek() {
+                Some(Token::Punct(c)) if *c == open => depth += 1,
+                Some(Token::Punct(c)) if *c == close => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error(format!("unclosed `{open}`"))),
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    fn skip_directives(&mut self) -> Result<()> {
+        while self.eat('@') {
+            self.name()?;
+            self.skip_group('(', ')')?;
+        }
+        Ok(())
+    }
+
+    fn skip_default(&mut self) -> Result<()> {
+        if !self.eat('=') {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(Token::Punct('[')) => self.skip_group('[', ']'),
+            Some(Token::Punct('{')) => self.skip_group('{', '}'),
+            Some(_) => {
+                self.pos += 1;
+                Ok(())
+            }
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn field_definitions(&mut self) -> Result<Vec<FieldDefinition>> {
+        let mut fields = vec![];
+        if !self.eat('{') {
+            return Ok(fields);
+        }
+        while !self.eat('}') {
+            let doc = self.description();
+            let name = self.name()?;
+            self.skip_group('(', ')')?;
+            self.expect(':')?;
+            let ty = self.ty()?;
+            self.skip_default()?;
+            self.skip_directives()?;
+            fields.push(FieldDefinition { name, doc, ty });
+        }
+        Ok(fields)
+    }
+
+    fn schema(mut self) -> Result<Schema> {
+        let mut schema = Schema {
+            definitions: vec![],
+            query: "Query".to_string(),
+            mutation: "Mutation".to_string(),
+            subscription: "Subscription".to_string(),
+        };
+        while self.peek().is_some() {
+            let doc = self.description();
+            let extend = self.eat_name("extend");
+            let keyword = self.name()?;
+            if keyword == "schema" {
+                self.skip_directives()?;
+                if self.eat('{') {
+                    while !self.eat('}') {
+                        let operation = self.name()?;
+                        self.expect(':')?;
+                        let root = self.name()?;
+                        match operation.as_str() {
+                            "query" => schema.query = root,
+                            "mutation" => schema.mutation = root,
+                            _ => schema.subscription = root,
+                        }
+                    }
+                }
+                continue;
+            }
+            if keyword == "directive" {
+                self.expect('@')?;
+                self.name()?;
+                self.skip_group('(', ')')?;
+                self.eat_name("repeatable");
+                if !self.eat_name("on") {
+                    return Err(self.error("expected the directive's locations"));
+                }
+                self.eat('|');
+                self.name()?;
+                while self.eat('|') {
+                    self.name()?;
+                }
+                continue;
+            }
+            let name = self.name()?;
+            let kind = match keyword.as_str() {
+                "scalar" => {
+                    self.skip_directives()?;
+                    Kind::Scalar
+                }
+                "type" | "interface" => {
+                    let mut interfaces = vec![];
+                    if self.eat_name("implements") {
+                        self.eat('&');
+                        interfaces.push(self.name()?);
+                        while self.eat('&') || self.peek_name().is_some() {
+                            interfaces.push(self.name()?);
+                        }
+                    }
+                    self.skip_directives()?;
+                    let fields = self.field_definitions()?;
+                    match keyword.as_str() {
+                        "type" => Kind::Object { fields, interfaces },
+                        _ => Kind::Interface { fields },
+                    }
+                }
+                "input" => {
+                    self.skip_directives()?;
+                    Kind::Input(self.field_definitions()?)
+                }
+                "enum" => {
+                    self.skip_directives()?;
+                    let mut values = vec![];
+                    if self.eat('{') {
+                        while !self.eat('}') {
+                            self.description();
+                            values.push(self.name()?);
+                            self.skip_directives()?;
+                        }
+                    }
+                    Kind::Enum(values)
+                }
+                "union" => {
+                    self.skip_directives()?;
+                    let mut members = vec![];
+                    if self.eat('=') {
+                        self.eat('|');
+                        members.push(self.name()?);
+                        while self.eat('|') {
+                            members.push(self.name()?);
+                        }
+                    }
+                    Kind::Union(members)
+                }
+                _ => return Err(self.error(format!("unexpected `{keyword}`"))),
+            };
+            match schema.definitions.iter_mut().find(|d| d.name == name) {
+                Some(existing) if extend => existing.kind.extend(kind),
+                Some(_) => return Err(self.error(format!("{name} is defined twice"))),
+                None => schema.definitions.push(Definition { name, doc, kind }),
+            }
+        }
+        Ok(schema)
+    }
+
+    fn executable(mut self) -> Result<(Vec<Operation>, BTreeMap<String, Fragment>)> {
+        let mut operations = vec![];
+        let mut fragments = BTreeMap::new();
+        while self.peek().is_some() {
+            let start = self.tokens[self.pos].1;
+            let keyword = match self.peek() {
+                Some(Token::Punct('{')) => {
+                    return Err(self.error("operations need a name to name their types"))
+                }
+                _ => self.name()?,
+            };
+            match keyword.as_str() {
+                "query" | "mutation" | "subscription" => {
+                    let name = match self.peek() {
+                        Some(Token::Name(_)) => self.name()?,
+                        _ => return Err(self.error("operations need a name to name their types")),
+                    };
+                    let mut variables = vec![];
+                    if self.eat('(') {
+                        while !self.eat(')') {
+                            self.expect('$')?;
+                            let variable = self.name()?;
+                            self.expect(':')?;
+                            variables.push((variable, self.ty()?));
+                            self.skip_default()?;
+                            self.skip_directives()?;
+                        }
+                    }
+                    self.skip_directives()?;
+                    let selections = self.selections()?;
+                    operations.push(Operation {
+                        kind: keyword,
+                        name,
+                        variables,
+                        selections,
+                        source: self.source_from(start),
+                    });
+                }
+                "fragment" => {
+                    let name = self.name()?;
+                    if !self.eat_name("on") {
+                        return Err(self.error("expected the fragment's type condition"));
+                    }
+                    let on = self.name()?;
+                    self.skip_directives()?;
+                    let selections = self.selections()?;
+                    let source = self.source_from(start);
+                    fragments.insert(
+                        name,
+                        Fragment {
+                            on,
+                            selections,
+                            source,
+                        },
+                    );
+                }
+                _ => return Err(self.error(format!("unexpected `{keyword}`"))),
+            }
+        }
+        Ok((operations, fragments))
+    }
+
+    /// The source from `start` to the end of the last token read
+    fn source_from(&self, start: usize) -> String {
+        self.source[start..self.tokens[self.pos - 1].2].to_string()
+    }
+
+    fn selections(&mut self) -> Result<Vec<Selection>> {
+        let mut selections = vec![];
+        self.expect('{')?;
+        while !self.eat('}') {
+            if self.peek() == Some(&Token::Spread) {
+                self.pos += 1;
+                let is_spread = matches!(self.peek_name(), Some(name) if name != "on");
+                if is_spread {
+                    selections.push(Selection::Spread(self.name()?));
+                    self.skip_directives()?;
+                } else {
+                    let condition = match self.eat_name("on") {
+                        true => Some(self.name()?),
+                        false => None,
+                    };
+                    self.skip_directives()?;
+                    selections.push(Selection::Inline {
+                        condition,
+                        selections: self.selections()?,
+                    });
+                }
+                continue;
+            }
+            let mut name = self.name()?;
+            let mut alias = None;
+            if self.eat(':') {
+                alias = Some(name);
+                name = self.name()?;
+            }
+            self.skip_group('(', ')')?;
+            self.skip_directives()?;
+            let selections_of_field = match self.peek() {
+                Some(Token::Punct('{')) => self.selections()?,
+                _ => vec![],
+            };
+            selections.push(Selection::Field {
+                alias,
+                name,
+                selections: selections_of_field,
+            });
+        }
+        Ok(selections)
+    }
+}
+
+impl Kind {
+    /// Adds what an `extend` declares
+    fn extend(&mut self, extension: Kind) {
+        match (self, extension) {
+            (
+                Kind::Object { fields, interfaces },
+                Kind::Object {
+                    fields: more_fields,
+                    interfaces: more_interfaces,
+                },
+            ) => {
+                fields.extend(more_fields);
+                interfaces.extend(more_interfaces);
+            }
+            (Kind::Interface { fields }, Kind::Interface { fields: more })
+            | (Kind::Input(fields), Kind::Input(more)) => fields.extend(more),
+            (Kind::Enum(values), Kind::Enum(more)) | (Kind::Union(values), Kind::Union(more)) => {
+                values.extend(more)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn tokenize(name: &str, source: &str) -> Result<Vec<(Token, usize, usize)>> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            // Commas are insignificant
+            c if c.is_whitespace() || c == ',' || c == '\u{feff}' => continue,
+            '#' => {
+                while chars.next_if(|(_, c)| *c != '\n').is_some() {}
+                continue;
+            }
+            '.' => {
+                if chars.next_if(|(_, c)| *c == '.').is_none()
+                    || chars.next_if(|(_, c)| *c == '.').is_none()
+                {
+                    return Err(Error::Schema(
+                        name.to_string(),
+                        "expected `...`".to_string(),
+                    ));
+                }
+                Token::Spread
+            }
+            '"' if source[start..].starts_with("\"\"\"") => {
+                chars.next();
+                chars.next();
+                let end = source[start + 3..]
+                    .find("\"\"\"")
+                    .map(|end| start + 3 + end)
+                    .ok_or_else(|| {
+                        Error::Schema(name.to_string(), "unterminated block string".to_string())
+                    })?;
+                while chars.next_if(|(i, _)| *i < end + 3).is_some() {}
+                let text = source[start + 3..end].replace("\\\"\"\"", "\"\"\"");
+                Token::Str(
+                    text.lines()
+                        .map(str::trim)
+                        .collect::<Vec<&str>>()
+                        .join("\n")
+                        .trim()
+                        .to_string(),
+                )
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => text.push('\n'),
+                            Some((_, 't')) => text.push('\t'),
+                            Some((_, c)) => text.push(c),
+                            None => break,
+                        },
+                        Some((_, '\n')) | None => {
+                            return Err(Error::Schema(
+                                name.to_string(),
+                                "unterminated string".to_string(),
+                            ))
+                        }
+                        Some((_, c)) => text.push(c),
+                    }
+                }
+                Token::Str(text)
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut text = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+                {
+                    text.push(c);
+                }
+                Token::Name(text)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                while chars
+                    .next_if(|(_, c)| c.is_ascii_alphanumeric() || ".+-".contains(*c))
+                    .is_some()
+                {}
+                Token::Number
+            }
+            c => Token::Punct(c),
+        };
+        let end = chars.peek().map_or(source.len(), |(i, _)| *i);
+        tokens.push((token, start, end));
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graphql::GraphQl;
+    use std::path::Path;
+
+    const SCHEMA: &str = r#"
+scalar DateTime
+
+"A character in the films"
+interface Character {
+  id: ID!
+  name: String!
+  friends: [Character]
+}
+
+type Human implements Character {
+  id: ID!
+  name: String!
+  friends: [Character]
+  homePlanet: String
+}
+
+type Droid implements Character @key(fields: "id") {
+  id: ID!
+  name: String!
+  friends: [Character]
+  primaryFunction: String
+}
+
+enum Episode { NEW_HOPE EMPIRE JEDI }
+
+input ReviewInput {
+  stars: Int!
+  "What the reviewer thought"
+  commentary: String
+}
+
+type Review { stars: Int!, createdAt: DateTime! }
+
+union SearchResult = Human | Droid
+
+type Query {
+  hero(episode: Episode): Character
+  search(text: String!): [SearchResult!]!
+}
+
+type Mutation {
+  createReview(episode: Episode, review: ReviewInput!): Review
+}
+"#;
+
+    const OPERATIONS: &str = r#"
+query HeroNames($episode: Episode) {
+  hero(episode: $episode) {
+    __typename
+    name
+    ... on Droid { primaryFunction }
+    ...HumanFields
+  }
+}
+
+fragment HumanFields on Human { homePlanet }
+
+mutation CreateReview($episode: Episode, $review: ReviewInput!) {
+  review: createReview(episode: $episode, review: $review) { stars createdAt }
+}
+"#;
+
+    #[test]
+    fn generates_schema_types() {
+        let rendered = GraphQl::parse_schema(SCHEMA)
+            .unwrap()
+            .map_scalar("DateTime", "String")
+            .into_module("schema")
+            .unwrap()
+            .render();
+        let types = &rendered[Path::new("schema.rs")];
+        assert!(types.contains("pub type DateTime = String;\n"));
+        assert!(types.contains(
+            "#[doc = \"A character in the films\"]\n\
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+            #[serde(tag = \"__typename\")]\npub enum Character {\n"
+        ));
+        assert!(types.contains(
+            "#[serde(rename_all = \"camelCase\")]\npub struct Human {\npub id: String,\n\
+            pub name: String,\npub friends: Option<Vec<Option<Character>>>,\n\
+            pub home_planet: Option<String>,\n}\n"
+        ));
+        assert!(types.contains(
+            "pub struct ReviewInput {\npub stars: i32,\n#[doc = \"What the reviewer thought\"]\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\npub commentary: Option<String>,\n"
+        ));
+        assert!(types.contains("#[serde(rename = \"NEW_HOPE\")]\n\nNewHope,\n"));
+        assert!(!rendered.contains_key(Path::new("operations.rs")));
+    }
+
+    #[test]
+    fn generates_operation_types() {
+        let rendered = GraphQl::parse_schema(SCHEMA)
+            .unwrap()
+            .add_operations(OPERATIONS)
+            .unwrap()
+            .into_module("schema")
+            .unwrap()
+            .render();
+        let operations = &rendered[Path::new("operations.rs")];
+        assert!(operations.contains(
+            "pub struct HeroNamesVariables {\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub episode: Option<super::Episode>,\n}\n"
+        ));
+        assert!(operations
+            .contains("pub struct HeroNamesData {\npub hero: Option<HeroNamesDataHero>,\n}\n"));
+        assert!(operations.contains(
+            "#[serde(tag = \"__typename\")]\npub enum HeroNamesDataHero {\n\n\
+            Human(HeroNamesDataHeroHuman),\n\nDroid(HeroNamesDataHeroDroid),\n}\n"
+        ));
+        assert!(operations.contains(
+            "pub struct HeroNamesDataHeroHuman {\npub name: String,\n\
+            pub home_planet: Option<String>,\n}\n"
+        ));
+        assert!(operations.contains(
+            "pub struct CreateReviewData {\npub review: Option<CreateReviewDataReview>,\n}\n"
+        ));
+        assert!(operations.contains("pub created_at: super::DateTime,\n"));
+        assert!(operations.contains(
+            "pub const HERO_NAMES_QUERY: &str = \"query HeroNames($episode: Episode) {\\n"
+        ));
+        assert!(operations.contains("\\nfragment HumanFields on Human { homePlanet }\";\n"));
+
+        let unnamed = GraphQl::parse_schema(SCHEMA)
+            .unwrap()
+            .add_operations("{ hero { name } }");
+        assert!(unnamed.is_err());
+    }
+
+    #[test]
+    fn keeps_clear_of_used_names() {
+        let schema = r#"
+type Query { options: [Option!]!, pick: Pick }
+type Option { label: String }
+union Pick = Option | Query
+"#;
+        let rendered = GraphQl::parse_schema(schema)
+            .unwrap()
+            .add_operations(
+                "query options { options { label } }\nquery Options { pick { __typename } }",
+            )
+            .unwrap()
+            .into_module("schema")
+            .unwrap()
+            .render();
+        let types = &rendered[Path::new("schema.rs")];
+        assert!(types.contains(
+            "pub struct Query {\npub options: Vec<Option2>,\npub pick: Option<Box<Pick>>,\n"
+        ));
+        assert!(types.contains("pub struct Option2 {\npub label: Option<String>,\n}\n"));
+        assert!(types.contains("pub enum Pick {\n\nOption(Option2),\n\nQuery(Query),\n}\n"));
+        let operations = &rendered[Path::new("operations.rs")];
+        assert!(operations.contains("pub const OPTIONS_QUERY: &str = \"query options "));
+        assert!(operations.contains("pub const OPTIONS_QUERY2: &str = \"query Options "));
+    }
+}
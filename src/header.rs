@@ -0,0 +1,126 @@
+use crate::errors::{Error, Result};
+use std::fmt::Write;
+use std::path::Path;
+
+const MARKER: &str = "@generated";
+const CHECKSUM_PREFIX: &str = "// checksum: fnv1a64:";
+
+/// Header written at the top of every file produced by a `ModuleBuilder`, marks the file as
+/// generated and optionally carries license text and a checksum of the content below it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct GeneratedHeader {
+    generator: String,
+    license: Option<String>,
+    checksum: bool,
+}
+
+impl Default for GeneratedHeader {
+    fn default() -> Self {
+        Self::new("codegen-rs")
+    }
+}
+
+impl GeneratedHeader {
+    pub fn new(generator: impl Into<String>) -> Self {
+        Self {
+            generator: generator.into(),
+            license: None,
+            checksum: true,
+        }
+    }
+
+    /// Written as line comments below the marker
+    pub fn set_license(mut self, license: impl Into<String>) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
+    /// Without a checksum only the marker is used to detect hand written files
+    pub fn disable_checksum(mut self) -> Self {
+        self.checksum = false;
+        self
+    }
+
+    pub fn apply(&self, body: &str) -> String {
+        let mut out = format!("// {MARKER} by {} DO NOT EDIT\n", self.generator);
+        if let Some(license) = &self.license {
+            for line in license.lines() {
+                if line.is_empty() {
+                    out.push_str("//\n");
+                } else {
+                    let _ = writeln!(out, "// {line}");
+                }
+            }
+        }
+        if self.checksum {
+            let _ = writeln!(out, "{CHECKSUM_PREFIX}{:016x}", checksum(body));
+        }
+        out.push('\n');
+        out.push_str(body);
+        out
+    }
+
+    /// Checks that an existing file was generated and hasn't been edited since
+    pub fn verify(&self, path: &Path, existing: &str) -> Result<()> {
+        let tampered = |reason: &str| {
+            Err(Error::Tampered(
+                path.display().to_string(),
+                reason.to_string(),
+            ))
+        };
+        let Some((header, body)) = existing.split_once("\n\n") else {
+            return tampered("missing @generated marker");
+        };
+        let header = header.lines().collect::<Vec<&str>>();
+        if !header.iter().all(|l| l.starts_with("//"))
+            || !header.first().is_some_and(|l| l.contains(MARKER))
+        {
+            return tampered("missing @generated marker");
+        }
+        if let Some(expected) = header.iter().find_map(|l| l.strip_prefix(CHECKSUM_PREFIX)) {
+            if expected != format!("{:016x}", checksum(body)) {
+                return tampered("checksum mismatch, the file was edited after generation");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a, only needs to catch edits, not adversaries
+fn checksum(body: &str) -> u64 {
+    body.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::header::GeneratedHeader;
+    use std::path::Path;
+
+    #[test]
+    fn writes_and_verifies() {
+        let header = GeneratedHeader::new("my-gen").set_license("MIT\n\nCopyright");
+        let file = header.apply("struct A {\n}\n");
+        assert!(file.starts_with(
+            "// @generated by my-gen DO NOT EDIT\n// MIT\n//\n// Copyright\n// checksum: fnv1a64:"
+        ));
+        assert!(file.ends_with("\n\nstruct A {\n}\n"));
+        let path = Path::new("a.rs");
+        assert!(header.verify(path, &file).is_ok());
+        let edited = file.replace("struct A", "struct B");
+        assert!(matches!(
+            header.verify(path, &edited),
+            Err(Error::Tampered(..))
+        ));
+        assert!(matches!(
+            header.verify(path, "struct A {\n}\n"),
+            Err(Error::Tampered(..))
+        ));
+        let unchecked = GeneratedHeader::default().disable_checksum();
+        assert!(unchecked
+            .verify(path, &unchecked.apply("struct A {\n}\n").replace('A', "B"))
+            .is_ok());
+    }
+}
@@ -0,0 +1,426 @@
+use crate::errors::{Error, Result};
+use crate::model::{Model, ModelField, ModelKind, ModelType, TypeRef, RESERVED_NAMES};
+use crate::util::casing::{singular, RustCase};
+use crate::FileBuilder;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Generates serde models from sample documents when there's no schema.
+/// The samples are merged into one shape:
+/// - fields missing from some samples become `Option`s, so do fields that were `null`
+/// - integers mixed with floats become `f64`, integers above `i64::MAX` become `u64`
+/// - values of different kinds, or only ever `null`, become `serde_json::Value`s
+/// - objects with the same fields and types share one struct, named after the first field seen
+///
+/// Structs whose keys share a case, ex: camelCase, get a serde `rename_all`.
+/// The generated code needs `serde` with `derive`, and `serde_json` if any value is untyped
+pub struct JsonSamples {
+    root_name: String,
+    samples: Vec<Value>,
+    enum_threshold: Option<usize>,
+}
+
+impl JsonSamples {
+    /// The root type is named `root_name`, if the samples are arrays it's an alias of a `Vec`
+    pub fn new(root_name: impl Into<String>) -> Self {
+        Self {
+            root_name: root_name.into(),
+            samples: vec![],
+            enum_threshold: None,
+        }
+    }
+
+    pub fn add_sample(self, source: &str) -> Result<Self> {
+        let sample = serde_json::from_str(source)
+            .map_err(|e| Error::Schema(self.root_name.clone(), e.to_string()))?;
+        Ok(self.add_value(sample))
+    }
+
+    pub fn add_sample_file(self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let sample = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| Error::Schema(path.display().to_string(), e.to_string()))?;
+        Ok(self.add_value(sample))
+    }
+
+    pub fn add_value(mut self, sample: Value) -> Self {
+        self.samples.push(sample);
+        self
+    }
+
+    /// Strings with at most `max_variants` distinct values become enums, as long as some value
+    /// repeats, a single sample of a string doesn't make an enum. Off by default
+    pub fn set_enum_threshold(mut self, max_variants: usize) -> Self {
+        self.enum_threshold = Some(max_variants);
+        self
+    }
+
+    pub fn into_file_builder(self, file_name: impl Into<String>) -> Result<FileBuilder> {
+        let shape = self
+            .samples
+            .iter()
+            .map(Shape::observe)
+            .reduce(Shape::merge)
+            .ok_or_else(|| Error::Schema(self.root_name.clone(), "no samples".to_string()))?;
+        let mut emitter = Emitter {
+            enum_threshold: self.enum_threshold,
+            types: vec![],
+            taken: BTreeSet::from(RESERVED_NAMES.map(String::from)),
+            structures: BTreeMap::new(),
+        };
+        let root_name = emitter.reserve(&self.root_name);
+        match &shape.kind {
+            Kind::Object(object) if !shape.nullable => {
+                emitter.struct_type(object, Some(root_name), "");
+            }
+            _ => {
+                let ty = emitter.type_of(&shape, &self.root_name);
+                emitter.types.push(ModelType {
+                    name: root_name,
+                    doc: None,
+                    kind: ModelKind::Alias(ty),
+                });
+            }
+        }
+        // Emitted innermost first, the root reads better on top
+        emitter.types.reverse();
+        Ok(Model {
+            types: emitter.types,
+            rename_all: true,
+        }
+        .into_file_builder(file_name))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Shape {
+    nullable: bool,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, Default)]
+enum Kind {
+    /// Only seen as `null` or in empty arrays
+    #[default]
+    Unknown,
+    Bool,
+    Integer {
+        negative: bool,
+        above_i64: bool,
+    },
+    Float,
+    /// Occurrences by value
+    String(BTreeMap<String, usize>),
+    Array(Box<Shape>),
+    Object(Object),
+    Mixed,
+}
+
+#[derive(Debug, Clone)]
+struct Object {
+    seen: usize,
+    /// Shape and number of objects the field was in, in the order first seen
+    fields: Vec<(String, Shape, usize)>,
+}
+
+impl Shape {
+    fn observe(value: &Value) -> Self {
+        let kind = match value {
+            Value::Null => {
+                return Shape {
+                    nullable: true,
+                    kind: Kind::Unknown,
+                }
+            }
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(n) if n.is_f64() => Kind::Float,
+            Value::Number(n) => Kind::Integer {
+                negative: n.as_i64().is_some_and(|n| n < 0),
+                above_i64: n.as_i64().is_none(),
+            },
+            Value::String(s) => Kind::String(BTreeMap::from([(s.clone(), 1)])),
+            Value::Array(items) => Kind::Array(Box::new(
+                items
+                    .iter()
+                    .map(Shape::observe)
+                    .reduce(Shape::merge)
+                    .unwrap_or_default(),
+            )),
+            Value::Object(fields) => Kind::Object(Object {
+                seen: 1,
+                fields: fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), Shape::observe(value), 1))
+                    .collect(),
+            }),
+        };
+        Shape {
+            nullable: false,
+            kind,
+        }
+    }
+
+    fn merge(self, other: Shape) -> Shape {
+        let kind = match (self.kind, other.kind) {
+            (Kind::Unknown, kind) | (kind, Kind::Unknown) => kind,
+            (Kind::Bool, Kind::Bool) => Kind::Bool,
+            (
+                Kind::Integer {
+                    negative,
+                    above_i64,
+                },
+                Kind::Integer {
+                    negative: other_negative,
+                    above_i64: other_above_i64,
+                },
+            ) => Kind::Integer {
+                negative: negative || other_negative,
+                above_i64: above_i64 || other_above_i64,
+            },
+            (Kind::Integer { .. } | Kind::Float, Kind::Integer { .. } | Kind::Float) => Kind::Float,
+            (Kind::String(mut values), Kind::String(other_values)) => {
+                for (value, count) in other_values {
+                    *values.entry(value).or_default() += count;
+                }
+                Kind::String(values)
+            }
+            (Kind::Array(items), Kind::Array(other_items)) => {
+                Kind::Array(Box::new(items.merge(*other_items)))
+            }
+            (Kind::Object(mut object), Kind::Object(other_object)) => {
+                object.seen += other_object.seen;
+                for (name, shape, present) in other_object.fields {
+                    match object.fields.iter_mut().find(|(n, _, _)| *n == name) {
+                        Some(field) => {
+                            field.1 = std::mem::take(&mut field.1).merge(shape);
+                            field.2 += present;
+                        }
+                        None => object.fields.push((name, shape, present)),
+                    }
+                }
+                Kind::Object(object)
+            }
+            _ => Kind::Mixed,
+        };
+        Shape {
+            nullable: self.nullable || other.nullable,
+            kind,
+        }
+    }
+}
+
+struct Emitter {
+    enum_threshold: Option<usize>,
+    /// Innermost first
+    types: Vec<ModelType>,
+    taken: BTreeSet<String>,
+    /// Names of emitted types by their structure
+    structures: BTreeMap<String, String>,
+}
+
+impl Emitter {
+    fn reserve(&mut self, hint: &str) -> String {
+        let name = RustCase::convert_lossy(hint, RustCase::Pascal);
+        let mut candidate = name.clone();
+        let mut counter = 2;
+        while !self.taken.insert(candidate.clone()) {
+            candidate = format!("{name}{counter}");
+            counter += 1;
+        }
+        candidate
+    }
+
+    fn type_of(&mut self, shape: &Shape, hint: &str) -> TypeRef {
+        let ty = match &shape.kind {
+            Kind::Unknown | Kind::Mixed => TypeRef::json_value(),
+            Kind::Bool => TypeRef::builtin("bool"),
+            Kind::Integer {
+                negative: false,
+                above_i64: true,
+            } => TypeRef::builtin("u64"),
+            Kind::Integer {
+                negative: true,
+                above_i64: true,
+            }
+            | Kind::Float => TypeRef::builtin("f64"),
+            Kind::Integer { .. } => TypeRef::builtin("i64"),
+            Kind::String(values) if self.is_enum(values) => {
+                let values = values.keys().cloned().collect::<Vec<String>>();
+                let key = format!("enum {values:?}");
+                match self.structures.get(&key) {
+                    Some(name) => TypeRef::Named(name.clone()),
+                    None => {
+                        let name = self.reserve(hint);
+                        self.structures.insert(key, name.clone());
+                        self.types.push(ModelType {
+                            name: name.clone(),
+                            doc: None,
+                            kind: ModelKind::Enum(values),
+                        });
+                        TypeRef::Named(name)
+                    }
+                }
+            }
+            Kind::String(_) => TypeRef::builtin("String"),
+            Kind::Array(items) => TypeRef::vec(self.type_of(items, &singular(hint))),
+            Kind::Object(object) => self.struct_type(object, None, hint),
+        };
+        match shape.nullable {
+            true => TypeRef::option(ty),
+            false => ty,
+        }
+    }
+
+    fn is_enum(&self, values: &BTreeMap<String, usize>) -> bool {
+        self.enum_threshold.is_some_and(|threshold| {
+            values.len() <= threshold && values.values().sum::<usize>() > values.len()
+        })
+    }
+
+    /// Structs with the same fields are only emitted once, the root is always emitted
+    fn struct_type(&mut self, object: &Object, name: Option<String>, hint: &str) -> TypeRef {
+        let fields = object
+            .fields
+            .iter()
+            .map(|(field, shape, present)| ModelField {
+                name: field.clone(),
+                ty: self.type_of(shape, field),
+                optional: *present < object.seen,
+                default: false,
+                doc: None,
+            })
+            .collect::<Vec<ModelField>>();
+        let mut key = fields
+            .iter()
+            .map(|f| format!("{:?} {:?} {}", f.name, f.ty, f.optional))
+            .collect::<Vec<String>>();
+        key.sort();
+        let key = format!("struct {key:?}");
+        if name.is_none() {
+            if let Some(existing) = self.structures.get(&key) {
+                return TypeRef::Named(existing.clone());
+            }
+        }
+        let name = name.unwrap_or_else(|| self.reserve(hint));
+        self.structures.entry(key).or_insert_with(|| name.clone());
+        self.types.push(ModelType {
+            name: name.clone(),
+            doc: None,
+            kind: ModelKind::Struct(fields),
+        });
+        TypeRef::Named(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_sample::JsonSamples;
+
+    #[test]
+    fn infers_merged_models() {
+        let first = r#"{
+            "orderId": 1,
+            "status": "open",
+            "total": 10,
+            "billingAddress": {"street": "Main", "zip": "1"},
+            "lines": [{"sku": "a", "status": "open"}, {"sku": "b", "status": "closed"}],
+            "type": "web"
+        }"#;
+        let second = r#"{
+            "orderId": 2,
+            "status": "closed",
+            "total": 12.5,
+            "billingAddress": {"street": "Side", "zip": "2"},
+            "shippingAddress": {"street": "Side", "zip": "2"},
+            "lines": [{"sku": "c", "status": "open"}],
+            "note": null,
+            "type": "store"
+        }"#;
+        let file = JsonSamples::new("order")
+            .add_sample(first)
+            .unwrap()
+            .add_sample(second)
+            .unwrap()
+            .set_enum_threshold(3)
+            .into_file_builder("order")
+            .unwrap()
+            .format_file();
+        let expected = r#"use serde::{Deserialize, Serialize};
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Order {
+pub order_id: i64,
+pub status: String,
+pub total: f64,
+pub billing_address: BillingAddress,
+pub lines: Vec<Line>,
+pub r#type: String,
+#[serde(skip_serializing_if = "Option::is_none")]
+pub shipping_address: Option<BillingAddress>,
+#[serde(skip_serializing_if = "Option::is_none")]
+pub note: Option<serde_json::Value>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Line {
+pub sku: String,
+pub status: Status,
+}
+#[derive(Copy, Eq, Hash, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Status {
+#[serde(rename = "closed")]
+
+Closed,
+#[serde(rename = "open")]
+
+Open,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BillingAddress {
+pub street: String,
+pub zip: String,
+}
+"#;
+        assert_eq!(file, expected);
+    }
+
+    #[test]
+    fn aliases_array_roots() {
+        let file = JsonSamples::new("users")
+            .add_sample(r#"[{"id": 18446744073709551615, "NAME": "a", "ALIAS_NAME": "b"}]"#)
+            .unwrap()
+            .into_file_builder("users")
+            .unwrap()
+            .format_file();
+        assert!(file.contains("pub type Users = Vec<User>;\n"));
+        assert!(file.contains(
+            "#[serde(rename_all = \"SCREAMING_SNAKE_CASE\")]\npub struct User {\n\
+            #[serde(rename = \"id\")]\npub id: u64,\npub name: String,\n"
+        ));
+        assert!(JsonSamples::new("empty")
+            .into_file_builder("empty")
+            .is_err());
+    }
+
+    #[test]
+    fn keeps_clear_of_used_names() {
+        let file = JsonSamples::new("string")
+            .add_sample(r#"{"option": {"vec": [1]}, "name": "a", "kind": "open"}"#)
+            .unwrap()
+            .add_sample(r#"{"option": {"vec": []}, "name": "b", "kind": "OPEN"}"#)
+            .unwrap()
+            .add_sample(r#"{"option": {"vec": [2]}, "name": "c", "kind": "open"}"#)
+            .unwrap()
+            .set_enum_threshold(2)
+            .into_file_builder("string")
+            .unwrap()
+            .format_file();
+        assert!(file.contains(
+            "pub struct String2 {\npub option: Option2,\npub name: String,\npub kind: Kind,\n}\n"
+        ));
+        assert!(file.contains("pub struct Option2 {\npub vec: Vec<i64>,\n}\n"));
+        assert!(file.contains(
+            "#[serde(rename = \"OPEN\")]\n\nOpen,\n#[serde(rename = \"open\")]\n\nOpen2,\n"
+        ));
+    }
+}
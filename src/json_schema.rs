@@ -0,0 +1,718 @@
+use crate::errors::{Error, Result};
+use crate::model::{
+    unique_names, Model, ModelField, ModelKind, ModelType, TypeRef, RESERVED_NAMES,
+};
+use crate::util::casing::RustCase;
+use crate::FileBuilder;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Generates serde models from a JSON Schema, a draft 2020-12 subset:
+/// - objects with `properties` become structs, properties not in `required` become `Option`s
+/// - `enum`s of strings become enums, `oneOf`/`anyOf` become untagged enums
+/// - arrays become `Vec`s, objects with only `additionalProperties` become `HashMap`s
+/// - `$ref`s point into `$defs`/`definitions` or at other local files, ex: `common.json#/$defs/Id`
+///
+/// The generated code needs `serde` with `derive` and `serde_json` for untyped values
+pub struct JsonSchema {
+    documents: BTreeMap<PathBuf, Value>,
+    root: PathBuf,
+    root_name: String,
+}
+
+impl JsonSchema {
+    /// The root type is named after the schema's `title`, or the file stem if it has none
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let root = path.as_ref().canonicalize()?;
+        let root_name = root
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let document = read_document(&root)?;
+        Ok(Self::from_document(root, root_name, document))
+    }
+
+    /// Files referenced from the schema are looked up relative to the working directory,
+    /// see `set_base_dir`
+    pub fn parse(root_name: impl Into<String>, source: &str) -> Result<Self> {
+        let root_name = root_name.into();
+        let document = serde_json::from_str(source)
+            .map_err(|e| Error::Schema(root_name.clone(), e.to_string()))?;
+        let root = PathBuf::from(format!("{root_name}.json"));
+        Ok(Self::from_document(root, root_name, document))
+    }
+
+    pub(crate) fn from_document(root: PathBuf, root_name: String, document: Value) -> Self {
+        Self {
+            documents: BTreeMap::from([(root.clone(), document)]),
+            root,
+            root_name,
+        }
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory the files referenced from a parsed schema are relative to
+    pub fn set_base_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        if let Some(document) = self.documents.remove(&self.root) {
+            let dir = dir.as_ref();
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+            self.root = dir.join(format!("{}.json", self.root_name));
+            self.documents.insert(self.root.clone(), document);
+        }
+        self
+    }
+
+    /// Every type reachable from the root and from its `$defs`, in a single file
+    pub fn into_file_builder(self, file_name: impl Into<String>) -> Result<FileBuilder> {
+        let root = self.root().to_path_buf();
+        let root_name = self.root_name.clone();
+        let mut converter = Converter::new(self);
+        let document = converter.load(&root)?;
+        let name = document
+            .get("title")
+            .and_then(Value::as_str)
+            .unwrap_or(&root_name)
+            .to_string();
+        if is_named(&document) {
+            converter.reference(&root, "", &name, false)?;
+        } else if document.get("type").is_some() {
+            let name = converter.reserve(&name);
+            let ty = converter.type_of(&root, &document, &name, false)?;
+            converter.finish(ModelType {
+                name,
+                doc: description(&document),
+                kind: ModelKind::Alias(ty),
+            });
+        }
+        for defs in ["$defs", "definitions"] {
+            if let Some(Value::Object(definitions)) = document.get(defs) {
+                for key in definitions.keys() {
+                    let pointer = format!("/{defs}/{}", escape_pointer(key));
+                    converter.reference(&root, &pointer, key, false)?;
+                }
+            }
+        }
+        Ok(converter.into_model().into_file_builder(file_name))
+    }
+}
+
+pub(crate) fn read_document(path: &Path) -> Result<Value> {
+    let source = std::fs::read_to_string(path)?;
+    #[cfg(feature = "openapi")]
+    if matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml" | "yml")
+    ) {
+        return crate::openapi::parse_yaml(&path.display().to_string(), &source);
+    }
+    serde_json::from_str(&source)
+        .map_err(|e| Error::Schema(path.display().to_string(), e.to_string()))
+}
+
+pub(crate) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn description(schema: &Value) -> Option<String> {
+    schema
+        .get("description")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+/// Schemas that need a type definition of their own rather than a builtin type
+fn is_named(schema: &Value) -> bool {
+    let Value::Object(schema) = schema else {
+        return false;
+    };
+    schema.contains_key("properties")
+        || string_enum(schema).is_some()
+        || ["oneOf", "anyOf"]
+            .iter()
+            .any(|key| union_members(schema, key).is_some_and(|m| nullable(&m).is_none()))
+        || schema
+            .get("allOf")
+            .and_then(Value::as_array)
+            .is_some_and(|parts| parts.len() > 1)
+        || types(schema).is_some_and(|t| t.iter().filter(|t| *t != "null").count() > 1)
+}
+
+fn string_enum(schema: &Map<String, Value>) -> Option<Vec<String>> {
+    schema
+        .get("enum")?
+        .as_array()?
+        .iter()
+        .map(|v| v.as_str().map(str::to_string))
+        .collect()
+}
+
+fn union_members(schema: &Map<String, Value>, key: &str) -> Option<Vec<Value>> {
+    schema.get(key)?.as_array().cloned()
+}
+
+/// `oneOf: [X, {"type": "null"}]` is an optional `X`
+fn nullable(members: &[Value]) -> Option<&Value> {
+    match members {
+        [member, null] | [null, member]
+            if null.get("type").and_then(Value::as_str) == Some("null") =>
+        {
+            Some(member)
+        }
+        _ => None,
+    }
+}
+
+fn types(schema: &Map<String, Value>) -> Option<Vec<String>> {
+    match schema.get("type")? {
+        Value::String(ty) => Some(vec![ty.clone()]),
+        Value::Array(types) => types
+            .iter()
+            .map(|t| t.as_str().map(str::to_string))
+            .collect(),
+        _ => None,
+    }
+}
+
+pub(crate) struct Converter {
+    schema: JsonSchema,
+    /// Types defined for a `(file, pointer)`
+    names: BTreeMap<(PathBuf, String), String>,
+    taken: BTreeSet<String>,
+    order: Vec<String>,
+    defined: BTreeMap<String, ModelType>,
+    /// Types whose definitions are being built, refering to one directly needs a `Box`
+    in_progress: Vec<String>,
+    /// Refs to non-named schemas being followed, to catch cycles that never reach a type
+    following: Vec<(PathBuf, String)>,
+}
+
+impl Converter {
+    pub(crate) fn new(schema: JsonSchema) -> Self {
+        Self {
+            schema,
+            names: BTreeMap::new(),
+            taken: BTreeSet::from(RESERVED_NAMES.map(String::from)),
+            order: vec![],
+            defined: BTreeMap::new(),
+            in_progress: vec![],
+            following: vec![],
+        }
+    }
+
+    pub(crate) fn into_model(mut self) -> Model {
+        Model {
+            types: self
+                .order
+                .iter()
+                .filter_map(|name| self.defined.remove(name))
+                .collect(),
+            rename_all: false,
+        }
+    }
+
+    pub(crate) fn load(&mut self, path: &Path) -> Result<Value> {
+        if let Some(document) = self.schema.documents.get(path) {
+            return Ok(document.clone());
+        }
+        let document = read_document(path)?;
+        self.schema
+            .documents
+            .insert(path.to_path_buf(), document.clone());
+        Ok(document)
+    }
+
+    fn reserve(&mut self, hint: &str) -> String {
+        let name = RustCase::convert_lossy(hint, RustCase::Pascal);
+        let mut candidate = name.clone();
+        let mut counter = 2;
+        while !self.taken.insert(candidate.clone()) {
+            candidate = format!("{name}{counter}");
+            counter += 1;
+        }
+        self.order.push(candidate.clone());
+        candidate
+    }
+
+    fn finish(&mut self, model_type: ModelType) {
+        self.defined.insert(model_type.name.clone(), model_type);
+    }
+
+    /// Splits a `$ref` into the file it points into and the pointer within it
+    fn resolve_ref(&self, doc: &Path, reference: &str) -> Result<(PathBuf, String)> {
+        let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        if file.contains("://") {
+            return Err(Error::Schema(
+                reference.to_string(),
+                "only local files can be referenced".to_string(),
+            ));
+        }
+        let path = if file.is_empty() {
+            doc.to_path_buf()
+        } else {
+            let relative = doc.parent().unwrap_or(Path::new("")).join(file);
+            relative.canonicalize().map_err(|e| {
+                Error::Schema(
+                    reference.to_string(),
+                    format!("{}: {e}", relative.display()),
+                )
+            })?
+        };
+        Ok((path, pointer.to_string()))
+    }
+
+    pub(crate) fn reference(
+        &mut self,
+        doc: &Path,
+        pointer: &str,
+        hint: &str,
+        indirect: bool,
+    ) -> Result<TypeRef> {
+        let key = (doc.to_path_buf(), pointer.to_string());
+        if let Some(name) = self.names.get(&key) {
+            let named = TypeRef::Named(name.clone());
+            return Ok(if !indirect && self.in_progress.contains(name) {
+                TypeRef::boxed(named)
+            } else {
+                named
+            });
+        }
+        let document = self.load(doc)?;
+        let schema = document.pointer(pointer).cloned().ok_or_else(|| {
+            Error::Schema(
+                format!("{}#{pointer}", doc.display()),
+                "nothing at the referenced location".to_string(),
+            )
+        })?;
+        if is_named(&schema) {
+            let name = self.reserve(hint);
+            self.names.insert(key, name.clone());
+            self.define(doc, &schema, name.clone())?;
+            Ok(TypeRef::Named(name))
+        } else {
+            if self.following.contains(&key) {
+                return Err(Error::Schema(
+                    format!("{}#{pointer}", doc.display()),
+                    "reference cycle without an object in it".to_string(),
+                ));
+            }
+            self.following.push(key);
+            let ty = self.type_of(doc, &schema, hint, indirect);
+            self.following.pop();
+            ty
+        }
+    }
+
+    fn define(&mut self, doc: &Path, schema: &Value, name: String) -> Result<()> {
+        self.in_progress.push(name.clone());
+        let kind = self.kind_of(doc, schema, &name);
+        self.in_progress.pop();
+        self.finish(ModelType {
+            doc: description(schema),
+            kind: kind?,
+            name,
+        });
+        Ok(())
+    }
+
+    fn kind_of(&mut self, doc: &Path, schema: &Value, name: &str) -> Result<ModelKind> {
+        let Value::Object(object) = schema else {
+            return Ok(ModelKind::Alias(TypeRef::json_value()));
+        };
+        if let Some(values) = string_enum(object) {
+            return Ok(ModelKind::Enum(values));
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(members) = union_members(object, key) {
+                return self.union(doc, &members, name);
+            }
+        }
+        if let Some(parts) = object.get("allOf").and_then(Value::as_array) {
+            let mut fields = vec![];
+            for part in parts {
+                let (part_doc, part) = self.follow(doc, part)?;
+                fields.extend(self.fields(&part_doc, &part, name)?);
+            }
+            return Ok(ModelKind::Struct(fields));
+        }
+        if object.contains_key("properties") {
+            return Ok(ModelKind::Struct(self.fields(doc, schema, name)?));
+        }
+        let members = types(object)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|t| t != "null")
+            .map(|t| {
+                let mut member = object.clone();
+                member.insert("type".to_string(), Value::String(t));
+                Value::Object(member)
+            })
+            .collect::<Vec<Value>>();
+        self.union(doc, &members, name)
+    }
+
+    /// Resolves `$ref`s until reaching a schema with content
+    pub(crate) fn follow(&mut self, doc: &Path, schema: &Value) -> Result<(PathBuf, Value)> {
+        let mut seen = BTreeSet::new();
+        let (mut doc, mut schema) = (doc.to_path_buf(), schema.clone());
+        while let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let (next_doc, pointer) = self.resolve_ref(&doc, reference)?;
+            if !seen.insert((next_doc.clone(), pointer.clone())) {
+                return Err(Error::Schema(
+                    reference.to_string(),
+                    "reference cycle".to_string(),
+                ));
+            }
+            let document = self.load(&next_doc)?;
+            schema = document.pointer(&pointer).cloned().ok_or_else(|| {
+                Error::Schema(
+                    reference.to_string(),
+                    "nothing at the referenced location".to_string(),
+                )
+            })?;
+            doc = next_doc;
+        }
+        Ok((doc, schema))
+    }
+
+    fn fields(&mut self, doc: &Path, schema: &Value, name: &str) -> Result<Vec<ModelField>> {
+        let required = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| {
+                r.iter()
+                    .filter_map(Value::as_str)
+                    .collect::<BTreeSet<&str>>()
+            })
+            .unwrap_or_default();
+        let mut fields = vec![];
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (property, property_schema) in properties {
+                let hint = format!(
+                    "{name}{}",
+                    RustCase::convert_lossy(property, RustCase::Pascal)
+                );
+                fields.push(ModelField {
+                    name: property.clone(),
+                    ty: self.type_of(doc, property_schema, &hint, false)?,
+                    optional: !required.contains(property.as_str()),
+                    default: false,
+                    doc: description(property_schema),
+                });
+            }
+        }
+        Ok(fields)
+    }
+
+    fn union(&mut self, doc: &Path, members: &[Value], name: &str) -> Result<ModelKind> {
+        let mut variant_names = vec![];
+        let mut variant_types = vec![];
+        for (ind, member) in members.iter().enumerate() {
+            let variant = member
+                .get("$ref")
+                .and_then(Value::as_str)
+                .and_then(|r| r.rsplit(['/', '#']).find(|s| !s.is_empty()))
+                .map(|r| r.trim_end_matches(".json"))
+                .or_else(|| member.get("title").and_then(Value::as_str))
+                .or_else(|| {
+                    member
+                        .as_object()
+                        .and_then(types)
+                        .and_then(|t| t.into_iter().next())
+                        .map(|t| type_variant_name(&t))
+                })
+                .map(|v| RustCase::convert_lossy(v, RustCase::Pascal))
+                .unwrap_or_else(|| format!("Variant{ind}"));
+            let hint = format!("{name}{variant}");
+            variant_types.push(self.type_of(doc, member, &hint, false)?);
+            variant_names.push(variant);
+        }
+        Ok(ModelKind::Union(
+            unique_names(variant_names)
+                .into_iter()
+                .zip(variant_types)
+                .collect(),
+        ))
+    }
+
+    pub(crate) fn type_of(
+        &mut self,
+        doc: &Path,
+        schema: &Value,
+        hint: &str,
+        indirect: bool,
+    ) -> Result<TypeRef> {
+        let object = match schema {
+            Value::Object(object) => object,
+            _ => return Ok(TypeRef::json_value()),
+        };
+        if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+            let (target, pointer) = self.resolve_ref(doc, reference)?;
+            let hint = match pointer.rsplit('/').next() {
+                Some(last) if !last.is_empty() => last.to_string(),
+                _ => target
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_else(|| hint.to_string()),
+            };
+            return self.reference(&target, &pointer, &hint, indirect);
+        }
+        for key in ["oneOf", "anyOf"] {
+            if let Some(member) = union_members(object, key).as_deref().and_then(nullable) {
+                return Ok(TypeRef::option(self.type_of(doc, member, hint, indirect)?));
+            }
+        }
+        // OpenAPI 3.0 has no `null` type
+        if object.get("nullable").and_then(Value::as_bool) == Some(true) {
+            let mut non_null = object.clone();
+            non_null.remove("nullable");
+            let ty = self.type_of(doc, &Value::Object(non_null), hint, indirect)?;
+            return Ok(TypeRef::option(ty));
+        }
+        if let Some([part]) = object
+            .get("allOf")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+        {
+            return self.type_of(doc, part, hint, indirect);
+        }
+        if is_named(schema) {
+            let name = self.reserve(hint);
+            self.define(doc, schema, name.clone())?;
+            return Ok(TypeRef::Named(name));
+        }
+        let all_types = types(object).unwrap_or_default();
+        let non_null = all_types
+            .iter()
+            .filter(|t| *t != "null")
+            .collect::<Vec<&String>>();
+        let ty = match non_null.first().map(|t| t.as_str()) {
+            Some("string") => TypeRef::builtin("String"),
+            Some("integer") => {
+                TypeRef::builtin(match object.get("format").and_then(Value::as_str) {
+                    Some("int32") => "i32",
+                    Some("uint32") => "u32",
+                    Some("uint64") => "u64",
+                    _ => "i64",
+                })
+            }
+            Some("number") => {
+                TypeRef::builtin(match object.get("format").and_then(Value::as_str) {
+                    Some("float") => "f32",
+                    _ => "f64",
+                })
+            }
+            Some("boolean") => TypeRef::builtin("bool"),
+            Some("array") => TypeRef::vec(match object.get("items") {
+                Some(items) => self.type_of(doc, items, &format!("{hint}Item"), true)?,
+                None => TypeRef::json_value(),
+            }),
+            Some("object") => TypeRef::map(match object.get("additionalProperties") {
+                Some(values @ Value::Object(_)) => {
+                    self.type_of(doc, values, &format!("{hint}Value"), true)?
+                }
+                _ => TypeRef::json_value(),
+            }),
+            Some("null") | None if all_types.iter().any(|t| t == "null") => TypeRef::builtin("()"),
+            _ if object.get("const").is_some_and(Value::is_string) => TypeRef::builtin("String"),
+            _ => TypeRef::json_value(),
+        };
+        Ok(
+            if non_null.len() < all_types.len() && !non_null.is_empty() {
+                TypeRef::option(ty)
+            } else {
+                ty
+            },
+        )
+    }
+}
+
+/// Variant name for an inline member of a union, ex: `oneOf: [{"type": "string"}, ..]`
+fn type_variant_name(ty: &str) -> &'static str {
+    match ty {
+        "string" => "String",
+        "integer" => "Integer",
+        "number" => "Number",
+        "boolean" => "Boolean",
+        "array" => "Array",
+        "object" => "Object",
+        "null" => "Null",
+        _ => "Value",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json_schema::JsonSchema;
+
+    #[test]
+    fn generates_models() {
+        let schema = r##"{
+            "title": "user",
+            "description": "A user account",
+            "type": "object",
+            "required": ["id", "userName", "role"],
+            "properties": {
+                "id": {"type": "integer", "format": "int32"},
+                "userName": {"type": "string"},
+                "type": {"type": ["string", "null"]},
+                "role": {"enum": ["admin", "read-only"]},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "labels": {"type": "object", "additionalProperties": {"type": "number"}},
+                "address": {
+                    "type": "object",
+                    "required": ["street"],
+                    "properties": {"street": {"type": "string", "description": "Street and number"}}
+                },
+                "contact": {"oneOf": [{"$ref": "#/$defs/Email"}, {"type": "string"}]},
+                "manager": {"$ref": "#"}
+            },
+            "$defs": {
+                "Email": {
+                    "type": "object",
+                    "required": ["address"],
+                    "properties": {"address": {"type": "string"}}
+                }
+            }
+        }"##;
+        let file = JsonSchema::parse("user", schema)
+            .unwrap()
+            .into_file_builder("models")
+            .unwrap();
+        assert_eq!(
+            "use std::collections::HashMap;\n\
+            use serde::{Deserialize, Serialize};\n\
+            #[doc = \"A user account\"]\n\
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+            pub struct User {\n\
+            pub id: i32,\n\
+            #[serde(rename = \"userName\")]\n\
+            pub user_name: String,\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub r#type: Option<String>,\n\
+            pub role: UserRole,\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub tags: Option<Vec<String>>,\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub labels: Option<HashMap<String, f64>>,\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub address: Option<UserAddress>,\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub contact: Option<UserContact>,\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub manager: Option<Box<User>>,\n\
+            }\n\
+            #[derive(Copy, Eq, Hash, Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+            pub enum UserRole {\n\
+            #[serde(rename = \"admin\")]\n\
+            \n\
+            Admin,\n\
+            #[serde(rename = \"read-only\")]\n\
+            \n\
+            ReadOnly,\n\
+            }\n\
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+            pub struct UserAddress {\n\
+            #[doc = \"Street and number\"]\n\
+            pub street: String,\n\
+            }\n\
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+            #[serde(untagged)]\n\
+            pub enum UserContact {\n\
+            \n\
+            Email(Email),\n\
+            \n\
+            String(String),\n\
+            }\n\
+            #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+            pub struct Email {\n\
+            pub address: String,\n\
+            }\n",
+            file.format_file()
+        );
+    }
+
+    #[test]
+    fn resolves_refs_across_files() {
+        let dir = std::env::temp_dir().join(format!("codegen-rs-schema-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        std::fs::write(
+            dir.join("order.json"),
+            r#"{"type": "object", "required": ["id"], "properties": {
+                "id": {"$ref": "common/types.json#/$defs/Id"},
+                "items": {"type": "array", "items": {"$ref": "common/types.json#/$defs/Item"}}
+            }}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common").join("types.json"),
+            r##"{"$defs": {
+                "Id": {"type": "string"},
+                "Item": {"type": "object", "required": ["id"], "properties": {"id": {"$ref": "#/$defs/Id"}}}
+            }}"##,
+        )
+        .unwrap();
+        let rendered = JsonSchema::from_file(dir.join("order.json"))
+            .unwrap()
+            .into_file_builder("order")
+            .unwrap()
+            .format_file();
+        assert!(rendered.contains("pub struct Order {\npub id: String,\n"));
+        assert!(rendered.contains("pub items: Option<Vec<Item>>,\n"));
+        assert!(rendered.contains("pub struct Item {\npub id: String,\n}\n"));
+        let missing = JsonSchema::parse(
+            "broken",
+            r##"{"properties": {"a": {"$ref": "#/$defs/Nope"}}}"##,
+        )
+        .unwrap()
+        .into_file_builder("broken");
+        assert!(missing.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dedupes_colliding_field_names() {
+        let rendered = JsonSchema::parse(
+            "user",
+            r#"{"type": "object", "required": ["userName", "user_name"], "properties": {
+                "userName": {"type": "string"},
+                "user_name": {"type": "string"}
+            }}"#,
+        )
+        .unwrap()
+        .into_file_builder("user")
+        .unwrap()
+        .format_file();
+        assert!(rendered.contains(
+            "#[serde(rename = \"userName\")]\n\
+            pub user_name: String,\n\
+            #[serde(rename = \"user_name\")]\n\
+            pub user_name2: String,\n"
+        ));
+    }
+
+    #[test]
+    fn keeps_clear_of_used_names() {
+        let rendered = JsonSchema::parse(
+            "string",
+            r##"{"type": "object", "required": ["name", "option"], "properties": {
+                "name": {"type": "string"},
+                "option": {"$ref": "#/definitions/Option"}
+            }, "definitions": {"Option": {"type": "object", "properties": {
+                "values": {"type": "array", "items": {"type": "integer"}}
+            }}}}"##,
+        )
+        .unwrap()
+        .into_file_builder("string")
+        .unwrap()
+        .format_file();
+        assert!(
+            rendered.contains("pub struct String2 {\npub name: String,\npub option: Option2,\n")
+        );
+        assert!(rendered.contains("pub struct Option2 {\n"));
+        assert!(rendered.contains("pub values: Option<Vec<i64>>,\n"));
+    }
+}
@@ -9,16 +9,67 @@ use crate::structures::{
     Annotations, ComponentSignature, Derives, Import, Module, Ownership, RustType, Signature,
     Synchronicity, ToSourceFilePart, TypeDef, TypeDefDeclaration,
 };
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 
 pub mod structures;
 #[macro_use]
 mod util;
 
 use crate::structures::gen_trait::TraitEntity;
-pub use util::casing::{fix_keyword, InferCase, RustCase};
-
+pub use build_script::BuildOutput;
+pub use cargo::{CrateBuilder, Dependency, WorkspaceBuilder};
+pub use check::{CheckReport, FileDiff};
+pub use errors::{Error, Result};
+pub use header::GeneratedHeader;
+pub use output::{
+    FileSystemSink, InMemorySink, ModuleLayout, OutputSink, RenderedTree, WriteReport, WriteStatus,
+    DEFAULT_MANIFEST,
+};
+pub use structures::cfg::Cfg;
+pub use tree::FileRef;
+pub use util::casing::{
+    escape_renames, fix_keyword, fix_keyword_for_edition, Edition, InferCase, RustCase,
+};
+pub use validation::Validate;
+
+#[cfg(feature = "assets")]
+pub mod assets;
+#[cfg(feature = "avro")]
+pub mod avro;
+mod build_script;
+mod cargo;
+mod check;
+#[cfg(feature = "config")]
+pub mod config;
 mod errors;
+mod features;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+mod header;
+#[cfg(feature = "json-schema")]
+pub mod json_sample;
+#[cfg(feature = "json-schema")]
+pub mod json_schema;
+// Front-ends without `json-schema` only use the naming helpers
+#[cfg(any(feature = "json-schema", feature = "proto", feature = "sql"))]
+#[cfg_attr(not(feature = "json-schema"), allow(dead_code))]
+mod model;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+pub mod output;
+#[cfg(feature = "proto")]
+pub mod proto;
+#[cfg(feature = "reader")]
+pub mod reader;
+#[cfg(feature = "sql")]
+pub mod sql;
+#[cfg(feature = "tokens")]
+pub mod tokens;
+mod tree;
+mod validation;
+#[cfg(feature = "xsd")]
+pub mod xsd;
 
 pub trait HasAnnotationBuilder {
     fn set_annotation(self) -> Self;
@@ -28,6 +79,12 @@ pub struct ModuleBuilder {
     pub mod_file: FileBuilder,
     module_files: Vec<ModuleFile>,
     submodules: Vec<Submodule>,
+    escape_keywords: bool,
+    edition: Option<Edition>,
+    header: Option<GeneratedHeader>,
+    force_overwrite: bool,
+    layout: Option<ModuleLayout>,
+    default_visibility: Visibility,
 }
 
 pub struct ModuleFile {
@@ -38,6 +95,7 @@ pub struct ModuleFile {
 
 pub struct Submodule {
     visibility: Visibility,
+    annotations: Annotations,
     builder: ModuleBuilder,
 }
 
@@ -47,9 +105,49 @@ impl ModuleBuilder {
             mod_file,
             module_files: vec![],
             submodules: vec![],
+            escape_keywords: true,
+            edition: None,
+            header: None,
+            force_overwrite: false,
+            layout: None,
+            default_visibility: Visibility::Public,
         }
     }
 
+    set_keyword_escaping!();
+
+    /// Overrides the edition of every file in the tree, submodules without their own edition
+    /// inherit it
+    pub fn set_edition(mut self, edition: Edition) -> Self {
+        self.edition = Some(edition);
+        self
+    }
+
+    /// Every file in the tree gets the header, submodules without their own header inherit it.
+    /// Writing refuses to overwrite files that are unmarked or were edited since generation
+    pub fn set_header(mut self, header: GeneratedHeader) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// How submodules are laid out on disk, submodules without their own layout inherit it.
+    /// Defaults to `ModuleLayout::ModRs`
+    pub fn set_layout(mut self, layout: ModuleLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Overwrite files even if their header shows they were edited by hand, applies to
+    /// submodules as well
+    pub fn force_overwrite(mut self) -> Self {
+        self.force_overwrite = true;
+        self
+    }
+
+    pub(crate) fn edition(&self) -> Edition {
+        self.edition.unwrap_or(self.mod_file.edition)
+    }
+
     pub fn add_module_file(
         mut self,
         visibility: Visibility,
@@ -64,47 +162,234 @@ impl ModuleBuilder {
         self
     }
 
-    pub fn add_submodule(mut self, visibility: Visibility, module_builder: ModuleBuilder) -> Self {
+    pub fn add_submodule(self, visibility: Visibility, module_builder: ModuleBuilder) -> Self {
+        self.add_submodule_with_annotations(visibility, module_builder, Annotations::empty())
+    }
+
+    /// Annotations go on the `mod` declaration, ex: a cfg gating the whole module
+    pub fn add_submodule_with_annotations(
+        mut self,
+        visibility: Visibility,
+        module_builder: ModuleBuilder,
+        annotations: Annotations,
+    ) -> Self {
         self.submodules.push(Submodule {
             visibility,
+            annotations,
             builder: module_builder,
         });
         self
     }
 
-    pub fn write_to_disk(self, root: impl AsRef<Path>) -> std::io::Result<()> {
+    /// Builds a tree from files keyed by their module path below `root`, ex: `["a", "b"]` for
+    /// `a::b`. Modules on a path without a file of their own get an empty one
+    pub fn nested(root: FileBuilder, mut files: BTreeMap<Vec<String>, FileBuilder>) -> Self {
+        Self::new(root).nest(&[], &mut files)
+    }
+
+    fn nest(mut self, path: &[String], files: &mut BTreeMap<Vec<String>, FileBuilder>) -> Self {
+        let children = files
+            .keys()
+            .filter(|p| p.len() > path.len() && p.starts_with(path))
+            .map(|p| p[path.len()].clone())
+            .collect::<BTreeSet<String>>();
+        for child in children {
+            let mut child_path = path.to_vec();
+            child_path.push(child.clone());
+            let fb = files
+                .remove(&child_path)
+                .unwrap_or_else(|| FileBuilder::new(&child));
+            if files.keys().any(|p| p.starts_with(&child_path)) {
+                self = self.add_submodule(
+                    Visibility::Public,
+                    ModuleBuilder::new(fb).nest(&child_path, files),
+                );
+            } else {
+                self = self.add_module_file(Visibility::Public, fb, Annotations::empty());
+            }
+        }
+        self
+    }
+
+    /// Writes the tree below `root`, unchanged files are not touched
+    pub fn write_to_disk(self, root: impl AsRef<Path>) -> Result<WriteReport> {
+        self.write_to_sink(&mut FileSystemSink::new(root.as_ref()))
+    }
+
+    /// Like `write_to_disk` but files generated by a previous run and no longer produced are
+    /// removed, tracked by a manifest in `root`
+    pub fn write_to_disk_tracked(self, root: impl AsRef<Path>) -> Result<WriteReport> {
+        self.write_to_sink(&mut FileSystemSink::new(root.as_ref()).track_manifest(DEFAULT_MANIFEST))
+    }
+
+    /// Renders the tree and hands every file to the sink, paths are relative to the module root
+    pub fn write_to_sink(self, sink: &mut impl OutputSink) -> Result<WriteReport> {
+        self.check_module_names()?;
+        let (rendered, guards) = self.render_guarded();
+        output::write_rendered(sink, rendered, &guards)
+    }
+
+    /// Header existing files are verified against before being overwritten
+    fn guard(&self) -> Option<GeneratedHeader> {
+        self.header.clone().filter(|_| !self.force_overwrite)
+    }
+
+    /// Renders the full tree to a map of paths relative to the module root and file contents
+    pub fn render(self) -> RenderedTree {
+        self.render_guarded().0
+    }
+
+    /// Renders the tree along with the header each file is guarded by, which is the header of
+    /// the module the file belongs to
+    pub(crate) fn render_guarded(self) -> (RenderedTree, BTreeMap<PathBuf, GeneratedHeader>) {
+        let mut rendered = RenderedTree::new();
+        let mut guards = BTreeMap::new();
+        let file = PathBuf::from(format!("{}.rs", self.mod_file.name));
+        if self.layout == Some(ModuleLayout::Inline) {
+            let header = self.header.clone();
+            if let Some(guard) = self.guard() {
+                guards.insert(file.clone(), guard);
+            }
+            let body = self.render_inline();
+            rendered.insert(
+                file,
+                match header {
+                    Some(header) => header.apply(&body),
+                    None => body,
+                },
+            );
+        } else {
+            self.render_into(file, Path::new(""), &mut rendered, &mut guards);
+        }
+        (rendered, guards)
+    }
+
+    /// Settings a parent has that the submodules didn't set themselves are pushed down
+    fn inherit_settings(&mut self) {
+        if let Some(edition) = self.edition {
+            self.mod_file.edition = edition;
+            for file in &mut self.module_files {
+                file.builder.edition = edition;
+            }
+        }
+        for submod in &mut self.submodules {
+            let builder = &mut submod.builder;
+            if let Some(edition) = self.edition {
+                builder.edition.get_or_insert(edition);
+            }
+            if let Some(header) = &self.header {
+                builder.header.get_or_insert_with(|| header.clone());
+            }
+            builder.force_overwrite |= self.force_overwrite;
+            // An inline module can't contain file backed ones
+            if self.layout == Some(ModuleLayout::Inline) {
+                builder.layout = Some(ModuleLayout::Inline);
+            } else if let Some(layout) = self.layout {
+                builder.layout.get_or_insert(layout);
+            }
+        }
+    }
+
+    fn render_into(
+        mut self,
+        file: PathBuf,
+        children: &Path,
+        rendered: &mut RenderedTree,
+        guards: &mut BTreeMap<PathBuf, GeneratedHeader>,
+    ) {
+        self.inherit_settings();
+        let with_header = |body: String| match &self.header {
+            Some(header) => header.apply(&body),
+            None => body,
+        };
+        // Files of submodules are guarded by their own header
+        let mut own_files = vec![file.clone()];
+        let edition = self.edition();
         let mut modules = vec![];
-        for file in &self.module_files {
-            std::fs::write(
-                root.as_ref().join(format!("{}.rs", file.builder.name)),
-                file.builder.format_file(),
-            )?;
+        for module_file in &self.module_files {
+            let mod_name = self.escape_name(&module_file.builder.name, edition);
+            let path = children.join(format!("{}.rs", module_file_stem(&mod_name)));
+            own_files.push(path.clone());
+            rendered.insert(path, with_header(module_file.builder.format_file()));
             modules.push(Module::new(
-                file.visibility,
-                &file.builder.name,
-                file.annotations.clone(),
+                module_file.visibility,
+                &mod_name,
+                module_file.annotations.clone(),
             ));
         }
-        for mut submod in self.submodules {
-            let mod_name_ref = &submod.builder.mod_file.name;
-            modules.push(Module::new(
+        for submod in std::mem::take(&mut self.submodules) {
+            let mod_name = self.escape_name(&submod.builder.mod_file.name, edition);
+            let stem = module_file_stem(&mod_name);
+            match submod.builder.layout {
+                Some(ModuleLayout::Inline) => modules.push(Module::new_inline(
+                    submod.visibility,
+                    &mod_name,
+                    submod.annotations.clone(),
+                    submod.builder.render_inline(),
+                )),
+                Some(ModuleLayout::NamedFile) => {
+                    modules.push(Module::new(
+                        submod.visibility,
+                        &mod_name,
+                        submod.annotations.clone(),
+                    ));
+                    submod.builder.render_into(
+                        children.join(format!("{stem}.rs")),
+                        &children.join(stem),
+                        rendered,
+                        guards,
+                    );
+                }
+                Some(ModuleLayout::ModRs) | None => {
+                    modules.push(Module::new(
+                        submod.visibility,
+                        &mod_name,
+                        submod.annotations.clone(),
+                    ));
+                    submod.builder.render_into(
+                        children.join(stem).join("mod.rs"),
+                        &children.join(stem),
+                        rendered,
+                        guards,
+                    );
+                }
+            }
+        }
+        if let Some(guard) = self.guard() {
+            guards.extend(own_files.into_iter().map(|path| (path, guard.clone())));
+        }
+        rendered.insert(file, with_header(self.mod_file.format_submodule(&modules)));
+    }
+
+    fn render_inline(mut self) -> String {
+        self.inherit_settings();
+        let edition = self.edition();
+        let mut modules = vec![];
+        for module_file in &self.module_files {
+            modules.push(Module::new_inline(
+                module_file.visibility,
+                self.escape_name(&module_file.builder.name, edition),
+                module_file.annotations.clone(),
+                module_file.builder.format_file(),
+            ));
+        }
+        for submod in std::mem::take(&mut self.submodules) {
+            modules.push(Module::new_inline(
                 submod.visibility,
-                mod_name_ref,
-                Annotations::empty(),
+                self.escape_name(&submod.builder.mod_file.name, edition),
+                submod.annotations,
+                submod.builder.render_inline(),
             ));
-            let new_path = root.as_ref().join(mod_name_ref);
-            std::fs::create_dir_all(&new_path)?;
-            submod.builder.mod_file.name = "mod".to_owned();
-            submod.builder.write_to_disk(&new_path)?;
         }
-        std::fs::write(
-            root.as_ref().join(format!("{}.rs", self.mod_file.name)),
-            self.mod_file.format_submodule(&modules),
-        )?;
-        Ok(())
+        self.mod_file.format_submodule(&modules)
     }
 }
 
+/// `mod r#type;` is looked up as `type.rs`
+fn module_file_stem(mod_name: &str) -> &str {
+    mod_name.trim_start_matches("r#")
+}
+
 struct OrderedFormat<T>
 where
     T: ToSourceFilePart,
@@ -122,6 +407,22 @@ where
     }
 }
 
+/// Items rendered for `edition`, paired with their position in the file
+fn ordered_parts<T>(
+    items: &[OrderedFormat<T>],
+    edition: Edition,
+) -> impl Iterator<Item = (usize, String)> + '_
+where
+    T: ToSourceFilePart,
+{
+    items.iter().map(move |i| {
+        (
+            i.order,
+            i.value.format_source_file_part_for_edition(edition),
+        )
+    })
+}
+
 impl ToSourceFilePart for String {
     fn format_source_file_part(&self) -> String {
         self.clone()
@@ -142,6 +443,7 @@ pub struct FileBuilder {
     implementations: Vec<OrderedFormat<ImplBuilder>>,
     macro_calls: Vec<OrderedFormat<String>>,
     parts: usize,
+    edition: Edition,
 }
 
 impl FileBuilder {
@@ -160,11 +462,18 @@ impl FileBuilder {
             implementations: vec![],
             macro_calls: vec![],
             parts: 0,
+            edition: Edition::default(),
         }
     }
 
     add_annotation!();
 
+    /// The edition decides which names need escaping when formatting
+    pub fn set_edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
     pub fn add_import(mut self, import: Import) -> Self {
         self.imports.push(OrderedFormat::new(self.parts, import));
         self.parts += 1;
@@ -266,57 +575,19 @@ impl FileBuilder {
     }
 
     pub fn format_submodule(&self, exposed_modules: &[Module]) -> String {
-        let mut formatted = self
-            .type_defs
-            .iter()
-            .map(|i| (i.order, i.value.format_source_file_part()))
-            .chain(
-                self.constants
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.functions
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.enums
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.structs
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.traits
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.container_structs
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.implementations
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.macro_calls
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
-            .chain(
-                self.imports
-                    .iter()
-                    .map(|i| (i.order, i.value.format_source_file_part())),
-            )
+        let edition = self.edition;
+        let mut formatted = ordered_parts(&self.type_defs, edition)
+            .chain(ordered_parts(&self.constants, edition))
+            .chain(ordered_parts(&self.functions, edition))
+            .chain(ordered_parts(&self.enums, edition))
+            .chain(ordered_parts(&self.structs, edition))
+            .chain(ordered_parts(&self.traits, edition))
+            .chain(ordered_parts(&self.container_structs, edition))
+            .chain(ordered_parts(&self.implementations, edition))
+            .chain(ordered_parts(&self.macro_calls, edition))
+            .chain(ordered_parts(&self.imports, edition))
             .collect::<Vec<(usize, String)>>();
-        formatted.sort_by(|a, b| a.0.cmp(&b.0));
+        formatted.sort_by_key(|a| a.0);
         std::iter::once(self.annotations.format())
             .chain(exposed_modules.iter().map(Module::format))
             .chain(formatted.into_iter().map(|(_, s)| s))
@@ -378,6 +649,7 @@ impl ConstantBuilder {
     }
 
     add_annotation!();
+    add_cfg!();
     set_visibility!();
 }
 
@@ -387,7 +659,7 @@ impl ToSourceFilePart for ConstantBuilder {
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct FunctionBuilder {
     annotations: Annotations,
     synchronicity: Synchronicity,
@@ -396,11 +668,16 @@ pub struct FunctionBuilder {
     args: Vec<Argument>,
     body: String,
     return_type: Option<ComponentSignature>,
+    escape_keywords: bool,
 }
 
 impl ToSourceFilePart for FunctionBuilder {
     fn format_source_file_part(&self) -> String {
-        self.clone().build().format()
+        self.format_source_file_part_for_edition(Edition::default())
+    }
+
+    fn format_source_file_part_for_edition(&self, edition: Edition) -> String {
+        self.clone().build(edition).format()
     }
 }
 
@@ -414,23 +691,36 @@ impl FunctionBuilder {
             args: vec![],
             body: "".into(),
             return_type: None,
+            escape_keywords: true,
         }
     }
 
     add_annotation!();
+    add_cfg!();
     set_async!();
     set_visibility!();
     add_argument!();
     set_body!();
     set_return_type!();
+    set_keyword_escaping!();
 
-    fn build(self) -> FunctionEntity {
+    fn build(self, edition: Edition) -> FunctionEntity {
+        let name = self.escape_name(&self.name, edition);
+        let args = self
+            .args
+            .iter()
+            .cloned()
+            .map(|mut arg| {
+                arg.named_sign.name = self.escape_name(&arg.named_sign.name, edition);
+                arg
+            })
+            .collect();
         FunctionEntity::new(
             self.annotations,
             self.synchronicity,
             self.visibility,
-            self.name,
-            self.args,
+            name,
+            args,
             self.body,
             self.return_type,
         )
@@ -444,11 +734,17 @@ pub struct EnumBuilder {
     visibility: Visibility,
     pub name: String,
     members: Vec<EnumMember>,
+    escape_keywords: bool,
+    rename_annotation: Option<String>,
 }
 
 impl ToSourceFilePart for EnumBuilder {
     fn format_source_file_part(&self) -> String {
-        self.clone().build().format()
+        self.format_source_file_part_for_edition(Edition::default())
+    }
+
+    fn format_source_file_part_for_edition(&self, edition: Edition) -> String {
+        self.clone().build(edition).format()
     }
 }
 
@@ -460,11 +756,25 @@ impl EnumBuilder {
             visibility: Visibility::Private,
             name: name.into(),
             members: vec![],
+            escape_keywords: true,
+            rename_annotation: None,
         }
     }
     add_annotation!();
+    add_cfg!();
     add_derive!();
     set_visibility!();
+    set_keyword_escaping!();
+    set_rename_annotation!();
+
+    /// Variants that will be renamed when rendered for `edition` as `(rendered, original)`
+    pub fn renamed_members(&self, edition: Edition) -> Vec<(String, String)> {
+        self.members
+            .iter()
+            .filter(|m| self.escape_keywords && escape_renames(&m.name))
+            .map(|m| (self.escape_name(&m.name, edition), m.name.clone()))
+            .collect()
+    }
 
     pub fn add_tag_member(mut self, name: impl Into<String>) -> Self {
         self.members.push(EnumMember::new(
@@ -475,6 +785,16 @@ impl EnumBuilder {
         self
     }
 
+    pub fn add_tag_member_with_annotations(
+        mut self,
+        name: impl Into<String>,
+        annotations: Annotations,
+    ) -> Self {
+        self.members
+            .push(EnumMember::new(name, MemberType::Empty(None), annotations));
+        self
+    }
+
     pub fn add_tag_member_with_value(
         mut self,
         name: impl Into<String>,
@@ -524,13 +844,30 @@ impl EnumBuilder {
         self
     }
 
-    fn build(self) -> EnumEntity {
+    fn build(self, edition: Edition) -> EnumEntity {
+        let members = self
+            .members
+            .iter()
+            .cloned()
+            .map(|mut member| {
+                if let Some(annotation) = self.rename_annotation_for(&member.name) {
+                    member.annotations.annotations.push(annotation);
+                }
+                member.name = self.escape_name(&member.name, edition);
+                if let MemberType::Pattern(patterns) = &mut member.member_type {
+                    for ncs in patterns {
+                        ncs.name = self.escape_name(&ncs.name, edition);
+                    }
+                }
+                member
+            })
+            .collect();
         EnumEntity::new(
             self.annotations,
             self.derives,
             self.visibility,
             self.name,
-            self.members,
+            members,
         )
     }
 }
@@ -558,6 +895,7 @@ impl TraitBuilder {
     }
 
     add_annotation!();
+    add_cfg!();
     set_visibility!();
 
     pub fn add_method(mut self, method_builder: MethodBuilder) -> Self {
@@ -576,12 +914,19 @@ impl TraitBuilder {
     }
 
     pub fn build(self) -> TraitEntity {
+        self.build_for_edition(Edition::default())
+    }
+
+    pub fn build_for_edition(self, edition: Edition) -> TraitEntity {
         TraitEntity::new(
             self.annotations,
             self.trait_type,
             self.super_traits,
             self.visibility,
-            self.methods.into_iter().map(MethodBuilder::build).collect(),
+            self.methods
+                .into_iter()
+                .map(|mb| mb.build(edition))
+                .collect(),
             self.types,
         )
     }
@@ -591,6 +936,10 @@ impl ToSourceFilePart for TraitBuilder {
     fn format_source_file_part(&self) -> String {
         self.clone().build().format()
     }
+
+    fn format_source_file_part_for_edition(&self, edition: Edition) -> String {
+        self.clone().build_for_edition(edition).format()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -600,6 +949,7 @@ pub struct ContainerStructBuilder {
     visibility: Visibility,
     pub name: String,
     contained_types: Vec<(Visibility, RustType)>,
+    unsupported_signature: Option<Signature>,
 }
 
 impl ContainerStructBuilder {
@@ -610,11 +960,27 @@ impl ContainerStructBuilder {
             visibility: Visibility::Private,
             name: name.into(),
             contained_types: vec![],
+            unsupported_signature: None,
         }
     }
 
+    /// A single generic signature has no type name to use,
+    /// the generic alias is used instead and the builder will fail validation
     pub fn new_from_signature(signature: &Signature) -> Self {
-        Self::new(&signature.rust_type().as_ref().unwrap().name)
+        let mut builder = Self::new(signature.get_any_alias());
+        if signature.rust_type().is_none() {
+            builder.unsupported_signature = Some(signature.clone());
+        }
+        builder
+    }
+
+    pub fn try_new_from_signature(signature: &Signature) -> Result<Self> {
+        signature
+            .rust_type()
+            .map(|rt| Self::new(&rt.name))
+            .ok_or_else(|| {
+                Error::UnsupportedSignature(signature.format(), "container struct".to_string())
+            })
     }
 
     pub fn add_contained(mut self, visibility: Visibility, rust_type: RustType) -> Self {
@@ -622,6 +988,7 @@ impl ContainerStructBuilder {
         self
     }
     add_annotation!();
+    add_cfg!();
     add_derive!();
     set_visibility!();
 
@@ -649,11 +1016,18 @@ pub struct StructBuilder {
     visibility: Visibility,
     pub name: String,
     fields: Vec<Field>,
+    unsupported_signature: Option<Signature>,
+    escape_keywords: bool,
+    rename_annotation: Option<String>,
 }
 
 impl ToSourceFilePart for StructBuilder {
     fn format_source_file_part(&self) -> String {
-        self.clone().build().format()
+        self.format_source_file_part_for_edition(Edition::default())
+    }
+
+    fn format_source_file_part_for_edition(&self, edition: Edition) -> String {
+        self.clone().build(edition).format()
     }
 }
 
@@ -665,16 +1039,46 @@ impl StructBuilder {
             visibility: Visibility::Private,
             name: name.into(),
             fields: vec![],
+            unsupported_signature: None,
+            escape_keywords: true,
+            rename_annotation: None,
         }
     }
 
+    /// A single generic signature has no type name to use,
+    /// the generic alias is used instead and the builder will fail validation
     pub fn new_from_signature(signature: &Signature) -> Self {
-        Self::new(&signature.rust_type().unwrap().name)
+        let mut builder = Self::new(signature.get_any_alias());
+        if signature.rust_type().is_none() {
+            builder.unsupported_signature = Some(signature.clone());
+        }
+        builder
+    }
+
+    pub fn try_new_from_signature(signature: &Signature) -> Result<Self> {
+        signature
+            .rust_type()
+            .map(|rt| Self::new(&rt.name))
+            .ok_or_else(|| Error::UnsupportedSignature(signature.format(), "struct".to_string()))
     }
 
     add_annotation!();
+    add_cfg!();
     add_derive!();
     set_visibility!();
+    set_keyword_escaping!();
+    set_rename_annotation!();
+
+    /// Fields that will be renamed when rendered for `edition` as `(rendered, original)`
+    pub fn renamed_fields(&self, edition: Edition) -> Vec<(String, String)> {
+        self.fields
+            .iter()
+            .map(|f| &f.named_component_signature.name)
+            .filter(|name| self.escape_keywords && escape_renames(name))
+            .map(|name| (self.escape_name(name, edition), name.clone()))
+            .collect()
+    }
+
     pub fn add_field(
         mut self,
         visibility: Visibility,
@@ -685,6 +1089,20 @@ impl StructBuilder {
         self
     }
 
+    pub fn add_field_with_annotations(
+        mut self,
+        visibility: Visibility,
+        named_component_signature: NamedComponentSignature,
+        annotations: Annotations,
+    ) -> Self {
+        self.fields.push(Field::new_with_annotations(
+            visibility,
+            named_component_signature,
+            annotations,
+        ));
+        self
+    }
+
     pub fn add_field_in_scope_simple_type(
         mut self,
         visibility: Visibility,
@@ -733,13 +1151,26 @@ impl StructBuilder {
         self
     }
 
-    fn build(self) -> StructEntity {
+    fn build(self, edition: Edition) -> StructEntity {
+        let fields = self
+            .fields
+            .iter()
+            .cloned()
+            .map(|mut field| {
+                let name = &field.named_component_signature.name;
+                if let Some(annotation) = self.rename_annotation_for(name) {
+                    field.annotations.annotations.push(annotation);
+                }
+                field.named_component_signature.name = self.escape_name(name, edition);
+                field
+            })
+            .collect();
         StructEntity::new(
             self.annotations,
             self.derives,
             self.visibility,
             self.name,
-            StructKind::Fields(self.fields),
+            StructKind::Fields(fields),
         )
     }
 }
@@ -756,7 +1187,11 @@ pub struct ImplBuilder {
 
 impl ToSourceFilePart for ImplBuilder {
     fn format_source_file_part(&self) -> String {
-        self.clone().build().format()
+        self.format_source_file_part_for_edition(Edition::default())
+    }
+
+    fn format_source_file_part_for_edition(&self, edition: Edition) -> String {
+        self.clone().build(edition).format()
     }
 }
 
@@ -772,6 +1207,9 @@ impl ImplBuilder {
         }
     }
 
+    add_annotation!();
+    add_cfg!();
+
     pub fn implement_for(mut self, implementing: Signature) -> Self {
         self.implementing = Some(implementing);
         self
@@ -837,7 +1275,7 @@ impl ImplBuilder {
         self
     }
 
-    fn build(self) -> ImplEntity {
+    fn build(self, edition: Edition) -> ImplEntity {
         ImplEntity::new(
             self.annotations,
             self.implementor,
@@ -846,7 +1284,7 @@ impl ImplBuilder {
             self.consts.into_iter().map(|cb| cb.build()).collect(),
             self.methods
                 .into_iter()
-                .map(|mb| mb.build())
+                .map(|mb| mb.build(edition))
                 .collect::<Vec<Method>>(),
         )
     }
@@ -862,6 +1300,7 @@ pub struct MethodBuilder {
     args: Vec<Argument>,
     body: Option<String>,
     return_type: Option<ComponentSignature>,
+    escape_keywords: bool,
 }
 
 impl MethodBuilder {
@@ -875,12 +1314,15 @@ impl MethodBuilder {
             args: vec![],
             body: Some("".to_string()),
             return_type: None,
+            escape_keywords: true,
         }
     }
 
     add_annotation!();
+    add_cfg!();
     set_visibility!();
     set_async!();
+    set_keyword_escaping!();
     pub fn set_self_ownership(mut self, ownership: Ownership) -> Self {
         self.self_ownership = Some(ownership);
         self
@@ -898,14 +1340,24 @@ impl MethodBuilder {
 
     set_return_type!();
 
-    fn build(self) -> Method {
+    fn build(self, edition: Edition) -> Method {
+        let name = self.escape_name(&self.name, edition);
+        let args = self
+            .args
+            .iter()
+            .cloned()
+            .map(|mut arg| {
+                arg.named_sign.name = self.escape_name(&arg.named_sign.name, edition);
+                arg
+            })
+            .collect();
         Method::new(
             self.annotations,
             self.visibility,
             self.synchronicity,
             self.self_ownership,
-            self.name,
-            self.args,
+            name,
+            args,
             Generics::default(),
             self.body,
             self.return_type,
@@ -916,8 +1368,12 @@ impl MethodBuilder {
 #[cfg(test)]
 mod tests {
     use crate::structures::gen_enum::NamedComponentSignature;
-    use crate::structures::{Ownership, Signature};
-    use crate::{Argument, ComponentSignature, FunctionBuilder, RustType};
+    use crate::structures::visibility::Visibility;
+    use crate::structures::{Ownership, Signature, ToSourceFilePart};
+    use crate::{
+        Argument, ComponentSignature, Edition, EnumBuilder, FileBuilder, FunctionBuilder, RustType,
+        StructBuilder,
+    };
 
     #[test]
     fn fb() {
@@ -930,7 +1386,63 @@ mod tests {
                 ),
             ))
             .add_argument_unbounded_generic(Ownership::Ref, "arg_2", "T")
-            .build();
+            .build(Edition::default());
         println!("{}", fb.format())
     }
+
+    #[test]
+    fn escapes_keywords() {
+        let sb = StructBuilder::new("MyStruct")
+            .add_field_in_scope_simple_type(Visibility::Public, "type", "String")
+            .add_field_in_scope_simple_type(Visibility::Public, "self", "i32")
+            .set_rename_annotation("serde(rename = \"{}\")");
+        assert_eq!(
+            vec![("self_".to_string(), "self".to_string())],
+            sb.renamed_fields(Edition::E2015)
+        );
+        assert_eq!(
+            "struct MyStruct {\npub r#type: String,\n#[serde(rename = \"self\")]\npub self_: i32,\n}\n",
+            sb.format_source_file_part()
+        );
+        let eb = EnumBuilder::new("MyEnum").add_tag_member("Self");
+        assert_eq!(
+            vec![("Self_".to_string(), "Self".to_string())],
+            eb.renamed_members(Edition::E2024)
+        );
+        assert_eq!("enum MyEnum {\n\nSelf_,\n}\n", eb.format_source_file_part());
+        let fb = FunctionBuilder::new("match")
+            .add_argument_in_scope_simple_type(Ownership::Owned, "in", "i32")
+            .disable_keyword_escaping();
+        assert_eq!("fn match(in: i32) {\n\n}\n", fb.format_source_file_part());
+        let fb = FunctionBuilder::new("match").add_argument_in_scope_simple_type(
+            Ownership::Owned,
+            "in",
+            "i32",
+        );
+        assert_eq!(
+            "fn r#match(r#in: i32) {\n\n}\n",
+            fb.format_source_file_part()
+        );
+    }
+
+    #[test]
+    fn escapes_for_edition() {
+        let file = || {
+            FileBuilder::new("my_file")
+                .add_function(FunctionBuilder::new("gen"))
+                .add_function(FunctionBuilder::new("async"))
+        };
+        assert_eq!(
+            "fn gen() {\n\n}\nfn r#async() {\n\n}\n",
+            file().format_file()
+        );
+        assert_eq!(
+            "fn r#gen() {\n\n}\nfn r#async() {\n\n}\n",
+            file().set_edition(Edition::E2024).format_file()
+        );
+        assert_eq!(
+            "fn gen() {\n\n}\nfn async() {\n\n}\n",
+            file().set_edition(Edition::E2015).format_file()
+        );
+    }
 }
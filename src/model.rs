@@ -0,0 +1,402 @@
+use crate::structures::gen_enum::NamedComponentSignature;
+use crate::structures::visibility::Visibility;
+use crate::structures::TypeDefDeclaration;
+use crate::structures::{Annotation, Annotations, Import, RustType, Signature, TypeDef};
+use crate::util::casing::{InferCase, RustCase};
+use crate::{EnumBuilder, FileBuilder, StructBuilder};
+
+/// The types the schema front-ends produce before they're turned into builders,
+/// everything is rendered as a serde model
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Model {
+    pub(crate) types: Vec<ModelType>,
+    /// Structs whose fields share a case get a serde `rename_all` instead of a rename per field
+    pub(crate) rename_all: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ModelType {
+    pub(crate) name: String,
+    pub(crate) doc: Option<String>,
+    pub(crate) kind: ModelKind,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum ModelKind {
+    Struct(Vec<ModelField>),
+    /// Unit variants named after their wire values
+    Enum(Vec<String>),
+    /// Untagged, the first variant that deserializes wins
+    Union(Vec<(String, TypeRef)>),
+    /// Told apart by a field holding the variant's wire name, ex: GraphQL's `__typename`,
+    /// or without a tag field by the wire name wrapping the variant, ex: an XML element.
+    /// Variants are named after the last segment of dotted names, ex: Avro's `com.acme.Money`,
+    /// suffixed when two share it
+    #[cfg(any(feature = "graphql", feature = "xsd", feature = "avro"))]
+    Tagged(Option<String>, Vec<(String, TypeRef)>),
+    Alias(TypeRef),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ModelField {
+    /// As it appears on the wire
+    pub(crate) name: String,
+    pub(crate) ty: TypeRef,
+    /// May be absent, rendered as an `Option` that isn't serialized when `None`
+    pub(crate) optional: bool,
+    /// Filled with its `Default` when absent, ex: a list that may be left out
+    pub(crate) default: bool,
+    pub(crate) doc: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum TypeRef {
+    Builtin(String),
+    Named(String),
+    Vec(Box<TypeRef>),
+    Map(Box<TypeRef>),
+    Option(Box<TypeRef>),
+    Boxed(Box<TypeRef>),
+}
+
+impl TypeRef {
+    pub(crate) fn builtin(name: impl Into<String>) -> Self {
+        TypeRef::Builtin(name.into())
+    }
+
+    pub(crate) fn json_value() -> Self {
+        TypeRef::builtin("serde_json::Value")
+    }
+
+    pub(crate) fn vec(inner: TypeRef) -> Self {
+        TypeRef::Vec(Box::new(inner))
+    }
+
+    pub(crate) fn map(inner: TypeRef) -> Self {
+        TypeRef::Map(Box::new(inner))
+    }
+
+    pub(crate) fn option(inner: TypeRef) -> Self {
+        match inner {
+            TypeRef::Option(_) => inner,
+            inner => TypeRef::Option(Box::new(inner)),
+        }
+    }
+
+    pub(crate) fn boxed(inner: TypeRef) -> Self {
+        TypeRef::Boxed(Box::new(inner))
+    }
+
+    /// Named types as seen from another module, ex: `models::User`
+    #[cfg(any(feature = "openapi", feature = "graphql"))]
+    pub(crate) fn qualified(self, module: &str) -> Self {
+        match self {
+            TypeRef::Builtin(_) => self,
+            TypeRef::Named(name) => TypeRef::Named(format!("{module}::{name}")),
+            TypeRef::Vec(inner) => TypeRef::vec(inner.qualified(module)),
+            TypeRef::Map(inner) => TypeRef::map(inner.qualified(module)),
+            TypeRef::Option(inner) => TypeRef::option(inner.qualified(module)),
+            TypeRef::Boxed(inner) => TypeRef::boxed(inner.qualified(module)),
+        }
+    }
+
+    pub(crate) fn rust_type(&self) -> RustType {
+        match self {
+            TypeRef::Builtin(name) | TypeRef::Named(name) => RustType::in_scope(name),
+            TypeRef::Vec(inner) => RustType::in_scope("Vec").wrap(inner.rust_type()),
+            TypeRef::Map(inner) => {
+                RustType::in_scope(format!("HashMap<String, {}>", inner.rust_type().format()))
+            }
+            TypeRef::Option(inner) => RustType::in_scope("Option").wrap(inner.rust_type()),
+            TypeRef::Boxed(inner) => RustType::in_scope("Box").wrap(inner.rust_type()),
+        }
+    }
+
+    pub(crate) fn uses_map(&self) -> bool {
+        match self {
+            TypeRef::Builtin(_) | TypeRef::Named(_) => false,
+            TypeRef::Map(_) => true,
+            TypeRef::Vec(inner) | TypeRef::Option(inner) | TypeRef::Boxed(inner) => {
+                inner.uses_map()
+            }
+        }
+    }
+}
+
+impl Model {
+    fn type_refs(&self) -> impl Iterator<Item = &TypeRef> {
+        self.types
+            .iter()
+            .flat_map(|t| -> Box<dyn Iterator<Item = &TypeRef>> {
+                match &t.kind {
+                    ModelKind::Struct(fields) => Box::new(fields.iter().map(|f| &f.ty)),
+                    ModelKind::Enum(_) => Box::new(std::iter::empty()),
+                    ModelKind::Union(variants) => Box::new(variants.iter().map(|(_, ty)| ty)),
+                    #[cfg(any(feature = "graphql", feature = "xsd", feature = "avro"))]
+                    ModelKind::Tagged(_, variants) => Box::new(variants.iter().map(|(_, ty)| ty)),
+                    ModelKind::Alias(ty) => Box::new(std::iter::once(ty)),
+                }
+            })
+    }
+
+    pub(crate) fn into_file_builder(self, name: impl Into<String>) -> FileBuilder {
+        let mut fb = FileBuilder::new(name);
+        if self.type_refs().any(TypeRef::uses_map) {
+            fb = fb.add_import(Import::spec("std::collections::HashMap"));
+        }
+        if self
+            .types
+            .iter()
+            .any(|t| !matches!(t.kind, ModelKind::Alias(_)))
+        {
+            fb = fb.add_import(Import::spec("serde::{Deserialize, Serialize}"));
+        }
+        for model_type in self.types {
+            fb = model_type.add_to(fb, self.rename_all);
+        }
+        fb
+    }
+}
+
+const DERIVES: &str = "Debug, Clone, PartialEq, Serialize, Deserialize";
+
+impl ModelType {
+    fn add_to(self, fb: FileBuilder, rename_all: bool) -> FileBuilder {
+        match self.kind {
+            ModelKind::Struct(fields) => {
+                let mut sb = StructBuilder::new(&self.name).set_visibility(Visibility::Public);
+                for annotation in doc_annotation(self.doc.as_deref()) {
+                    sb = sb.add_annotation(annotation);
+                }
+                sb = sb.add_simple_annotation(format!("derive({DERIVES})"));
+                let rule = match rename_all {
+                    true => RenameRule::shared(fields.iter().map(|f| f.name.as_str())),
+                    false => None,
+                };
+                if let Some(rule) = rule {
+                    sb = sb.add_simple_annotation(format!("serde(rename_all = {:?})", rule.name()));
+                }
+                let rust_names = unique_names(
+                    fields
+                        .iter()
+                        .map(|f| RustCase::convert_lossy(&f.name, RustCase::Snake)),
+                );
+                for (field, rust_name) in fields.into_iter().zip(rust_names) {
+                    let mut annotations = doc_annotation(field.doc.as_deref());
+                    // serde strips the `r#` of raw identifiers
+                    let serde_name = rust_name.trim_start_matches("r#");
+                    let serde_name = match rule {
+                        Some(rule) => rule.apply(serde_name),
+                        None => serde_name.to_string(),
+                    };
+                    if serde_name != field.name {
+                        annotations.push(serde_rename(&field.name));
+                    }
+                    if field.default {
+                        annotations.push(Annotation::new("serde(default)"));
+                    }
+                    let ty = if field.optional {
+                        annotations.push(Annotation::new(
+                            "serde(skip_serializing_if = \"Option::is_none\")",
+                        ));
+                        TypeRef::option(field.ty)
+                    } else {
+                        field.ty
+                    };
+                    sb = sb.add_field_with_annotations(
+                        Visibility::Public,
+                        NamedComponentSignature::new_simple_type(rust_name, ty.rust_type()),
+                        Annotations::new(annotations),
+                    );
+                }
+                fb.add_struct(sb)
+            }
+            ModelKind::Enum(values) => {
+                let mut eb = EnumBuilder::new(&self.name).set_visibility(Visibility::Public);
+                for annotation in doc_annotation(self.doc.as_deref()) {
+                    eb = eb.add_annotation(annotation);
+                }
+                eb = eb.add_simple_annotation(format!("derive(Copy, Eq, Hash, {DERIVES})"));
+                for value in unique_names(
+                    values
+                        .iter()
+                        .map(|v| RustCase::convert_lossy(v, RustCase::Pascal)),
+                )
+                .into_iter()
+                .zip(&values)
+                {
+                    eb = match value {
+                        (name, value) if name == *value => eb.add_tag_member(name),
+                        (name, value) => eb.add_tag_member_with_annotations(
+                            name,
+                            Annotations::new(vec![serde_rename(value)]),
+                        ),
+                    };
+                }
+                fb.add_enum(eb)
+            }
+            ModelKind::Union(variants) => {
+                let mut eb = EnumBuilder::new(&self.name).set_visibility(Visibility::Public);
+                for annotation in doc_annotation(self.doc.as_deref()) {
+                    eb = eb.add_annotation(annotation);
+                }
+                eb = eb
+                    .add_simple_annotation(format!("derive({DERIVES})"))
+                    .add_simple_annotation("serde(untagged)");
+                for (name, ty) in variants {
+                    eb = eb.add_type_member(name, Signature::simple(ty.rust_type()));
+                }
+                fb.add_enum(eb)
+            }
+            #[cfg(any(feature = "graphql", feature = "xsd", feature = "avro"))]
+            ModelKind::Tagged(tag, variants) => {
+                let mut eb = EnumBuilder::new(&self.name).set_visibility(Visibility::Public);
+                for annotation in doc_annotation(self.doc.as_deref()) {
+                    eb = eb.add_annotation(annotation);
+                }
+                eb = eb.add_simple_annotation(format!("derive({DERIVES})"));
+                if let Some(tag) = tag {
+                    eb = eb.add_simple_annotation(format!("serde(tag = {tag:?})"));
+                }
+                let names = unique_names(variants.iter().map(|(wire, _)| {
+                    let local = wire.rsplit('.').next().unwrap_or(wire);
+                    RustCase::convert_lossy(local, RustCase::Pascal)
+                }));
+                for ((wire, ty), name) in variants.into_iter().zip(names) {
+                    let signature = Signature::simple(ty.rust_type());
+                    eb = match name == wire {
+                        true => eb.add_type_member(name, signature),
+                        false => eb.add_type_member_with_annotations(
+                            name,
+                            signature,
+                            Annotations::new(vec![serde_rename(&wire)]),
+                        ),
+                    };
+                }
+                fb.add_enum(eb)
+            }
+            ModelKind::Alias(ty) => fb.add_type_def(TypeDef::Const(TypeDefDeclaration::new(
+                Visibility::Public,
+                NamedComponentSignature::new_simple_type(self.name, ty.rust_type()),
+            ))),
+        }
+    }
+}
+
+/// The serde `rename_all` rules for fields that aren't snake case
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RenameRule {
+    Camel,
+    Pascal,
+    Kebab,
+    Scream,
+    Cobol,
+}
+
+impl RenameRule {
+    /// The case all multi-word names share, single words fit any case
+    fn shared<'a>(names: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut shared = None;
+        for name in names {
+            let rule = match InferCase::infer(name) {
+                Ok(InferCase::Camel) => RenameRule::Camel,
+                Ok(InferCase::Pascal) => RenameRule::Pascal,
+                Ok(InferCase::Kebab) => RenameRule::Kebab,
+                Ok(InferCase::Scream) => RenameRule::Scream,
+                Ok(InferCase::Cobol) => RenameRule::Cobol,
+                Ok(InferCase::AllCaps | InferCase::SafeChunk) => continue,
+                _ => return None,
+            };
+            if shared.is_some_and(|shared| shared != rule) {
+                return None;
+            }
+            shared = Some(rule);
+        }
+        shared
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            RenameRule::Camel => "camelCase",
+            RenameRule::Pascal => "PascalCase",
+            RenameRule::Kebab => "kebab-case",
+            RenameRule::Scream => "SCREAMING_SNAKE_CASE",
+            RenameRule::Cobol => "SCREAMING-KEBAB-CASE",
+        }
+    }
+
+    /// Same as serde, a field that doesn't come out as its wire name still needs a rename
+    fn apply(self, field: &str) -> String {
+        match self {
+            RenameRule::Camel | RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for c in field.chars() {
+                    if c == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.extend(c.to_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(c);
+                    }
+                }
+                let mut chars = pascal.chars();
+                match (self, chars.next()) {
+                    (RenameRule::Camel, Some(first)) => first.to_lowercase().chain(chars).collect(),
+                    _ => pascal,
+                }
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::Scream => field.to_uppercase(),
+            RenameRule::Cobol => field.to_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+fn serde_rename(name: &str) -> Annotation {
+    Annotation::new(format!("serde(rename = {name:?})"))
+}
+
+pub(crate) fn doc_annotation(doc: Option<&str>) -> Vec<Annotation> {
+    doc.map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| Annotation::new(format!("doc = {d:?}")))
+        .into_iter()
+        .collect()
+}
+
+/// Names the generated code refers to, a type named after one would shadow it
+pub(crate) const RESERVED_NAMES: [&str; 7] = [
+    "Box",
+    "Deserialize",
+    "HashMap",
+    "Option",
+    "Serialize",
+    "String",
+    "Vec",
+];
+
+/// Suffixes repeated names with a counter, ex: two schemas both named `Item`
+pub(crate) fn unique_names(names: impl IntoIterator<Item = String>) -> Vec<String> {
+    unique_names_in(&mut std::collections::BTreeSet::new(), names)
+}
+
+/// Like `unique_names`, also keeping clear of the names in `taken` and adding the results to it
+pub(crate) fn unique_names_in(
+    taken: &mut std::collections::BTreeSet<String>,
+    names: impl IntoIterator<Item = String>,
+) -> Vec<String> {
+    names
+        .into_iter()
+        .map(|name| {
+            let mut candidate = name.clone();
+            let mut counter = 2;
+            while !taken.insert(candidate.clone()) {
+                candidate = format!("{name}{counter}");
+                counter += 1;
+            }
+            candidate
+        })
+        .collect()
+}
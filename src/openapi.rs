@@ -0,0 +1,1014 @@
+use crate::errors::{Error, Result};
+use crate::json_schema::{escape_pointer, read_document, Converter, JsonSchema};
+use crate::model::{unique_names_in, TypeRef, RESERVED_NAMES};
+use crate::structures::gen_enum::NamedComponentSignature;
+use crate::structures::generics::{Bound, Bounds, Generic, Generics};
+use crate::structures::method::Argument;
+use crate::structures::visibility::Visibility;
+use crate::structures::{
+    Annotation, Annotations, ComponentSignature, Import, Ownership, RustType, Signature,
+    Synchronicity, TypeDef, TypeDefDeclaration,
+};
+use crate::util::casing::RustCase;
+use crate::{
+    EnumBuilder, FileBuilder, FunctionBuilder, ImplBuilder, MethodBuilder, ModuleBuilder,
+    StructBuilder, TraitBuilder,
+};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Generates a client and its models from an OpenAPI 3.0/3.1 document in YAML or JSON.
+/// Schemas become serde models in a `models` module, see `JsonSchema` for what's supported.
+/// Every operation becomes a method on the client returning the decoded success response
+/// or an error enum of the operation's other documented responses.
+///
+/// The client sends requests through a generated `Transport` trait rather than an HTTP library,
+/// so it works with any of them and can be tested against an in-process mock.
+/// The generated code needs `serde` with `derive` and `serde_json`
+pub struct OpenApi {
+    schema: JsonSchema,
+    client_name: String,
+    synchronicity: Synchronicity,
+}
+
+impl OpenApi {
+    /// `$ref`s to other files are resolved relative to the document
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let root = path.as_ref().canonicalize()?;
+        let document = read_document(&root)?;
+        Self::new(root, document)
+    }
+
+    /// YAML or JSON, files referenced from the document are looked up relative to the working
+    /// directory, see `set_base_dir`
+    pub fn parse(source: &str) -> Result<Self> {
+        let document = parse_yaml("openapi", source)?;
+        Self::new(PathBuf::from("openapi.json"), document)
+    }
+
+    fn new(root: PathBuf, document: Value) -> Result<Self> {
+        let version = document.get("openapi").and_then(Value::as_str);
+        if !version.is_some_and(|v| v.starts_with("3.")) {
+            return Err(Error::Schema(
+                root.display().to_string(),
+                format!("expected an OpenAPI 3 document, found version {version:?}"),
+            ));
+        }
+        Ok(Self {
+            schema: JsonSchema::from_document(root, "openapi".to_string(), document),
+            client_name: "Client".to_string(),
+            synchronicity: Synchronicity::Sync,
+        })
+    }
+
+    /// Directory the files referenced from a parsed document are relative to
+    pub fn set_base_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.schema = self.schema.set_base_dir(dir);
+        self
+    }
+
+    /// Defaults to `Client`
+    pub fn set_client_name(mut self, name: impl Into<String>) -> Self {
+        self.client_name = name.into();
+        self
+    }
+
+    set_async!();
+
+    /// The module's own file has the client, transport and errors, the models are in a
+    /// `models` module file
+    pub fn into_module(self, name: impl Into<String>) -> Result<ModuleBuilder> {
+        let root = self.schema.root().to_path_buf();
+        let mut converter = Converter::new(self.schema);
+        let document = converter.load(&root)?;
+        // Named first, inline schemas of operations that clash get a suffix instead
+        if let Some(Value::Object(schemas)) = document.pointer("/components/schemas") {
+            for key in schemas.keys() {
+                let pointer = format!("/components/schemas/{}", escape_pointer(key));
+                converter.reference(&root, &pointer, key, false)?;
+            }
+        }
+        let mut found = vec![];
+        if let Some(Value::Object(paths)) = document.get("paths") {
+            for (path, item) in paths {
+                let (item_doc, item) = converter.follow(&root, item)?;
+                for method in METHODS {
+                    if let Some(operation) = item.get(method) {
+                        found.push((
+                            path.clone(),
+                            method,
+                            item_doc.clone(),
+                            item.clone(),
+                            operation.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+        // Methods sit next to the client's own `new` and `transport`
+        let mut methods = BTreeSet::from(["new", "transport"].map(String::from));
+        let names = unique_names_in(
+            &mut methods,
+            found.iter().map(|(path, method, _, _, operation)| {
+                match operation.get("operationId").and_then(Value::as_str) {
+                    Some(id) => RustCase::convert_lossy(id, RustCase::Snake),
+                    None => RustCase::convert_lossy(&format!("{method} {path}"), RustCase::Snake),
+                }
+            }),
+        );
+        let mut operations = vec![];
+        for (name, (path, method, doc, item, operation)) in names.into_iter().zip(found) {
+            operations.push(Operation::read(
+                &mut converter,
+                &doc,
+                name,
+                &path,
+                method,
+                &item,
+                &operation,
+            )?);
+        }
+        // Error enums share the client file with the transport types and the client itself
+        let mut types = BTreeSet::from(RESERVED_NAMES.map(String::from));
+        types.extend(["Request", "Response", "Transport", "TransportError"].map(String::from));
+        types.insert(self.client_name.clone());
+        let error_names = unique_names_in(
+            &mut types,
+            operations
+                .iter()
+                .map(|operation| operation.error_name.clone()),
+        );
+        for (operation, error_name) in operations.iter_mut().zip(error_names) {
+            operation.error_name = error_name;
+        }
+        let models = converter.into_model().into_file_builder("models");
+        let client = ClientWriter {
+            client_name: self.client_name,
+            synchronicity: self.synchronicity,
+        }
+        .write(FileBuilder::new(name), &operations);
+        Ok(ModuleBuilder::new(client).add_module_file(
+            Visibility::Public,
+            models,
+            Annotations::empty(),
+        ))
+    }
+}
+
+/// YAML allows keys that aren't strings, ex: unquoted status codes, those are stringified
+pub(crate) fn parse_yaml(location: &str, source: &str) -> Result<Value> {
+    let yaml = serde_yaml::from_str::<serde_yaml::Value>(source)
+        .map_err(|e| Error::Schema(location.to_string(), e.to_string()))?;
+    Ok(yaml_to_json(yaml))
+}
+
+fn yaml_to_json(yaml: serde_yaml::Value) -> Value {
+    match yaml {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                n.as_f64()
+                    .and_then(serde_json::Number::from_f64)
+                    .map_or(Value::Null, Value::Number)
+            }
+        }
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(seq) => {
+            Value::Array(seq.into_iter().map(yaml_to_json).collect())
+        }
+        serde_yaml::Value::Mapping(mapping) => Value::Object(
+            mapping
+                .into_iter()
+                .map(|(k, v)| {
+                    let key = match yaml_to_json(k) {
+                        Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    (key, yaml_to_json(v))
+                })
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+enum Status {
+    Code(u16),
+    /// `4XX`
+    Range(u16),
+    Default,
+}
+
+impl Status {
+    fn parse(status: &str) -> Option<Status> {
+        match status {
+            "default" => Some(Status::Default),
+            _ => match status
+                .strip_suffix("XX")
+                .or_else(|| status.strip_suffix("xx"))
+            {
+                Some(class) => class.parse().ok().map(Status::Range),
+                None => status.parse().ok().map(Status::Code),
+            },
+        }
+    }
+
+    fn is_success(self) -> bool {
+        match self {
+            Status::Code(code) => (200..300).contains(&code),
+            Status::Range(class) => class == 2,
+            Status::Default => false,
+        }
+    }
+
+    fn pattern(self) -> String {
+        match self {
+            Status::Code(code) => code.to_string(),
+            Status::Range(class) => format!("{class}00..={class}99"),
+            Status::Default => "_".to_string(),
+        }
+    }
+
+    fn variant(self) -> String {
+        match self {
+            Status::Code(code) => format!("Status{code}"),
+            Status::Range(class) => format!("Status{class}xx"),
+            Status::Default => "Default".to_string(),
+        }
+    }
+}
+
+/// A request or response body, JSON is decoded into `ty`, anything else is a `String`
+#[derive(Debug, Clone, PartialEq)]
+enum Content {
+    Empty,
+    Json(TypeRef),
+    Raw,
+}
+
+impl Content {
+    fn rust_type(&self) -> String {
+        match self {
+            Content::Empty => "()".to_string(),
+            Content::Json(ty) => ty.rust_type().format(),
+            Content::Raw => "String".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ParamLocation {
+    Path,
+    Query,
+    Header,
+}
+
+struct Param {
+    name: String,
+    rust_name: String,
+    location: ParamLocation,
+    ty: TypeRef,
+    required: bool,
+}
+
+struct Operation {
+    name: String,
+    error_name: String,
+    method: String,
+    path: String,
+    doc: Option<String>,
+    params: Vec<Param>,
+    body: Option<(Content, bool)>,
+    responses: Vec<(Status, Content)>,
+}
+
+impl Operation {
+    fn read(
+        converter: &mut Converter,
+        doc: &Path,
+        name: String,
+        path: &str,
+        method: &str,
+        item: &Value,
+        operation: &Value,
+    ) -> Result<Self> {
+        let type_name = RustCase::convert_lossy(&name, RustCase::Pascal);
+        let mut params: Vec<Param> = vec![];
+        let declared = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .chain(
+                operation
+                    .get("parameters")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten(),
+            );
+        for param in declared {
+            let (param_doc, param) = converter.follow(doc, param)?;
+            let Some(param_name) = param.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let location = match param.get("in").and_then(Value::as_str) {
+                Some("path") => ParamLocation::Path,
+                Some("query") => ParamLocation::Query,
+                Some("header") => ParamLocation::Header,
+                // Cookies are left to the transport
+                _ => continue,
+            };
+            let hint = format!(
+                "{type_name}{}",
+                RustCase::convert_lossy(param_name, RustCase::Pascal)
+            );
+            let ty = match param.get("schema") {
+                Some(schema) => converter.type_of(&param_doc, schema, &hint, false)?,
+                None => TypeRef::builtin("String"),
+            };
+            let param = Param {
+                name: param_name.to_string(),
+                rust_name: RustCase::convert_lossy(param_name, RustCase::Snake),
+                location,
+                ty,
+                required: location == ParamLocation::Path
+                    || param.get("required").and_then(Value::as_bool) == Some(true),
+            };
+            // Operation parameters override the path's
+            params.retain(|p| p.name != param.name || p.location != param.location);
+            params.push(param);
+        }
+        let mut rest = path;
+        while let Some((_, after)) = rest.split_once('{') {
+            let Some((template_name, after)) = after.split_once('}') else {
+                break;
+            };
+            if !params
+                .iter()
+                .any(|p| p.location == ParamLocation::Path && p.name == template_name)
+            {
+                return Err(Error::Schema(
+                    format!("{} {path}", method.to_uppercase()),
+                    format!("operation {name} doesn't declare path parameter {template_name}"),
+                ));
+            }
+            rest = after;
+        }
+        // The generated body declares `request` and calls `param_string`, `body` is an argument
+        let mut taken = BTreeSet::from(["body", "request", "param_string"].map(String::from));
+        for param in &mut params {
+            let base = param.rust_name.clone();
+            let mut counter = 2;
+            while !taken.insert(param.rust_name.clone()) {
+                param.rust_name = format!("{base}{counter}");
+                counter += 1;
+            }
+        }
+        let body = match operation.get("requestBody") {
+            Some(body) => {
+                let (body_doc, body) = converter.follow(doc, body)?;
+                let content =
+                    read_content(converter, &body_doc, &body, &format!("{type_name}Request"))?;
+                let required = body.get("required").and_then(Value::as_bool) == Some(true);
+                Some((content, required))
+            }
+            None => None,
+        };
+        let mut responses = vec![];
+        if let Some(Value::Object(declared)) = operation.get("responses") {
+            for (status, response) in declared {
+                let Some(status) = Status::parse(status) else {
+                    continue;
+                };
+                let (response_doc, response) = converter.follow(doc, response)?;
+                let hint = if status.is_success() {
+                    format!("{type_name}Response")
+                } else {
+                    format!("{type_name}{}", status.variant())
+                };
+                responses.push((
+                    status,
+                    read_content(converter, &response_doc, &response, &hint)?,
+                ));
+            }
+        }
+        // Exact codes before ranges before the default so every arm is reachable
+        responses.sort_by_key(|(status, _)| *status);
+        Ok(Self {
+            name,
+            error_name: format!("{type_name}Error"),
+            method: method.to_uppercase(),
+            path: path.to_string(),
+            doc: operation
+                .get("summary")
+                .or_else(|| operation.get("description"))
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            params,
+            body,
+            responses,
+        })
+    }
+
+    fn success(&self) -> Content {
+        self.responses
+            .iter()
+            .find(|(status, _)| status.is_success())
+            .map(|(_, content)| content.clone())
+            .unwrap_or(Content::Empty)
+    }
+
+    /// Responses that end up as error variants, a success response with a different body than
+    /// the first one does too
+    fn errors(&self) -> Vec<&(Status, Content)> {
+        let success = self.success();
+        self.responses
+            .iter()
+            .filter(|(status, content)| !status.is_success() || *content != success)
+            .collect()
+    }
+}
+
+fn read_content(
+    converter: &mut Converter,
+    doc: &Path,
+    holder: &Value,
+    hint: &str,
+) -> Result<Content> {
+    let Some(Value::Object(media_types)) = holder.get("content") else {
+        return Ok(Content::Empty);
+    };
+    let json = media_types
+        .iter()
+        .find(|(media_type, _)| media_type.contains("json"));
+    match json {
+        Some((_, media)) => match media.get("schema") {
+            Some(schema) => Ok(Content::Json(
+                converter
+                    .type_of(doc, schema, hint, false)?
+                    .qualified("models"),
+            )),
+            None => Ok(Content::Json(TypeRef::json_value())),
+        },
+        None if media_types.is_empty() => Ok(Content::Empty),
+        None => Ok(Content::Raw),
+    }
+}
+
+struct ClientWriter {
+    client_name: String,
+    synchronicity: Synchronicity,
+}
+
+impl ClientWriter {
+    fn write(&self, mut fb: FileBuilder, operations: &[Operation]) -> FileBuilder {
+        let uses_map = operations.iter().any(|op| {
+            op.params.iter().any(|p| p.ty.uses_map())
+                || op
+                    .responses
+                    .iter()
+                    .map(|(_, content)| content)
+                    .chain(op.body.iter().map(|(content, _)| content))
+                    .any(|content| matches!(content, Content::Json(ty) if ty.uses_map()))
+        });
+        if uses_map {
+            fb = fb.add_import(Import::spec("std::collections::HashMap"));
+        }
+        fb = self.write_transport(fb);
+        let transport = Generics::single(Generic::bounded(
+            "T",
+            Bounds::single(Bound::required(RustType::in_scope("Transport"))),
+        ));
+        fb = fb.add_struct(
+            StructBuilder::new(&self.client_name)
+                .set_visibility(Visibility::Public)
+                .add_field_unbounded_generic(Visibility::Private, "transport", "T"),
+        );
+        let mut client = ImplBuilder::new(Signature::generic_container(
+            RustType::in_scope(&self.client_name),
+            transport,
+        ))
+        .add_method(
+            MethodBuilder::new("new")
+                .set_visibility(Visibility::Public)
+                .add_argument(Argument::new(
+                    Ownership::Owned,
+                    NamedComponentSignature::new_unbounded_generic("transport", "T"),
+                ))
+                .set_return_type(simple_signature("Self"))
+                .set_body("Self { transport }"),
+        )
+        .add_method(
+            MethodBuilder::new("transport")
+                .set_visibility(Visibility::Public)
+                .set_self_ownership(Ownership::Ref)
+                .set_return_type(simple_signature("&T"))
+                .set_body("&self.transport"),
+        );
+        for operation in operations {
+            client = client.add_method(self.operation_method(operation));
+        }
+        fb = fb.add_impl(client);
+        for operation in operations {
+            fb = write_error(fb, operation);
+        }
+        if operations.iter().any(|op| !op.params.is_empty()) {
+            fb = fb.add_function(
+                FunctionBuilder::new("param_string")
+                    .add_argument(Argument::new(
+                        Ownership::Ref,
+                        NamedComponentSignature::new_simple_type(
+                            "value",
+                            RustType::in_scope("impl serde::Serialize"),
+                        ),
+                    ))
+                    .set_return_type(simple_signature("String"))
+                    .set_body(
+                        "    match serde_json::to_value(value) {\n        \
+                        Ok(serde_json::Value::String(s)) => s,\n        \
+                        Ok(other) => other.to_string(),\n        \
+                        Err(_) => String::new(),\n    \
+                        }",
+                    ),
+            );
+        }
+        fb
+    }
+
+    fn write_transport(&self, fb: FileBuilder) -> FileBuilder {
+        let request = StructBuilder::new("Request")
+            .set_visibility(Visibility::Public)
+            .add_derive_in_scope("Debug")
+            .add_derive_in_scope("Clone")
+            .add_derive_in_scope("PartialEq")
+            .add_field_in_scope_simple_type(Visibility::Public, "method", "&'static str")
+            .add_field_in_scope_simple_type(Visibility::Public, "path", "String")
+            .add_field_in_scope_simple_type(Visibility::Public, "query", "Vec<(String, String)>")
+            .add_field_in_scope_simple_type(Visibility::Public, "headers", "Vec<(String, String)>")
+            .add_field_in_scope_simple_type(Visibility::Public, "body", "Option<String>");
+        let response = StructBuilder::new("Response")
+            .set_visibility(Visibility::Public)
+            .add_derive_in_scope("Debug")
+            .add_derive_in_scope("Clone")
+            .add_derive_in_scope("PartialEq")
+            .add_field_in_scope_simple_type(Visibility::Public, "status", "u16")
+            .add_field_in_scope_simple_type(Visibility::Public, "body", "String");
+        let mut send = MethodBuilder::new("send")
+            .set_self_ownership(Ownership::Ref)
+            .add_argument(Argument::new(
+                Ownership::Owned,
+                NamedComponentSignature::new_simple_type("request", RustType::in_scope("Request")),
+            ))
+            .set_return_type(simple_signature("Result<Response, TransportError>"))
+            .set_trait_no_body();
+        let mut transport = TraitBuilder::new(Signature::simple(RustType::in_scope("Transport")))
+            .set_visibility(Visibility::Public)
+            .add_annotation(Annotation::new(
+                "doc = \"Sends requests for the client, paths are relative to the server\"",
+            ));
+        if self.synchronicity == Synchronicity::Async {
+            send = send.set_async();
+            transport = transport.add_simple_annotation("allow(async_fn_in_trait)");
+        }
+        fb.add_struct(request)
+            .add_struct(response)
+            .add_type_def(TypeDef::Const(TypeDefDeclaration::new(
+                Visibility::Public,
+                NamedComponentSignature::new_simple_type(
+                    "TransportError",
+                    RustType::in_scope("Box<dyn std::error::Error + Send + Sync>"),
+                ),
+            )))
+            .add_trait(transport.add_method(send))
+    }
+
+    fn operation_method(&self, operation: &Operation) -> MethodBuilder {
+        let error = operation.error_name.as_str();
+        let mut method = MethodBuilder::new(&operation.name)
+            .set_visibility(Visibility::Public)
+            .set_self_ownership(Ownership::Ref)
+            .set_return_type(simple_signature(&format!(
+                "Result<{}, {error}>",
+                operation.success().rust_type()
+            )));
+        if let Some(doc) = &operation.doc {
+            method = method.add_annotation(Annotation::new(format!("doc = {:?}", doc.trim())));
+        }
+        if self.synchronicity == Synchronicity::Async {
+            method = method.set_async();
+        }
+        let mut ordered = operation
+            .params
+            .iter()
+            .filter(|p| p.location == ParamLocation::Path)
+            .collect::<Vec<&Param>>();
+        ordered.extend(
+            operation
+                .params
+                .iter()
+                .filter(|p| p.location != ParamLocation::Path),
+        );
+        for param in ordered {
+            let ty = param.ty.clone().qualified("models");
+            let ty = if param.required {
+                ty
+            } else {
+                TypeRef::option(ty)
+            };
+            method = method.add_argument(Argument::new(
+                Ownership::Owned,
+                NamedComponentSignature::new_simple_type(&param.rust_name, ty.rust_type()),
+            ));
+        }
+        if let Some((content, required)) = &operation.body {
+            let ty = RustType::in_scope(content.rust_type());
+            let ty = if *required {
+                ty
+            } else {
+                RustType::in_scope("Option").wrap(ty)
+            };
+            method = method.add_argument(Argument::new(
+                Ownership::Owned,
+                NamedComponentSignature::new_simple_type("body", ty),
+            ));
+        }
+        method.set_body(self.operation_body(operation, error))
+    }
+
+    fn operation_body(&self, operation: &Operation, error: &str) -> String {
+        let mut lines = vec![];
+        let mut template = String::new();
+        let mut path_args = vec![];
+        let mut rest = operation.path.as_str();
+        while let Some((before, after)) = rest.split_once('{') {
+            let Some((name, after)) = after.split_once('}') else {
+                break;
+            };
+            template.push_str(before);
+            template.push_str("{}");
+            let rust = operation
+                .params
+                .iter()
+                .find(|p| p.location == ParamLocation::Path && p.name == name)
+                .map_or(name, |p| p.rust_name.as_str());
+            path_args.push(format!("param_string(&{rust})"));
+            rest = after;
+        }
+        template.push_str(rest);
+        let path = if path_args.is_empty() {
+            format!("{template:?}.to_string()")
+        } else {
+            format!("format!({template:?}, {})", path_args.join(", "))
+        };
+        let mutable = if operation.body.is_some()
+            || operation
+                .params
+                .iter()
+                .any(|p| p.location != ParamLocation::Path)
+        {
+            "mut "
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "let {mutable}request = Request {{\n    method: {:?},\n    path: {path},\n    \
+            query: vec![],\n    headers: vec![],\n    body: None,\n}};",
+            operation.method
+        ));
+        for param in &operation.params {
+            let target = match param.location {
+                ParamLocation::Path => continue,
+                ParamLocation::Query => "query",
+                ParamLocation::Header => "headers",
+            };
+            if param.required {
+                lines.push(format!(
+                    "request.{target}.push(({:?}.to_string(), param_string(&{})));",
+                    param.name, param.rust_name
+                ));
+            } else {
+                lines.push(format!(
+                    "if let Some(value) = &{} {{\n    \
+                    request.{target}.push(({:?}.to_string(), param_string(value)));\n}}",
+                    param.rust_name, param.name
+                ));
+            }
+        }
+        if let Some((content, required)) = &operation.body {
+            let (encode, media_type) = match content {
+                Content::Json(_) => (
+                    format!("serde_json::to_string(&body).map_err({error}::Json)?"),
+                    "application/json",
+                ),
+                _ => ("body".to_string(), "text/plain"),
+            };
+            let set = format!(
+                "request.headers.push((\"content-type\".to_string(), {media_type:?}.to_string()));\n\
+                request.body = Some({encode});"
+            );
+            if *required {
+                lines.push(set);
+            } else {
+                lines.push(format!("if let Some(body) = body {{\n{}\n}}", indent(&set)));
+            }
+        }
+        let send_await = match self.synchronicity {
+            Synchronicity::Sync => "",
+            Synchronicity::Async => ".await",
+        };
+        lines.push(format!(
+            "let response = self\n    .transport\n    .send(request){send_await}\n    .map_err({error}::Transport)?;"
+        ));
+        let success = operation.success();
+        let mut arms = vec![];
+        for (status, content) in &operation.responses {
+            let value = match content {
+                Content::Empty => None,
+                Content::Json(_) => Some(format!(
+                    "serde_json::from_str(&response.body).map_err({error}::Json)"
+                )),
+                Content::Raw => Some("Ok(response.body)".to_string()),
+            };
+            let arm = if status.is_success() && *content == success {
+                value.unwrap_or_else(|| "Ok(())".to_string())
+            } else {
+                let variant = status.variant();
+                match content {
+                    Content::Empty => format!("Err({error}::{variant})"),
+                    Content::Json(_) => format!(
+                        "Err({error}::{variant}(\n    serde_json::from_str(&response.body).map_err({error}::Json)?,\n))"
+                    ),
+                    Content::Raw => format!("Err({error}::{variant}(response.body))"),
+                }
+            };
+            arms.push(format!("{} => {arm},", status.pattern()));
+        }
+        if !operation
+            .responses
+            .iter()
+            .any(|(status, _)| *status == Status::Default)
+        {
+            arms.push(format!("_ => Err({error}::Unexpected(response)),"));
+        }
+        lines.push(format!(
+            "match response.status {{\n{}\n}}",
+            indent(&arms.join("\n"))
+        ));
+        indent(&lines.join("\n"))
+    }
+}
+
+fn write_error(fb: FileBuilder, operation: &Operation) -> FileBuilder {
+    let name = operation.error_name.clone();
+    let mut error = EnumBuilder::new(&name)
+        .set_visibility(Visibility::Public)
+        .add_derive_in_scope("Debug");
+    for (status, content) in operation.errors() {
+        error = match content {
+            Content::Empty => error.add_tag_member(status.variant()),
+            content => error.add_type_member(
+                status.variant(),
+                Signature::simple(RustType::in_scope(content.rust_type())),
+            ),
+        };
+    }
+    error = error
+        .add_type_member(
+            "Transport",
+            Signature::simple(RustType::in_scope("TransportError")),
+        )
+        .add_type_member(
+            "Json",
+            Signature::simple(RustType::in_scope("serde_json::Error")),
+        );
+    if !operation
+        .responses
+        .iter()
+        .any(|(status, _)| *status == Status::Default)
+    {
+        error = error.add_type_member(
+            "Unexpected",
+            Signature::simple(RustType::in_scope("Response")),
+        );
+    }
+    let display = ImplBuilder::new(Signature::simple(RustType::in_scope(&name)))
+        .implement_for(Signature::simple(RustType::in_scope("std::fmt::Display")))
+        .add_method(
+            MethodBuilder::new("fmt")
+                .set_self_ownership(Ownership::Ref)
+                .add_argument(Argument::new(
+                    Ownership::MutRef,
+                    NamedComponentSignature::new_simple_type(
+                        "f",
+                        RustType::in_scope("std::fmt::Formatter<'_>"),
+                    ),
+                ))
+                .set_return_type(simple_signature("std::fmt::Result"))
+                .set_body("    write!(f, \"{self:?}\")"),
+        );
+    let std_error = ImplBuilder::new(Signature::simple(RustType::in_scope(&name)))
+        .implement_for(Signature::simple(RustType::in_scope("std::error::Error")));
+    fb.add_enum(error).add_impl(display).add_impl(std_error)
+}
+
+fn simple_signature(rust_type: &str) -> ComponentSignature {
+    ComponentSignature::Signature(Signature::simple(RustType::in_scope(rust_type)))
+}
+
+fn indent(block: &str) -> String {
+    block
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("    {line}")
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::openapi::OpenApi;
+    use std::path::Path;
+
+    const SPEC: &str = r##"
+openapi: 3.0.3
+info: {title: Pets, version: "1"}
+paths:
+  /pets/{petId}:
+    parameters:
+      - {name: petId, in: path, required: true, schema: {type: integer}}
+    get:
+      operationId: getPet
+      summary: Finds a pet
+      parameters:
+        - {name: verbose, in: query, schema: {type: boolean}}
+      responses:
+        200:
+          description: ok
+          content: {application/json: {schema: {$ref: "#/components/schemas/Pet"}}}
+        404: {description: not found}
+        default:
+          description: error
+          content: {text/plain: {schema: {type: string}}}
+components:
+  schemas:
+    Pet:
+      type: object
+      required: [name]
+      properties:
+        name: {type: string}
+        tag: {type: string, nullable: true}
+"##;
+
+    #[test]
+    fn generates_client() {
+        let rendered = OpenApi::parse(SPEC)
+            .unwrap()
+            .set_async()
+            .into_module("api")
+            .unwrap()
+            .render();
+        let models = &rendered[Path::new("models.rs")];
+        assert!(models.contains("pub struct Pet {\npub name: String,\n"));
+        assert!(models.contains("pub tag: Option<String>,\n"));
+        let client = &rendered[Path::new("api.rs")];
+        assert!(client.starts_with("pub mod models;\n"));
+        assert!(client.contains(
+            "#[allow(async_fn_in_trait)]\npub trait Transport {\n\
+            async fn send(&self, request: Request) -> Result<Response, TransportError> ;\n"
+        ));
+        assert!(client.contains("impl<T> Client<T> where T: Transport {\n"));
+        assert!(client.contains(
+            "#[doc = \"Finds a pet\"]\n\
+            pub async fn get_pet(&self, pet_id: i64, verbose: Option<bool>) \
+            -> Result<models::Pet, GetPetError> {\n"
+        ));
+        assert!(client.contains("        path: format!(\"/pets/{}\", param_string(&pet_id)),\n"));
+        assert!(client.contains(
+            "        request.query.push((\"verbose\".to_string(), param_string(value)));\n"
+        ));
+        assert!(client.contains("        .send(request).await\n"));
+        assert!(client.contains(
+            "    match response.status {\n        \
+            200 => serde_json::from_str(&response.body).map_err(GetPetError::Json),\n        \
+            404 => Err(GetPetError::Status404),\n        \
+            _ => Err(GetPetError::Default(response.body)),\n    }\n"
+        ));
+        assert!(client.contains(
+            "pub enum GetPetError {\n\nStatus404,\n\nDefault(String),\n\n\
+            Transport(TransportError),\n\nJson(serde_json::Error),\n}\n"
+        ));
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(OpenApi::parse("swagger: \"2.0\"\n").is_err());
+        assert!(OpenApi::parse("openapi: [").is_err());
+    }
+
+    #[test]
+    fn keeps_params_apart_from_locals() {
+        let spec = r##"
+openapi: 3.0.3
+info: {title: Search, version: "1"}
+paths:
+  /items/{request}:
+    post:
+      operationId: search
+      parameters:
+        - {name: request, in: path, required: true, schema: {type: string}}
+        - {name: body, in: query, schema: {type: string}}
+        - {name: param_string, in: header, required: true, schema: {type: string}}
+      requestBody:
+        content: {text/plain: {schema: {type: string}}}
+      responses:
+        204: {description: done}
+"##;
+        let rendered = OpenApi::parse(spec)
+            .unwrap()
+            .into_module("api")
+            .unwrap()
+            .render();
+        let client = &rendered[Path::new("api.rs")];
+        assert!(client.contains(
+            "pub fn search(&self, request2: String, body2: Option<String>, \
+            param_string2: String, body: Option<String>) -> Result<(), SearchError> {\n"
+        ));
+        assert!(client.contains("        path: format!(\"/items/{}\", param_string(&request2)),\n"));
+        assert!(client.contains("    if let Some(value) = &body2 {\n"));
+    }
+
+    #[test]
+    fn keeps_operations_apart_from_client_names() {
+        let spec = r##"
+openapi: 3.0.3
+info: {title: Transport, version: "1"}
+paths:
+  /transport:
+    get:
+      operationId: transport
+      responses:
+        204: {description: done}
+        500: {description: failed}
+    put:
+      operationId: new
+      responses:
+        204: {description: done}
+    post:
+      operationId: api
+      responses:
+        204: {description: done}
+"##;
+        let rendered = OpenApi::parse(spec)
+            .unwrap()
+            .set_client_name("ApiError")
+            .into_module("api")
+            .unwrap()
+            .render();
+        let client = &rendered[Path::new("api.rs")];
+        assert!(client.contains("pub fn transport2(&self) -> Result<(), Transport2Error> {\n"));
+        assert!(client.contains("pub fn new2(&self) -> Result<(), New2Error> {\n"));
+        assert!(client.contains("pub fn api(&self) -> Result<(), ApiError2> {\n"));
+        assert!(client.contains("pub enum Transport2Error {\n"));
+        assert!(client.contains("pub type TransportError = "));
+    }
+
+    #[test]
+    fn rejects_undeclared_path_params() {
+        let spec = r##"
+openapi: 3.0.3
+info: {title: Items, version: "1"}
+paths:
+  /items/{id}:
+    get:
+      operationId: get_item
+      responses:
+        204: {description: done}
+"##;
+        let Err(Error::Schema(location, reason)) = OpenApi::parse(spec).unwrap().into_module("api")
+        else {
+            panic!("an undeclared path parameter is accepted");
+        };
+        assert_eq!(location, "GET /items/{id}");
+        assert_eq!(
+            reason,
+            "operation get_item doesn't declare path parameter id"
+        );
+    }
+}
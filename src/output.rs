@@ -0,0 +1,714 @@
+use crate::GeneratedHeader;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::path::{Component, Path, PathBuf};
+
+/// A rendered module tree, paths are relative to the module root
+pub type RenderedTree = BTreeMap<PathBuf, String>;
+
+/// Where the files of a submodule named `a` end up
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ModuleLayout {
+    /// `a/mod.rs` with its files next to it in `a/`
+    ModRs,
+    /// `a.rs` with its files in `a/`, the layout preferred since the 2018 edition
+    NamedFile,
+    /// `mod a { .. }` inline in the parent, a tree with this layout at the root is a single file
+    Inline,
+}
+
+/// Manifest file name used by `FileSystemSink::track_manifest` when no other name is wanted
+pub const DEFAULT_MANIFEST: &str = ".codegen-manifest";
+
+/// What a sink did with a single file
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WriteStatus {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Summary of a full write of a module tree
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct WriteReport {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+impl WriteReport {
+    pub fn record(&mut self, status: WriteStatus) {
+        match status {
+            WriteStatus::Created => self.created += 1,
+            WriteStatus::Updated => self.updated += 1,
+            WriteStatus::Unchanged => self.unchanged += 1,
+        }
+    }
+
+    /// Whether anything on the destination was touched
+    pub fn changed(&self) -> bool {
+        self.created + self.updated + self.removed > 0
+    }
+}
+
+impl Display for WriteReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "created {}, updated {}, unchanged {}, removed {}",
+            self.created, self.updated, self.unchanged, self.removed
+        )
+    }
+}
+
+/// Destination for rendered files, paths handed to the sink are always relative
+pub trait OutputSink {
+    fn write_file(&mut self, path: &Path, contents: &str) -> std::io::Result<WriteStatus>;
+
+    /// Current content at `path`, sinks that can't read back return `None`
+    fn read_existing(&self, _path: &Path) -> std::io::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Files a previous run generated, `commit` removes the ones that are no longer written
+    fn tracked(&self) -> std::io::Result<Vec<PathBuf>> {
+        Ok(vec![])
+    }
+
+    /// Called once every file has been handed over, makes staged writes visible and returns
+    /// how many stale files were removed
+    fn commit(&mut self) -> std::io::Result<usize> {
+        Ok(0)
+    }
+
+    /// Called instead of `commit` when the write failed, staged files should be discarded
+    fn abort(&mut self) {}
+}
+
+/// Writes files below a root directory, creating directories as needed.
+/// Files whose content is already up to date are left alone so their mtime doesn't change.
+/// Changed files are staged next to their target and renamed into place on `commit`,
+/// so a run that fails halfway, even during the renames, leaves the previous output intact
+#[derive(Debug)]
+pub struct FileSystemSink {
+    root: PathBuf,
+    manifest: Option<String>,
+    written: BTreeSet<PathBuf>,
+    staged: Vec<(PathBuf, PathBuf)>,
+}
+
+impl FileSystemSink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            manifest: None,
+            written: BTreeSet::new(),
+            staged: vec![],
+        }
+    }
+
+    /// Keeps a list of generated files in `file_name` at the root, files listed by a previous
+    /// run that are no longer produced get deleted
+    pub fn track_manifest(mut self, file_name: impl Into<String>) -> Self {
+        self.manifest = Some(file_name.into());
+        self
+    }
+
+    fn stage(&mut self, target: PathBuf, contents: &str) -> std::io::Result<()> {
+        let temp = temp_path(&target);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Pushed first so a partially written temp file is still cleaned up
+        self.staged.push((temp.clone(), target));
+        std::fs::write(&temp, contents)
+    }
+
+    fn remove_stale(&mut self) -> std::io::Result<usize> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(0);
+        };
+        let manifest_path = self.root.join(manifest);
+        let mut removed = 0;
+        for stale in read_manifest(&manifest_path)?
+            .into_iter()
+            .filter(|p| !self.written.contains(p))
+        {
+            let target = self.root.join(&stale);
+            match std::fs::remove_file(&target) {
+                Ok(()) => removed += 1,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+            // Clean up directories left empty, fails harmlessly on the first non-empty one
+            for dir in stale.ancestors().skip(1) {
+                if dir.as_os_str().is_empty() || std::fs::remove_dir(self.root.join(dir)).is_err() {
+                    break;
+                }
+            }
+        }
+        let mut listing = String::from(
+            "# Generated by codegen-rs, files listed here are removed when no longer generated\n",
+        );
+        for path in &self.written {
+            listing.push_str(&path.to_string_lossy().replace('\\', "/"));
+            listing.push('\n');
+        }
+        if std::fs::read_to_string(&manifest_path).ok().as_deref() != Some(listing.as_str()) {
+            self.stage(manifest_path, &listing)?;
+            self.rename_staged()?;
+        }
+        Ok(removed)
+    }
+
+    /// Moves staged files into place, if a rename fails the ones already moved are rolled back.
+    /// Only a failure while rolling back can leave a mix of old and new files
+    fn rename_staged(&mut self) -> std::io::Result<()> {
+        let staged = std::mem::take(&mut self.staged);
+        let mut replaced = vec![];
+        let mut result = Ok(());
+        for (temp, target) in &staged {
+            match replace(temp, target) {
+                Ok(backup) => replaced.push((target, backup)),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        if result.is_err() {
+            for (target, backup) in replaced.into_iter().rev() {
+                let _ = match backup {
+                    Some(backup) => std::fs::rename(backup, target),
+                    None => std::fs::remove_file(target),
+                };
+            }
+            for (temp, _) in &staged {
+                let _ = std::fs::remove_file(temp);
+            }
+        } else {
+            for backup in replaced.into_iter().filter_map(|(_, backup)| backup) {
+                let _ = std::fs::remove_file(backup);
+            }
+        }
+        result
+    }
+}
+
+impl Drop for FileSystemSink {
+    fn drop(&mut self) {
+        self.abort();
+    }
+}
+
+/// Files listed by a manifest, a missing manifest lists nothing
+pub(crate) fn read_manifest(manifest_path: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let contents = match std::fs::read_to_string(manifest_path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    Ok(contents
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(PathBuf::from)
+        // Never follow a hand-edited manifest outside of the root
+        .filter(|p| p.components().all(|c| matches!(c, Component::Normal(_))))
+        .collect())
+}
+
+/// Renames `temp` over `target`, returns where the previous file at `target` was moved to
+fn replace(temp: &Path, target: &Path) -> std::io::Result<Option<PathBuf>> {
+    let backup = sibling_path(target, "bak");
+    let backup = match std::fs::rename(target, &backup) {
+        Ok(()) => Some(backup),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    if let Err(e) = std::fs::rename(temp, target) {
+        if let Some(backup) = &backup {
+            let _ = std::fs::rename(backup, target);
+        }
+        return Err(e);
+    }
+    Ok(backup)
+}
+
+fn temp_path(target: &Path) -> PathBuf {
+    sibling_path(target, "tmp")
+}
+
+/// Hidden and without an `.rs` extension so a leftover is never picked up as a module
+fn sibling_path(target: &Path, kind: &str) -> PathBuf {
+    let name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    target.with_file_name(format!(".{name}.codegen-{kind}-{}", std::process::id()))
+}
+
+fn check_relative(path: &Path) -> std::io::Result<()> {
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} is not a plain relative path", path.display()),
+        ))
+    }
+}
+
+impl OutputSink for FileSystemSink {
+    fn write_file(&mut self, path: &Path, contents: &str) -> std::io::Result<WriteStatus> {
+        check_relative(path)?;
+        self.written.insert(path.to_path_buf());
+        let target = self.root.join(path);
+        let status = match std::fs::read(&target) {
+            Ok(existing) if existing == contents.as_bytes() => return Ok(WriteStatus::Unchanged),
+            Ok(_) => WriteStatus::Updated,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => WriteStatus::Created,
+            Err(e) => return Err(e),
+        };
+        self.stage(target, contents)?;
+        Ok(status)
+    }
+
+    fn read_existing(&self, path: &Path) -> std::io::Result<Option<String>> {
+        match std::fs::read_to_string(self.root.join(path)) {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn tracked(&self) -> std::io::Result<Vec<PathBuf>> {
+        match &self.manifest {
+            Some(manifest) => read_manifest(&self.root.join(manifest)),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn commit(&mut self) -> std::io::Result<usize> {
+        self.rename_staged()?;
+        self.remove_stale()
+    }
+
+    fn abort(&mut self) {
+        for (temp, _) in std::mem::take(&mut self.staged) {
+            let _ = std::fs::remove_file(temp);
+        }
+    }
+}
+
+/// Verifies guarded files up front so a tampered file doesn't leave the tree half written,
+/// then hands everything to the sink. Stale files about to be removed are verified as well
+pub(crate) fn write_rendered(
+    sink: &mut impl OutputSink,
+    rendered: RenderedTree,
+    guards: &BTreeMap<PathBuf, GeneratedHeader>,
+) -> crate::Result<WriteReport> {
+    for (path, header) in guards {
+        let Some(contents) = rendered.get(path) else {
+            continue;
+        };
+        match sink.read_existing(path)? {
+            Some(existing) if &existing != contents => header.verify(path, &existing)?,
+            _ => {}
+        }
+    }
+    for stale in sink.tracked()? {
+        if rendered.contains_key(&stale) {
+            continue;
+        }
+        let Some(header) = stale_guard(&stale, &rendered, guards) else {
+            continue;
+        };
+        if let Some(existing) = sink.read_existing(&stale)? {
+            header.verify(&stale, &existing)?;
+        }
+    }
+    let mut report = WriteReport::default();
+    for (path, contents) in rendered {
+        match sink.write_file(&path, &contents) {
+            Ok(status) => report.record(status),
+            Err(e) => {
+                sink.abort();
+                return Err(e.into());
+            }
+        }
+    }
+    report.removed = sink.commit()?;
+    Ok(report)
+}
+
+/// A stale file is guarded like the generated files closest to it
+fn stale_guard<'a>(
+    stale: &Path,
+    rendered: &RenderedTree,
+    guards: &'a BTreeMap<PathBuf, GeneratedHeader>,
+) -> Option<&'a GeneratedHeader> {
+    stale
+        .ancestors()
+        .skip(1)
+        .find_map(|dir| rendered.keys().find(|path| path.parent() == Some(dir)))
+        .and_then(|path| guards.get(path))
+}
+
+/// Collects files in memory, useful for tests and for tools that post-process output
+#[derive(Debug, Clone, Default)]
+pub struct InMemorySink {
+    pub files: RenderedTree,
+}
+
+impl InMemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<&str> {
+        self.files.get(path.as_ref()).map(String::as_str)
+    }
+
+    pub fn into_files(self) -> RenderedTree {
+        self.files
+    }
+}
+
+impl OutputSink for InMemorySink {
+    fn write_file(&mut self, path: &Path, contents: &str) -> std::io::Result<WriteStatus> {
+        Ok(
+            match self.files.insert(path.to_path_buf(), contents.to_string()) {
+                None => WriteStatus::Created,
+                Some(previous) if previous == contents => WriteStatus::Unchanged,
+                Some(_) => WriteStatus::Updated,
+            },
+        )
+    }
+
+    fn read_existing(&self, path: &Path) -> std::io::Result<Option<String>> {
+        Ok(self.get(path).map(str::to_string))
+    }
+}
+
+/// Appends files to a tar archive, call `finish` to write the trailer and get the writer back
+#[cfg(feature = "tar")]
+pub struct TarSink<W: std::io::Write> {
+    builder: tar::Builder<W>,
+}
+
+#[cfg(feature = "tar")]
+impl<W: std::io::Write> TarSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            builder: tar::Builder::new(writer),
+        }
+    }
+
+    pub fn finish(self) -> std::io::Result<W> {
+        self.builder.into_inner()
+    }
+}
+
+#[cfg(feature = "tar")]
+impl<W: std::io::Write> OutputSink for TarSink<W> {
+    fn write_file(&mut self, path: &Path, contents: &str) -> std::io::Result<WriteStatus> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        self.builder
+            .append_data(&mut header, path, contents.as_bytes())?;
+        Ok(WriteStatus::Created)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::{
+        InMemorySink, ModuleLayout, OutputSink, RenderedTree, WriteReport, DEFAULT_MANIFEST,
+    };
+    use crate::structures::visibility::Visibility;
+    use crate::{Annotations, Error, FileBuilder, GeneratedHeader, ModuleBuilder, StructBuilder};
+    use std::path::{Path, PathBuf};
+
+    fn tree() -> ModuleBuilder {
+        ModuleBuilder::new(FileBuilder::new("lib"))
+            .add_module_file(
+                Visibility::Public,
+                FileBuilder::new("models").add_struct(StructBuilder::new("Model")),
+                Annotations::empty(),
+            )
+            .add_submodule(
+                Visibility::Private,
+                ModuleBuilder::new(FileBuilder::new("type")).add_module_file(
+                    Visibility::Public,
+                    FileBuilder::new("inner"),
+                    Annotations::empty(),
+                ),
+            )
+    }
+
+    #[test]
+    fn renders_in_memory() {
+        let rendered = tree().render();
+        let paths = rendered
+            .keys()
+            .map(|p| p.to_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec!["lib.rs", "models.rs", "type/inner.rs", "type/mod.rs"],
+            paths
+        );
+        assert_eq!(
+            "pub mod models;\nmod r#type;\n",
+            rendered[Path::new("lib.rs")]
+        );
+        assert_eq!("struct Model {\n}\n", rendered[Path::new("models.rs")]);
+        let mut sink = InMemorySink::new();
+        tree().write_to_sink(&mut sink).unwrap();
+        assert_eq!(rendered, sink.into_files());
+    }
+
+    #[test]
+    fn layouts() {
+        let paths = |rendered: &RenderedTree| {
+            rendered
+                .keys()
+                .map(|p| p.to_str().unwrap().to_string())
+                .collect::<Vec<String>>()
+        };
+        let rendered = tree().set_layout(ModuleLayout::NamedFile).render();
+        assert_eq!(
+            vec!["lib.rs", "models.rs", "type/inner.rs", "type.rs"],
+            paths(&rendered)
+        );
+        assert_eq!("pub mod inner;\n", rendered[Path::new("type.rs")]);
+        let rendered = tree()
+            .set_layout(ModuleLayout::Inline)
+            .set_header(GeneratedHeader::default().disable_checksum())
+            .render();
+        assert_eq!(vec!["lib.rs"], paths(&rendered));
+        assert_eq!(
+            "// @generated by codegen-rs DO NOT EDIT\n\npub mod models {\nstruct Model {\n}\n}\nmod r#type {\npub mod inner {\n}\n}\n",
+            rendered[Path::new("lib.rs")]
+        );
+        // Only the submodule is inlined into its parent
+        let rendered = ModuleBuilder::new(FileBuilder::new("lib"))
+            .add_submodule(
+                Visibility::Public,
+                ModuleBuilder::new(FileBuilder::new("a")).set_layout(ModuleLayout::Inline),
+            )
+            .render();
+        assert_eq!(vec!["lib.rs"], paths(&rendered));
+        assert_eq!("pub mod a {\n}\n", rendered[Path::new("lib.rs")]);
+    }
+
+    #[test]
+    fn writes_to_disk() {
+        let root = std::env::temp_dir().join(format!("codegen-rs-sink-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let mut sink = crate::FileSystemSink::new(&root);
+        sink.write_file(Path::new("a/b.rs"), "fn b() {}\n").unwrap();
+        assert!(!root.join("a/b.rs").exists());
+        sink.commit().unwrap();
+        assert_eq!(
+            "fn b() {}\n",
+            std::fs::read_to_string(root.join("a/b.rs")).unwrap()
+        );
+        // A failed run discards what it staged and leaves the previous output alone
+        sink.write_file(Path::new("a/b.rs"), "fn c() {}\n").unwrap();
+        assert!(sink.write_file(Path::new("../escape.rs"), "").is_err());
+        sink.abort();
+        assert_eq!(
+            vec![std::ffi::OsString::from("b.rs")],
+            std::fs::read_dir(root.join("a"))
+                .unwrap()
+                .map(|e| e.unwrap().file_name())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "fn b() {}\n",
+            std::fs::read_to_string(root.join("a/b.rs")).unwrap()
+        );
+        let bad_name = ModuleBuilder::new(FileBuilder::new("lib")).add_module_file(
+            Visibility::Public,
+            FileBuilder::new("../../x"),
+            Annotations::empty(),
+        );
+        assert!(matches!(
+            bad_name.write_to_disk(&root),
+            Err(Error::InvalidIdentifier(..))
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn skips_unchanged_and_removes_stale() {
+        let root = std::env::temp_dir().join(format!("codegen-rs-stale-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let report = tree().write_to_disk_tracked(&root).unwrap();
+        assert_eq!(4, report.created);
+        let report = tree().write_to_disk_tracked(&root).unwrap();
+        assert_eq!(4, report.unchanged);
+        assert!(!report.changed());
+        let shrunk = ModuleBuilder::new(FileBuilder::new("lib")).add_module_file(
+            Visibility::Public,
+            FileBuilder::new("models"),
+            Annotations::empty(),
+        );
+        let report = shrunk.write_to_disk_tracked(&root).unwrap();
+        assert_eq!(
+            WriteReport {
+                created: 0,
+                updated: 2,
+                unchanged: 0,
+                removed: 2,
+            },
+            report
+        );
+        assert!(!root.join("type").exists());
+        assert!(root.join(DEFAULT_MANIFEST).exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_overwrite_edits() {
+        let header = GeneratedHeader::default();
+        let mut sink = InMemorySink::new();
+        tree()
+            .set_header(header.clone())
+            .write_to_sink(&mut sink)
+            .unwrap();
+        let models = sink.get("models.rs").unwrap().to_string();
+        assert!(models.starts_with("// @generated by codegen-rs DO NOT EDIT\n"));
+        assert!(models.ends_with("\n\nstruct Model {\n}\n"));
+        sink.files.insert(
+            PathBuf::from("models.rs"),
+            models.replace("Model", "Edited"),
+        );
+        sink.files
+            .insert(PathBuf::from("lib.rs"), "// hand written\n".to_string());
+        let err = tree()
+            .set_header(header.clone())
+            .write_to_sink(&mut sink)
+            .unwrap_err();
+        assert!(matches!(err, Error::Tampered(..)));
+        // Nothing was written by the failed run
+        assert_eq!(Some("// hand written\n"), sink.get("lib.rs"));
+        let report = tree()
+            .set_header(header)
+            .force_overwrite()
+            .write_to_sink(&mut sink)
+            .unwrap();
+        assert_eq!(2, report.updated);
+        assert_eq!(Some(models.as_str()), sink.get("models.rs"));
+    }
+
+    #[test]
+    fn rolls_back_failed_commit() {
+        let root = std::env::temp_dir().join(format!("codegen-rs-rollback-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let tree = |name: &str| {
+            ModuleBuilder::new(FileBuilder::new("lib"))
+                .add_module_file(
+                    Visibility::Public,
+                    FileBuilder::new("models").add_struct(StructBuilder::new(name)),
+                    Annotations::empty(),
+                )
+                .add_module_file(
+                    Visibility::Public,
+                    FileBuilder::new("zz").add_struct(StructBuilder::new(name)),
+                    Annotations::empty(),
+                )
+        };
+        tree("Model").write_to_disk(&root).unwrap();
+        // Keeps `zz.rs` from being moved aside, so its rename fails after `models.rs` is done
+        let blocker = root.join(format!(".zz.rs.codegen-bak-{}", std::process::id()));
+        std::fs::create_dir_all(blocker.join("inner")).unwrap();
+        assert!(tree("Changed").write_to_disk(&root).is_err());
+        let models = std::fs::read_to_string(root.join("models.rs")).unwrap();
+        assert!(models.contains("struct Model"));
+        let leftovers = std::fs::read_dir(&root)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with('.') && !blocker.ends_with(name))
+            .collect::<Vec<String>>();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+
+        std::fs::remove_dir_all(&blocker).unwrap();
+        let report = tree("Changed").write_to_disk(&root).unwrap();
+        assert_eq!(2, report.updated);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn guards_submodules_and_stale_files() {
+        let root = std::env::temp_dir().join(format!("codegen-rs-guard-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let guarded_submodule = |name: &str| {
+            ModuleBuilder::new(FileBuilder::new("lib")).add_submodule(
+                Visibility::Public,
+                ModuleBuilder::new(FileBuilder::new(name))
+                    .set_header(GeneratedHeader::default())
+                    .add_module_file(
+                        Visibility::Public,
+                        FileBuilder::new("inner"),
+                        Annotations::empty(),
+                    ),
+            )
+        };
+        guarded_submodule("type")
+            .write_to_disk_tracked(&root)
+            .unwrap();
+        let inner = root.join("type").join("inner.rs");
+        std::fs::write(&inner, "// hand written\n").unwrap();
+        let err = guarded_submodule("type")
+            .add_module_file(
+                Visibility::Public,
+                FileBuilder::new("models"),
+                Annotations::empty(),
+            )
+            .write_to_disk_tracked(&root)
+            .unwrap_err();
+        assert!(matches!(err, Error::Tampered(..)));
+
+        // The submodule is gone, its edited file isn't removed as stale
+        let shrunk = || {
+            ModuleBuilder::new(FileBuilder::new("lib"))
+                .set_header(GeneratedHeader::default())
+                .add_submodule(
+                    Visibility::Public,
+                    ModuleBuilder::new(FileBuilder::new("type")),
+                )
+        };
+        let err = shrunk().write_to_disk_tracked(&root).unwrap_err();
+        assert!(matches!(err, Error::Tampered(..)));
+        assert!(inner.exists());
+        let report = shrunk()
+            .force_overwrite()
+            .write_to_disk_tracked(&root)
+            .unwrap();
+        assert_eq!(1, report.removed);
+        assert!(!inner.exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn writes_tar() {
+        let mut sink = crate::output::TarSink::new(vec![]);
+        tree().write_to_sink(&mut sink).unwrap();
+        let bytes = sink.finish().unwrap();
+        let mut archive = tar::Archive::new(bytes.as_slice());
+        let paths = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_str().unwrap().to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            vec!["lib.rs", "models.rs", "type/inner.rs", "type/mod.rs"],
+            paths
+        );
+    }
+}
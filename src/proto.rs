@@ -0,0 +1,1215 @@
+use crate::errors::{Error, Result};
+use crate::model::{doc_annotation, unique_names, RESERVED_NAMES};
+use crate::structures::gen_enum::NamedComponentSignature;
+use crate::structures::visibility::Visibility;
+use crate::structures::{Annotations, ComponentSignature, RustType, Signature};
+use crate::util::casing::RustCase;
+use crate::{EnumBuilder, FileBuilder, ImplBuilder, MethodBuilder, ModuleBuilder, StructBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Generates Rust types from proto3 files without going through `protoc`.
+/// Messages become structs, enums become `#[repr(i32)]` enums, `oneof`s become enums of their
+/// fields and every package becomes a module, ex: `foo.bar.v1` is `foo::bar::v1`.
+/// Services, options and extensions are skipped.
+///
+/// Types from other packages are referred to through relative paths, so the generated tree can
+/// be mounted anywhere. Well-known types are mapped to Rust types, see `map_type`, except
+/// `Any`, `Struct`, `Value` and `ListValue` which have no std equivalent
+pub struct Proto {
+    sources: Vec<Source>,
+    include_dirs: Vec<PathBuf>,
+    mapped: BTreeMap<String, String>,
+}
+
+enum Source {
+    File(PathBuf),
+    Text(String, String),
+}
+
+const WELL_KNOWN: [(&str, &str); 13] = [
+    ("google.protobuf.Timestamp", "std::time::SystemTime"),
+    ("google.protobuf.Duration", "std::time::Duration"),
+    ("google.protobuf.Empty", "()"),
+    ("google.protobuf.FieldMask", "Vec<String>"),
+    ("google.protobuf.BoolValue", "bool"),
+    ("google.protobuf.BytesValue", "Vec<u8>"),
+    ("google.protobuf.DoubleValue", "f64"),
+    ("google.protobuf.FloatValue", "f32"),
+    ("google.protobuf.Int32Value", "i32"),
+    ("google.protobuf.Int64Value", "i64"),
+    ("google.protobuf.StringValue", "String"),
+    ("google.protobuf.UInt32Value", "u32"),
+    ("google.protobuf.UInt64Value", "u64"),
+];
+
+impl Default for Proto {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Proto {
+    /// Well-known types are mapped to std types, wrappers like `StringValue` map to their
+    /// inner type and are optional like every message field
+    pub fn new() -> Self {
+        Self {
+            sources: vec![],
+            include_dirs: vec![],
+            mapped: WELL_KNOWN
+                .iter()
+                .map(|(proto, rust)| (proto.to_string(), rust.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Self {
+        Self::new().add_file(path)
+    }
+
+    pub fn parse(source: impl Into<String>) -> Self {
+        Self::new().add_source("input.proto", source)
+    }
+
+    /// Imports are looked up next to the importing file, then in the include dirs.
+    /// Imports of `google/protobuf/` are never read, their types are mapped instead
+    pub fn add_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Imports of the source are looked up in the other sources by name, then in the include
+    /// dirs
+    pub fn add_source(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.sources.push(Source::Text(name.into(), source.into()));
+        self
+    }
+
+    pub fn add_include_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.include_dirs.push(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Uses a Rust type for a fully qualified message, ex: `google.protobuf.Timestamp` to
+    /// `chrono::DateTime<chrono::Utc>`, the message doesn't need to be defined in the sources.
+    /// Messages derive `Debug`, `Clone`, `PartialEq` and `Default` so the type must as well
+    pub fn map_type(mut self, proto_name: impl Into<String>, rust_type: impl Into<String>) -> Self {
+        let proto_name = proto_name.into();
+        self.mapped.insert(
+            proto_name.trim_start_matches('.').to_string(),
+            rust_type.into(),
+        );
+        self
+    }
+
+    /// `name` is the root module, types outside of a package go in its file
+    pub fn into_module(self, name: impl Into<String>) -> Result<ModuleBuilder> {
+        let name = name.into();
+        let files = self.load()?;
+        let mut generator = Generator {
+            mapped: self.mapped,
+            symbols: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            oneofs: BTreeMap::new(),
+            taken: BTreeMap::new(),
+        };
+        // Top-level types keep their names, nested ones are suffixed if they clash with them
+        for file in &files {
+            for message in &file.messages {
+                generator.declare(file, &file.scope(), &message.name, Kind::Message, None);
+            }
+            for proto_enum in &file.enums {
+                generator.declare(file, &file.scope(), &proto_enum.name, Kind::Enum, None);
+            }
+        }
+        for file in &files {
+            for message in &file.messages {
+                generator.declare_nested(file, message, &file.scope());
+            }
+        }
+        for file in &files {
+            for message in &file.messages {
+                generator.link(file, message, &format!("{}.{}", file.scope(), message.name))?;
+            }
+        }
+        let mut packages: BTreeMap<Vec<String>, FileBuilder> = BTreeMap::new();
+        for file in &files {
+            let package = file.package_path();
+            let mut fb = packages
+                .remove(&package)
+                .unwrap_or_else(|| FileBuilder::new(package.last().unwrap_or(&name)));
+            for message in &file.messages {
+                fb = generator.write_message(fb, file, message, &file.scope())?;
+            }
+            for proto_enum in &file.enums {
+                fb = generator.write_enum(fb, proto_enum, &file.scope());
+            }
+            packages.insert(package, fb);
+        }
+        let root = packages
+            .remove(&vec![])
+            .unwrap_or_else(|| FileBuilder::new(name));
+        Ok(ModuleBuilder::nested(root, packages))
+    }
+
+    fn load(&self) -> Result<Vec<ProtoFile>> {
+        let mut files = vec![];
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![];
+        for source in &self.sources {
+            match source {
+                Source::File(path) => pending.push(path.clone()),
+                Source::Text(name, text) => {
+                    let file = ProtoFile::parse(name, text)?;
+                    for import in &file.imports {
+                        if let Some(path) = self.find_import(None, import)? {
+                            pending.push(path);
+                        }
+                    }
+                    files.push(file);
+                }
+            }
+        }
+        while let Some(path) = pending.pop() {
+            let path = path.canonicalize()?;
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            let file = ProtoFile::parse(
+                &path.display().to_string(),
+                &std::fs::read_to_string(&path)?,
+            )?;
+            for import in &file.imports {
+                if let Some(import) = self.find_import(path.parent(), import)? {
+                    pending.push(import);
+                }
+            }
+            files.push(file);
+        }
+        Ok(files)
+    }
+
+    fn find_import(&self, dir: Option<&Path>, import: &str) -> Result<Option<PathBuf>> {
+        let added = self
+            .sources
+            .iter()
+            .any(|source| matches!(source, Source::Text(name, _) if name == import));
+        if added || import.starts_with("google/protobuf/") {
+            return Ok(None);
+        }
+        dir.into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(import))
+            .find(|path| path.is_file())
+            .map(Some)
+            .ok_or_else(|| Error::Schema(import.to_string(), "import not found".to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Message,
+    Enum,
+}
+
+struct Symbol {
+    package: Vec<String>,
+    rust_name: String,
+    kind: Kind,
+}
+
+enum Resolved {
+    Scalar(&'static str),
+    Mapped(String),
+    Message(String),
+    Enum(String),
+}
+
+struct Generator {
+    mapped: BTreeMap<String, String>,
+    /// By fully qualified name with a leading `.`
+    symbols: BTreeMap<String, Symbol>,
+    /// Messages held directly by a message, a cycle through these needs a `Box`
+    edges: BTreeMap<String, BTreeSet<String>>,
+    /// The enums of `oneof`s by the message's fully qualified name and their index
+    oneofs: BTreeMap<(String, usize), String>,
+    /// Names used per package, nested types are flattened into it
+    taken: BTreeMap<Vec<String>, BTreeSet<String>>,
+}
+
+impl Generator {
+    fn declare(
+        &mut self,
+        file: &ProtoFile,
+        scope: &str,
+        name: &str,
+        kind: Kind,
+        parent: Option<&str>,
+    ) -> String {
+        let package = file.package_path();
+        let rust_name = self.reserve(&package, &nested_name(parent, name));
+        self.symbols.insert(
+            format!("{scope}.{name}"),
+            Symbol {
+                package,
+                rust_name: rust_name.clone(),
+                kind,
+            },
+        );
+        rust_name
+    }
+
+    fn declare_nested(&mut self, file: &ProtoFile, message: &Message, scope: &str) {
+        let fqn = format!("{scope}.{}", message.name);
+        let parent = self.symbols[&fqn].rust_name.clone();
+        for nested in &message.messages {
+            self.declare(file, &fqn, &nested.name, Kind::Message, Some(&parent));
+            self.declare_nested(file, nested, &fqn);
+        }
+        for nested in &message.enums {
+            self.declare(file, &fqn, &nested.name, Kind::Enum, Some(&parent));
+        }
+        for (index, oneof) in message.oneofs.iter().enumerate() {
+            let rust_name = self.reserve(
+                &file.package_path(),
+                &nested_name(Some(&parent), &oneof.name),
+            );
+            self.oneofs.insert((fqn.clone(), index), rust_name);
+        }
+    }
+
+    fn reserve(&mut self, package: &[String], name: &str) -> String {
+        // `Default` is implemented by name for enums
+        let taken = self.taken.entry(package.to_vec()).or_insert_with(|| {
+            RESERVED_NAMES
+                .iter()
+                .chain(&["Default"])
+                .map(|name| name.to_string())
+                .collect()
+        });
+        let mut candidate = name.to_string();
+        let mut counter = 2;
+        while !taken.insert(candidate.clone()) {
+            candidate = format!("{name}{counter}");
+            counter += 1;
+        }
+        candidate
+    }
+
+    fn link(&mut self, file: &ProtoFile, message: &Message, fqn: &str) -> Result<()> {
+        let held = message
+            .fields
+            .iter()
+            .filter(|f| f.label != Label::Repeated)
+            .chain(message.oneofs.iter().flat_map(|o| &o.fields))
+            .filter_map(|f| match &f.ty {
+                FieldType::Named(name) => Some(name),
+                FieldType::Map(..) => None,
+            })
+            .map(|name| self.resolve(file, fqn, name))
+            .collect::<Result<Vec<Resolved>>>()?
+            .into_iter()
+            .filter_map(|resolved| match resolved {
+                Resolved::Message(target) => Some(target),
+                _ => None,
+            })
+            .collect();
+        self.edges.insert(fqn.to_string(), held);
+        for nested in &message.messages {
+            self.link(file, nested, &format!("{fqn}.{}", nested.name))?;
+        }
+        Ok(())
+    }
+
+    fn reaches(&self, from: &str, to: &str) -> bool {
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![from];
+        while let Some(current) = pending.pop() {
+            if current == to {
+                return true;
+            }
+            if visited.insert(current) {
+                pending.extend(
+                    self.edges
+                        .get(current)
+                        .into_iter()
+                        .flatten()
+                        .map(String::as_str),
+                );
+            }
+        }
+        false
+    }
+
+    /// Names are looked up from the innermost scope outwards, a leading `.` is fully qualified
+    fn resolve(&self, file: &ProtoFile, scope: &str, name: &str) -> Result<Resolved> {
+        if let Some(scalar) = scalar(name) {
+            return Ok(Resolved::Scalar(scalar));
+        }
+        let mut candidates = vec![];
+        if name.starts_with('.') {
+            candidates.push(name.to_string());
+        } else {
+            let mut scope = scope;
+            loop {
+                candidates.push(format!("{scope}.{name}"));
+                match scope.rfind('.') {
+                    Some(end) => scope = &scope[..end],
+                    None => break,
+                }
+            }
+        }
+        for candidate in candidates {
+            if let Some(symbol) = self.symbols.get(&candidate) {
+                return Ok(match symbol.kind {
+                    Kind::Message => Resolved::Message(candidate),
+                    Kind::Enum => Resolved::Enum(candidate),
+                });
+            }
+            if let Some(rust_type) = self.mapped.get(&candidate[1..]) {
+                return Ok(Resolved::Mapped(rust_type.clone()));
+            }
+        }
+        let mut reason = format!("unknown type {name} in {}", &scope[1..]);
+        // Their files are never read, the rest of the well-known types need a mapping
+        if name.trim_start_matches('.').starts_with("google.protobuf.") {
+            reason.push_str(", map it to a Rust type with `Proto::map_type`");
+        }
+        Err(Error::Schema(file.name.clone(), reason))
+    }
+
+    /// The path of a resolved type from a module of the given package
+    fn path(&self, resolved: &Resolved, from: &[String]) -> String {
+        match resolved {
+            Resolved::Scalar(scalar) => scalar.to_string(),
+            Resolved::Mapped(rust_type) => rust_type.clone(),
+            Resolved::Message(fqn) | Resolved::Enum(fqn) => {
+                let symbol = &self.symbols[fqn];
+                let common = from
+                    .iter()
+                    .zip(&symbol.package)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                let mut path = vec!["super".to_string(); from.len() - common];
+                path.extend(symbol.package[common..].iter().cloned());
+                path.push(symbol.rust_name.clone());
+                path.join("::")
+            }
+        }
+    }
+
+    /// A held message that can contain the message itself needs a `Box`
+    fn held_type(&self, resolved: &Resolved, from: &[String], holder: &str) -> String {
+        match resolved {
+            Resolved::Message(fqn) if self.reaches(fqn, holder) => {
+                format!("Box<{}>", self.path(resolved, from))
+            }
+            _ => self.path(resolved, from),
+        }
+    }
+
+    fn field_type(&self, file: &ProtoFile, field: &Field, fqn: &str) -> Result<String> {
+        let package = file.package_path();
+        Ok(match &field.ty {
+            FieldType::Map(key, value) => {
+                let key = self.resolve(file, fqn, key)?;
+                let value = self.resolve(file, fqn, value)?;
+                format!(
+                    "std::collections::HashMap<{}, {}>",
+                    self.path(&key, &package),
+                    self.path(&value, &package)
+                )
+            }
+            FieldType::Named(name) => {
+                let resolved = self.resolve(file, fqn, name)?;
+                match (field.label, &resolved) {
+                    (Label::Repeated, _) => format!("Vec<{}>", self.path(&resolved, &package)),
+                    (Label::Optional, _) | (_, Resolved::Message(_) | Resolved::Mapped(_)) => {
+                        format!("Option<{}>", self.held_type(&resolved, &package, fqn))
+                    }
+                    (Label::Singular, _) => self.path(&resolved, &package),
+                }
+            }
+        })
+    }
+
+    fn write_message(
+        &self,
+        mut fb: FileBuilder,
+        file: &ProtoFile,
+        message: &Message,
+        scope: &str,
+    ) -> Result<FileBuilder> {
+        let fqn = format!("{scope}.{}", message.name);
+        let rust_name = self.symbols[&fqn].rust_name.clone();
+        let mut sb = StructBuilder::new(&rust_name).set_visibility(Visibility::Public);
+        for derive in ["Debug", "Clone", "PartialEq", "Default"] {
+            sb = sb.add_derive_in_scope(derive);
+        }
+        let mut oneofs = vec![];
+        let wire_names = message.members.iter().map(|member| match member {
+            Member::Field(index) => &message.fields[*index].name,
+            Member::Oneof(index) => &message.oneofs[*index].name,
+        });
+        let names = unique_names(
+            wire_names
+                .clone()
+                .map(|name| RustCase::convert_lossy(name, RustCase::Snake)),
+        );
+        for ((member, wire_name), rust_name) in message.members.iter().zip(wire_names).zip(names) {
+            let annotations = wire_annotations(wire_name, &rust_name, RustCase::Snake);
+            match member {
+                Member::Field(index) => {
+                    let field = &message.fields[*index];
+                    sb = sb.add_field_with_annotations(
+                        Visibility::Public,
+                        NamedComponentSignature::new_simple_type(
+                            rust_name,
+                            RustType::in_scope(self.field_type(file, field, &fqn)?),
+                        ),
+                        annotations,
+                    );
+                }
+                Member::Oneof(index) => {
+                    let oneof = &message.oneofs[*index];
+                    let oneof_name = self.oneofs[&(fqn.clone(), *index)].clone();
+                    sb = sb.add_field_with_annotations(
+                        Visibility::Public,
+                        NamedComponentSignature::new_simple_type(
+                            rust_name,
+                            RustType::in_scope(format!("Option<{oneof_name}>")),
+                        ),
+                        annotations,
+                    );
+                    let mut eb = EnumBuilder::new(oneof_name).set_visibility(Visibility::Public);
+                    for derive in ["Debug", "Clone", "PartialEq"] {
+                        eb = eb.add_derive_in_scope(derive);
+                    }
+                    let variants = oneof
+                        .fields
+                        .iter()
+                        .filter_map(|field| match &field.ty {
+                            FieldType::Named(name) => Some((&field.name, name)),
+                            FieldType::Map(..) => None,
+                        })
+                        .collect::<Vec<_>>();
+                    let variant_names = unique_names(
+                        variants
+                            .iter()
+                            .map(|(name, _)| RustCase::convert_lossy(name, RustCase::Pascal)),
+                    );
+                    for ((wire_name, ty), variant) in variants.into_iter().zip(variant_names) {
+                        let resolved = self.resolve(file, &fqn, ty)?;
+                        eb = eb.add_type_member_with_annotations(
+                            &variant,
+                            Signature::simple(RustType::in_scope(self.held_type(
+                                &resolved,
+                                &file.package_path(),
+                                &fqn,
+                            ))),
+                            wire_annotations(wire_name, &variant, RustCase::Pascal),
+                        );
+                    }
+                    oneofs.push(eb);
+                }
+            }
+        }
+        fb = fb.add_struct(sb);
+        for eb in oneofs {
+            fb = fb.add_enum(eb);
+        }
+        for nested in &message.messages {
+            fb = self.write_message(fb, file, nested, &fqn)?;
+        }
+        for nested in &message.enums {
+            fb = self.write_enum(fb, nested, &fqn);
+        }
+        Ok(fb)
+    }
+
+    fn write_enum(&self, fb: FileBuilder, proto_enum: &ProtoEnum, scope: &str) -> FileBuilder {
+        let rust_name = &self.symbols[&format!("{scope}.{}", proto_enum.name)].rust_name;
+        let mut eb = EnumBuilder::new(rust_name)
+            .set_visibility(Visibility::Public)
+            .add_simple_annotation("repr(i32)");
+        for derive in ["Debug", "Clone", "Copy", "PartialEq", "Eq", "Hash"] {
+            eb = eb.add_derive_in_scope(derive);
+        }
+        let prefix = RustCase::convert_lossy(&proto_enum.name, RustCase::Pascal);
+        let mut numbers = BTreeSet::new();
+        // Aliases share a number which a Rust enum can't, the first name wins
+        let values = proto_enum
+            .values
+            .iter()
+            .filter(|(_, number)| numbers.insert(*number))
+            .collect::<Vec<_>>();
+        // Stripping the prefix can make values clash, ex: `COLOR_RED` and `RED` in `Color`
+        let members = unique_names(values.iter().map(|(name, _)| {
+            let pascal = RustCase::convert_lossy(name, RustCase::Pascal);
+            match pascal.strip_prefix(&prefix) {
+                Some(rest) if rest.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                    rest.to_string()
+                }
+                _ => pascal,
+            }
+        }));
+        let mut default = None;
+        for ((_, number), member) in values.into_iter().zip(members) {
+            if *number == 0 || default.is_none() {
+                default = Some(member.clone());
+            }
+            eb = eb.add_tag_member_with_value(member, number.to_string());
+        }
+        let fb = fb.add_enum(eb);
+        match default {
+            Some(default) => fb.add_impl(
+                ImplBuilder::new(Signature::simple(RustType::in_scope(rust_name)))
+                    .implement_for(Signature::simple(RustType::in_scope("Default")))
+                    .add_method(
+                        MethodBuilder::new("default")
+                            .set_return_type(ComponentSignature::Signature(Signature::simple(
+                                RustType::in_scope("Self"),
+                            )))
+                            .set_body(format!("    Self::{default}")),
+                    ),
+            ),
+            None => fb,
+        }
+    }
+}
+
+/// Names that got a counter suffix document the name they have in the proto
+fn wire_annotations(wire_name: &str, rust_name: &str, case: RustCase) -> Annotations {
+    if RustCase::convert_lossy(wire_name, case) == rust_name {
+        return Annotations::empty();
+    }
+    let doc = format!("`{wire_name}` in the proto");
+    Annotations::new(doc_annotation(Some(&doc)))
+}
+
+/// Nested types are prefixed with their parents, ex: `Outer.Inner` is `OuterInner`
+fn nested_name(parent: Option<&str>, name: &str) -> String {
+    let name = RustCase::convert_lossy(name, RustCase::Pascal);
+    match parent {
+        Some(parent) => format!("{parent}{name}"),
+        None => name,
+    }
+}
+
+fn scalar(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "double" => "f64",
+        "float" => "f32",
+        "int32" | "sint32" | "sfixed32" => "i32",
+        "int64" | "sint64" | "sfixed64" => "i64",
+        "uint32" | "fixed32" => "u32",
+        "uint64" | "fixed64" => "u64",
+        "bool" => "bool",
+        "string" => "String",
+        "bytes" => "Vec<u8>",
+        _ => return None,
+    })
+}
+
+struct ProtoFile {
+    name: String,
+    package: Option<String>,
+    imports: Vec<String>,
+    messages: Vec<Message>,
+    enums: Vec<ProtoEnum>,
+}
+
+impl ProtoFile {
+    fn parse(name: &str, source: &str) -> Result<Self> {
+        Parser {
+            name,
+            tokens: tokenize(name, source)?,
+            pos: 0,
+        }
+        .file()
+    }
+
+    /// Fully qualified names of the file's types start with it
+    fn scope(&self) -> String {
+        match &self.package {
+            Some(package) => format!(".{package}"),
+            None => String::new(),
+        }
+    }
+
+    fn package_path(&self) -> Vec<String> {
+        self.package
+            .iter()
+            .flat_map(|p| p.split('.'))
+            .map(|segment| RustCase::convert_lossy(segment, RustCase::Snake))
+            .collect()
+    }
+}
+
+struct Message {
+    name: String,
+    fields: Vec<Field>,
+    oneofs: Vec<Oneof>,
+    /// Fields and oneofs in declaration order
+    members: Vec<Member>,
+    messages: Vec<Message>,
+    enums: Vec<ProtoEnum>,
+}
+
+enum Member {
+    Field(usize),
+    Oneof(usize),
+}
+
+struct Oneof {
+    name: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    name: String,
+    label: Label,
+    ty: FieldType,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Label {
+    Singular,
+    Optional,
+    Repeated,
+}
+
+enum FieldType {
+    Named(String),
+    Map(String, String),
+}
+
+struct ProtoEnum {
+    name: String,
+    values: Vec<(String, i32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Symbol(char),
+}
+
+fn tokenize(name: &str, source: &str) -> Result<Vec<(Token, usize)>> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|c| *c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            previous = c;
+                        }
+                        None => {
+                            return Err(Error::Schema(
+                                format!("{name}:{line}"),
+                                "unterminated comment".to_string(),
+                            ))
+                        }
+                    }
+                }
+            }
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) => value.push(c),
+                        None => {
+                            return Err(Error::Schema(
+                                format!("{name}:{line}"),
+                                "unterminated string".to_string(),
+                            ))
+                        }
+                    }
+                }
+                tokens.push((Token::Str(value), line));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' || c == '.' => {
+                let mut value = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || "_.".contains(*c))
+                {
+                    value.push(c);
+                }
+                tokens.push((Token::Ident(value), line));
+            }
+            c if c.is_ascii_digit() => {
+                let mut value = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || "_.".contains(*c))
+                {
+                    value.push(c);
+                }
+                tokens.push((Token::Number(value), line));
+            }
+            c => tokens.push((Token::Symbol(c), line)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    name: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: impl Into<String>) -> Error {
+        let line = self
+            .tokens
+            .get(self.pos.min(self.tokens.len().saturating_sub(1)))
+            .map_or(1, |(_, line)| *line);
+        Error::Schema(format!("{}:{line}", self.name), reason.into())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error("unexpected end of file"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek_ident(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Ident(ident)) => Some(ident),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<()> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{symbol}`, found {:?}", self.peek())))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            token => {
+                self.pos -= 1;
+                Err(self.error(format!("expected a name, found {token:?}")))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        match self.next()? {
+            Token::Str(value) => Ok(value),
+            token => {
+                self.pos -= 1;
+                Err(self.error(format!("expected a string, found {token:?}")))
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<i32> {
+        let negative = self.eat('-');
+        let value = match self.next()? {
+            Token::Number(value) => value,
+            token => {
+                self.pos -= 1;
+                return Err(self.error(format!("expected a number, found {token:?}")));
+            }
+        };
+        let parsed = if let Some(hex) = value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+            i64::from_str_radix(hex, 16)
+        } else if value.len() > 1 && value.starts_with('0') {
+            i64::from_str_radix(&value[1..], 8)
+        } else {
+            value.parse()
+        };
+        parsed
+            .ok()
+            .map(|n| if negative { -n } else { n })
+            .and_then(|n| i32::try_from(n).ok())
+            .ok_or_else(|| self.error(format!("invalid number {value}")))
+    }
+
+    /// Skips up to the end of the statement or block, ex: options, services and extensions
+    fn skip(&mut self) -> Result<()> {
+        let mut depth = 0;
+        loop {
+            match self.next()? {
+                Token::Symbol('{') => depth += 1,
+                Token::Symbol('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                Token::Symbol(';') if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Field and enum value options, ex: `[deprecated = true]`
+    fn skip_options(&mut self) -> Result<()> {
+        if self.eat('[') {
+            while self.next()? != Token::Symbol(']') {}
+        }
+        Ok(())
+    }
+
+    fn file(mut self) -> Result<ProtoFile> {
+        let mut file = ProtoFile {
+            name: self.name.to_string(),
+            package: None,
+            imports: vec![],
+            messages: vec![],
+            enums: vec![],
+        };
+        let mut syntax = None;
+        while let Some(token) = self.peek().cloned() {
+            match token {
+                Token::Symbol(';') => self.pos += 1,
+                Token::Ident(keyword) => {
+                    self.pos += 1;
+                    match keyword.as_str() {
+                        "syntax" => {
+                            self.expect('=')?;
+                            syntax = Some(self.string()?);
+                            self.expect(';')?;
+                        }
+                        "package" => {
+                            file.package = Some(self.ident()?);
+                            self.expect(';')?;
+                        }
+                        "import" => {
+                            if matches!(self.peek_ident(), Some("public" | "weak")) {
+                                self.pos += 1;
+                            }
+                            file.imports.push(self.string()?);
+                            self.expect(';')?;
+                        }
+                        "message" => file.messages.push(self.message()?),
+                        "enum" => file.enums.push(self.proto_enum()?),
+                        "option" | "service" | "extend" => self.skip()?,
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error(format!("unexpected `{keyword}`")));
+                        }
+                    }
+                }
+                token => return Err(self.error(format!("unexpected {token:?}"))),
+            }
+        }
+        if syntax.as_deref() != Some("proto3") {
+            return Err(Error::Schema(
+                file.name,
+                format!("only proto3 is supported, found syntax {syntax:?}"),
+            ));
+        }
+        Ok(file)
+    }
+
+    fn message(&mut self) -> Result<Message> {
+        let mut message = Message {
+            name: self.ident()?,
+            fields: vec![],
+            oneofs: vec![],
+            members: vec![],
+            messages: vec![],
+            enums: vec![],
+        };
+        self.expect('{')?;
+        while !self.eat('}') {
+            if self.eat(';') {
+                continue;
+            }
+            match self.peek_ident() {
+                Some("message") => {
+                    self.pos += 1;
+                    message.messages.push(self.message()?);
+                }
+                Some("enum") => {
+                    self.pos += 1;
+                    message.enums.push(self.proto_enum()?);
+                }
+                Some("oneof") => {
+                    self.pos += 1;
+                    let mut oneof = Oneof {
+                        name: self.ident()?,
+                        fields: vec![],
+                    };
+                    self.expect('{')?;
+                    while !self.eat('}') {
+                        if self.eat(';') {
+                            continue;
+                        }
+                        match self.peek_ident() {
+                            Some("option") => self.skip()?,
+                            _ => oneof.fields.push(self.field()?),
+                        }
+                    }
+                    message.members.push(Member::Oneof(message.oneofs.len()));
+                    message.oneofs.push(oneof);
+                }
+                Some("option" | "reserved" | "extensions" | "extend") => self.skip()?,
+                _ => {
+                    message.members.push(Member::Field(message.fields.len()));
+                    message.fields.push(self.field()?);
+                }
+            }
+        }
+        Ok(message)
+    }
+
+    fn field(&mut self) -> Result<Field> {
+        let label = match self.peek_ident() {
+            Some("repeated") => Label::Repeated,
+            Some("optional") => Label::Optional,
+            Some("required") => return Err(self.error("`required` isn't allowed in proto3")),
+            _ => Label::Singular,
+        };
+        if label != Label::Singular {
+            self.pos += 1;
+        }
+        let ty = self.ident()?;
+        let ty = if ty == "map" && self.eat('<') {
+            let key = self.ident()?;
+            self.expect(',')?;
+            let value = self.ident()?;
+            self.expect('>')?;
+            FieldType::Map(key, value)
+        } else {
+            FieldType::Named(ty)
+        };
+        let name = self.ident()?;
+        self.expect('=')?;
+        self.number()?;
+        self.skip_options()?;
+        self.expect(';')?;
+        Ok(Field { name, label, ty })
+    }
+
+    fn proto_enum(&mut self) -> Result<ProtoEnum> {
+        let mut proto_enum = ProtoEnum {
+            name: self.ident()?,
+            values: vec![],
+        };
+        self.expect('{')?;
+        while !self.eat('}') {
+            if self.eat(';') {
+                continue;
+            }
+            match self.peek_ident() {
+                Some("option" | "reserved") => self.skip()?,
+                _ => {
+                    let name = self.ident()?;
+                    self.expect('=')?;
+                    let number = self.number()?;
+                    self.skip_options()?;
+                    self.expect(';')?;
+                    proto_enum.values.push((name, number));
+                }
+            }
+        }
+        Ok(proto_enum)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::proto::Proto;
+    use std::path::Path;
+
+    const SOURCE: &str = r#"
+syntax = "proto3";
+package shop.v1;
+
+import "google/protobuf/timestamp.proto";
+
+// Comments and options are skipped
+message Order {
+  enum Status {
+    STATUS_UNSPECIFIED = 0;
+    STATUS_PLACED = 1 [deprecated = true];
+  }
+  string id = 1;
+  repeated Order children = 2;
+  map<string, int64> totals = 3;
+  Status status = 4;
+  google.protobuf.Timestamp created = 5;
+  optional string note = 6;
+  oneof payment {
+    Card card = 7;
+    string voucher = 8;
+  }
+  Order parent = 9;
+  reserved 10;
+}
+
+message Card { string number = 1; }
+
+service Shop { rpc Get(Order) returns (Order); }
+"#;
+
+    #[test]
+    fn generates_packages() {
+        let rendered = Proto::parse(SOURCE)
+            .map_type("google.protobuf.Timestamp", "Timestamp")
+            .into_module("api")
+            .unwrap()
+            .render();
+        assert_eq!(rendered[Path::new("api.rs")], "pub mod shop;\n");
+        assert_eq!(rendered[Path::new("shop/mod.rs")], "pub mod v1;\n");
+        let expected = r#"#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Order {
+pub id: String,
+pub children: Vec<Order>,
+pub totals: std::collections::HashMap<String, i64>,
+pub status: OrderStatus,
+pub created: Option<Timestamp>,
+pub note: Option<String>,
+pub payment: Option<OrderPayment>,
+pub parent: Option<Box<Order>>,
+}
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderPayment {
+
+Card(Card),
+
+Voucher(String),
+}
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderStatus {
+
+Unspecified = 0,
+
+Placed = 1,
+}
+impl Default for OrderStatus  {
+fn default() -> Self {
+    Self::Unspecified
+}
+
+}
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Card {
+pub number: String,
+}
+"#;
+        assert_eq!(rendered[Path::new("shop/v1.rs")], expected);
+    }
+
+    #[test]
+    fn refers_across_packages() {
+        let common =
+            r#"syntax = "proto3"; package acme.common; message Money { int64 units = 1; }"#;
+        let billing = r#"
+syntax = "proto3";
+package acme.billing;
+import "common.proto";
+message Invoice { acme.common.Money total = 1; repeated common.Money lines = 2; }
+"#;
+        let rendered = Proto::new()
+            .add_source("common.proto", common)
+            .add_source("billing.proto", billing)
+            .into_module("api")
+            .unwrap()
+            .render();
+        let invoice = &rendered[Path::new("acme/billing.rs")];
+        assert!(invoice.contains("pub total: Option<super::common::Money>,\n"));
+        assert!(invoice.contains("pub lines: Vec<super::common::Money>,\n"));
+
+        let error = Proto::parse("syntax = \"proto2\";").into_module("api");
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn keeps_flattened_names_apart() {
+        let source = r#"
+syntax = "proto3";
+message Outer {
+  message Inner { string id = 1; }
+  Inner inner = 1;
+  oneof kind { string name = 2; }
+  message Kind { bool set = 1; }
+}
+message OuterInner { Outer.Inner nested = 1; }
+"#;
+        let rendered = Proto::parse(source).into_module("api").unwrap().render();
+        let api = &rendered[Path::new("api.rs")];
+        assert!(api.contains("pub struct Outer {\npub inner: Option<OuterInner2>,\npub kind: Option<OuterKind2>,\n}\n"));
+        assert!(api.contains("pub enum OuterKind2 {\n"));
+        assert!(api.contains("pub struct OuterKind {\npub set: bool,\n}\n"));
+        assert!(api.contains("pub struct OuterInner {\npub nested: Option<OuterInner2>,\n}\n"));
+    }
+
+    #[test]
+    fn keeps_member_names_apart() {
+        let source = r#"
+syntax = "proto3";
+message Option { string user_name = 1; string userName = 2; oneof user { bool a_b = 3; int32 aB = 4; } }
+enum Color { COLOR_RED = 0; RED = 1; }
+"#;
+        let rendered = Proto::parse(source).into_module("api").unwrap().render();
+        let api = &rendered[Path::new("api.rs")];
+        assert!(api.contains(
+            "pub struct Option2 {\npub user_name: String,\n#[doc = \"`userName` in the proto\"]\n\
+            pub user_name2: String,\npub user: Option<Option2User>,\n}\n"
+        ));
+        assert!(api.contains("AB(bool),\n#[doc = \"`aB` in the proto\"]\n\nAB2(i32),\n"));
+        assert!(api.contains("Red = 0,\n\nRed2 = 1,\n"));
+    }
+
+    #[test]
+    fn points_unmapped_well_known_types_at_map_type() {
+        let source = r#"
+syntax = "proto3";
+import "google/protobuf/struct.proto";
+message Event { google.protobuf.Struct payload = 1; }
+"#;
+        let Err(Error::Schema(_, reason)) = Proto::parse(source).into_module("api") else {
+            panic!("an unmapped type is accepted");
+        };
+        assert!(reason.ends_with("map it to a Rust type with `Proto::map_type`"));
+        let rendered = Proto::parse(source)
+            .map_type("google.protobuf.Struct", "serde_json::Value")
+            .into_module("api")
+            .unwrap()
+            .render();
+        assert!(rendered[Path::new("api.rs")].contains("pub payload: Option<serde_json::Value>,\n"));
+    }
+}
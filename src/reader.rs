@@ -0,0 +1,899 @@
+use crate::errors::{Error, Result};
+use crate::structures::gen_enum::{EnumMember, MemberType, NamedComponentSignature};
+use crate::structures::gen_struct::Field;
+use crate::structures::generics::{Bound, Bounds, Generic, Generics};
+use crate::structures::method::Argument;
+use crate::structures::visibility::Visibility;
+use crate::structures::{
+    Annotation, Annotations, ComponentSignature, Derives, Import, Ownership, RustType, Signature,
+    TypeDef, TypeDefDeclaration,
+};
+use crate::{
+    ConstantBuilder, ContainerStructBuilder, EnumBuilder, FileBuilder, FunctionBuilder,
+    ImplBuilder, MethodBuilder, StructBuilder, TraitBuilder,
+};
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+use std::path::Path;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+
+/// An item read from source, either mapped onto one of the builders or kept as-is
+/// because the model can't represent it (macros, lifetimes, unit structs etc.)
+#[derive(Debug, Clone)]
+pub enum ParsedItem {
+    Import(Import),
+    Const(ConstantBuilder),
+    TypeDef(TypeDef),
+    Function(FunctionBuilder),
+    Enum(EnumBuilder),
+    Struct(StructBuilder),
+    ContainerStruct(ContainerStructBuilder),
+    Trait(TraitBuilder),
+    Impl(ImplBuilder),
+    Verbatim(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    pub name: String,
+    pub items: Vec<ParsedItem>,
+}
+
+impl ParsedFile {
+    pub fn map_items(mut self, f: impl FnMut(ParsedItem) -> ParsedItem) -> Self {
+        self.items = self.items.into_iter().map(f).collect();
+        self
+    }
+
+    pub fn map_struct(self, name: &str, f: impl FnOnce(StructBuilder) -> StructBuilder) -> Self {
+        let mut f = Some(f);
+        self.map_items(|item| match item {
+            ParsedItem::Struct(sb) if sb.name == name => match f.take() {
+                Some(f) => ParsedItem::Struct(f(sb)),
+                None => ParsedItem::Struct(sb),
+            },
+            other => other,
+        })
+    }
+
+    pub fn into_file_builder(self) -> FileBuilder {
+        let mut fb = FileBuilder::new(self.name);
+        for item in self.items {
+            fb = match item {
+                ParsedItem::Import(i) => fb.add_import(i),
+                ParsedItem::Const(c) => fb.add_const(c),
+                ParsedItem::TypeDef(t) => fb.add_type_def(t),
+                ParsedItem::Function(f) => fb.add_function(f),
+                ParsedItem::Enum(e) => fb.add_enum(e),
+                ParsedItem::Struct(s) => fb.add_struct(s),
+                ParsedItem::ContainerStruct(c) => fb.add_container_struct(c),
+                ParsedItem::Trait(t) => fb.add_trait(t),
+                ParsedItem::Impl(i) => fb.add_impl(i),
+                ParsedItem::Verbatim(v) => fb.add_any(v),
+            };
+        }
+        fb
+    }
+}
+
+/// Reads a rust source file, the file stem becomes the name of the resulting file
+pub fn read_file(path: impl AsRef<Path>) -> Result<ParsedFile> {
+    let path = path.as_ref();
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| Error::Parse(format!("No valid file name in {path:?}")))?;
+    let source = std::fs::read_to_string(path)?;
+    parse_file(name, &source)
+}
+
+/// Parses rust source into the builder model.
+/// Function bodies, expressions and types are kept as they are written, items the model can't
+/// express are kept verbatim. Comments that aren't doc comments are lost outside of bodies.
+pub fn parse_file(name: impl Into<String>, source: &str) -> Result<ParsedFile> {
+    let file = syn::parse_file(source).map_err(|e| Error::Parse(e.to_string()))?;
+    let reader = Reader { source };
+    let mut items = vec![];
+    if !file.attrs.is_empty() {
+        let inner = file
+            .attrs
+            .iter()
+            .map(|a| format!("{}\n", reader.text(a)))
+            .collect::<String>();
+        items.push(ParsedItem::Verbatim(inner));
+    }
+    for item in &file.items {
+        items.push(
+            reader
+                .item(item)
+                .unwrap_or_else(|| ParsedItem::Verbatim(format!("{}\n", reader.text(item)))),
+        );
+    }
+    Ok(ParsedFile {
+        name: name.into(),
+        items,
+    })
+}
+
+struct Reader<'a> {
+    source: &'a str,
+}
+
+impl<'a> Reader<'a> {
+    fn text(&self, node: &impl Spanned) -> String {
+        let range = node.span().byte_range();
+        self.source[range].to_string()
+    }
+
+    fn item(&self, item: &syn::Item) -> Option<ParsedItem> {
+        match item {
+            syn::Item::Use(u) => self.import(u).map(ParsedItem::Import),
+            syn::Item::Const(c) => self.constant(c).map(ParsedItem::Const),
+            syn::Item::Static(s) => self.static_constant(s).map(ParsedItem::Const),
+            syn::Item::Type(t) => self.type_def(t).map(ParsedItem::TypeDef),
+            syn::Item::Fn(f) => self.function(f).map(ParsedItem::Function),
+            syn::Item::Enum(e) => self.enumeration(e).map(ParsedItem::Enum),
+            syn::Item::Struct(s) => self.structure(s),
+            syn::Item::Trait(t) => self.trait_def(t).map(ParsedItem::Trait),
+            syn::Item::Impl(i) => self.implementation(i).map(ParsedItem::Impl),
+            _ => None,
+        }
+    }
+
+    fn import(&self, item: &syn::ItemUse) -> Option<Import> {
+        if !item.attrs.is_empty() || !matches!(item.vis, syn::Visibility::Inherited) {
+            return None;
+        }
+        let leading = if item.leading_colon.is_some() {
+            "::"
+        } else {
+            ""
+        };
+        Some(Import::spec(format!("{leading}{}", self.text(&item.tree))))
+    }
+
+    fn constant(&self, item: &syn::ItemConst) -> Option<ConstantBuilder> {
+        if !item.generics.params.is_empty() {
+            return None;
+        }
+        let annotations = self.annotations(&item.attrs)?;
+        let mut cb = ConstantBuilder::const_builder(
+            item.ident.to_string(),
+            self.rust_type(&item.ty),
+            self.text(&item.expr),
+        )
+        .set_visibility(self.visibility(&item.vis)?);
+        cb.annotations = annotations;
+        Some(cb)
+    }
+
+    fn static_constant(&self, item: &syn::ItemStatic) -> Option<ConstantBuilder> {
+        if !matches!(item.mutability, syn::StaticMutability::None) {
+            return None;
+        }
+        let annotations = self.annotations(&item.attrs)?;
+        let mut cb = ConstantBuilder::static_builder(
+            item.ident.to_string(),
+            self.rust_type(&item.ty),
+            self.text(&item.expr),
+        )
+        .set_visibility(self.visibility(&item.vis)?);
+        cb.annotations = annotations;
+        Some(cb)
+    }
+
+    fn type_def(&self, item: &syn::ItemType) -> Option<TypeDef> {
+        if !item.attrs.is_empty() || !item.generics.params.is_empty() {
+            return None;
+        }
+        Some(TypeDef::Const(TypeDefDeclaration::new(
+            self.visibility(&item.vis)?,
+            NamedComponentSignature::new(
+                item.ident.to_string(),
+                self.component_signature(&item.ty, &[])?,
+            ),
+        )))
+    }
+
+    fn function(&self, item: &syn::ItemFn) -> Option<FunctionBuilder> {
+        let sig = &item.sig;
+        if !self.plain_signature(sig) {
+            return None;
+        }
+        let params = self.generics(&sig.generics)?;
+        let mut fb =
+            FunctionBuilder::new(sig.ident.to_string()).set_visibility(self.visibility(&item.vis)?);
+        fb.annotations = self.annotations(&item.attrs)?;
+        if sig.asyncness.is_some() {
+            fb = fb.set_async();
+        }
+        for input in &sig.inputs {
+            match input {
+                syn::FnArg::Receiver(_) => return None,
+                syn::FnArg::Typed(pt) => fb.args.push(self.argument(pt, &params)?),
+            }
+        }
+        if !same_aliases(&args_generics(&fb.args, &[]), &params) {
+            return None;
+        }
+        if let syn::ReturnType::Type(_, ty) = &sig.output {
+            fb.return_type = Some(self.component_signature(ty, &params)?);
+        }
+        fb.body = self.block_body(&item.block);
+        Some(fb)
+    }
+
+    fn enumeration(&self, item: &syn::ItemEnum) -> Option<EnumBuilder> {
+        let params = self.generics(&item.generics)?;
+        let (annotations, derives) = self.annotations_and_derives(&item.attrs)?;
+        let mut eb =
+            EnumBuilder::new(item.ident.to_string()).set_visibility(self.visibility(&item.vis)?);
+        eb.annotations = annotations;
+        eb.derives = derives;
+        let mut used = Generics::default();
+        for variant in &item.variants {
+            let member_type = match &variant.fields {
+                syn::Fields::Unit => MemberType::Empty(
+                    variant
+                        .discriminant
+                        .as_ref()
+                        .map(|(_, expr)| self.text(expr)),
+                ),
+                syn::Fields::Unnamed(u) => {
+                    if u.unnamed.len() != 1 {
+                        return None;
+                    }
+                    let field = u.unnamed.first()?;
+                    if !field.attrs.is_empty() {
+                        return None;
+                    }
+                    let signature = match self.component_signature(&field.ty, &params)? {
+                        ComponentSignature::Signature(s) => s,
+                        ComponentSignature::Generic(g) => Signature::simple_generic(g),
+                    };
+                    used = used.union(&signature.get_generics());
+                    MemberType::Type(signature)
+                }
+                syn::Fields::Named(n) => {
+                    let mut patterns = vec![];
+                    for field in &n.named {
+                        if !field.attrs.is_empty() {
+                            return None;
+                        }
+                        let ncs = NamedComponentSignature::new(
+                            field.ident.as_ref()?.to_string(),
+                            self.component_signature(&field.ty, &params)?,
+                        );
+                        used = used.union(&component_generics(&ncs.component_signature));
+                        patterns.push(ncs);
+                    }
+                    MemberType::Pattern(patterns)
+                }
+            };
+            eb.members.push(EnumMember::new(
+                variant.ident.to_string(),
+                member_type,
+                self.annotations(&variant.attrs)?,
+            ));
+        }
+        if !same_aliases(&used.get_generics(), &params) {
+            return None;
+        }
+        Some(eb)
+    }
+
+    fn structure(&self, item: &syn::ItemStruct) -> Option<ParsedItem> {
+        let params = self.generics(&item.generics)?;
+        let (annotations, derives) = self.annotations_and_derives(&item.attrs)?;
+        let visibility = self.visibility(&item.vis)?;
+        match &item.fields {
+            syn::Fields::Named(n) => {
+                let mut sb = StructBuilder::new(item.ident.to_string()).set_visibility(visibility);
+                sb.annotations = annotations;
+                sb.derives = derives;
+                let mut used = Generics::default();
+                for field in &n.named {
+                    let ncs = NamedComponentSignature::new(
+                        field.ident.as_ref()?.to_string(),
+                        self.component_signature(&field.ty, &params)?,
+                    );
+                    used = used.union(&component_generics(&ncs.component_signature));
+                    sb.fields.push(Field::new_with_annotations(
+                        self.visibility(&field.vis)?,
+                        ncs,
+                        self.annotations(&field.attrs)?,
+                    ));
+                }
+                if !same_aliases(&used.get_generics(), &params) {
+                    return None;
+                }
+                Some(ParsedItem::Struct(sb))
+            }
+            syn::Fields::Unnamed(u) => {
+                if !params.is_empty() || u.unnamed.is_empty() {
+                    return None;
+                }
+                let mut csb =
+                    ContainerStructBuilder::new(item.ident.to_string()).set_visibility(visibility);
+                csb.annotations = annotations;
+                csb.derives = derives;
+                for field in &u.unnamed {
+                    if !field.attrs.is_empty() {
+                        return None;
+                    }
+                    csb =
+                        csb.add_contained(self.visibility(&field.vis)?, self.rust_type(&field.ty));
+                }
+                Some(ParsedItem::ContainerStruct(csb))
+            }
+            syn::Fields::Unit => None,
+        }
+    }
+
+    fn trait_def(&self, item: &syn::ItemTrait) -> Option<TraitBuilder> {
+        if item.unsafety.is_some() || item.auto_token.is_some() || item.restriction.is_some() {
+            return None;
+        }
+        let params = self.generics(&item.generics)?;
+        let mut tb = TraitBuilder::new(Signature::generic_container(
+            RustType::in_scope(item.ident.to_string()),
+            Generics::multiple(params.clone()),
+        ))
+        .set_visibility(self.visibility(&item.vis)?);
+        tb.annotations = self.annotations(&item.attrs)?;
+        for bound in &item.supertraits {
+            match bound {
+                syn::TypeParamBound::Trait(_) => {
+                    tb.super_traits
+                        .push(Signature::simple(RustType::in_scope(self.text(bound))));
+                }
+                _ => return None,
+            }
+        }
+        for trait_item in &item.items {
+            match trait_item {
+                syn::TraitItem::Fn(f) => {
+                    let mut mb = self.method(&f.sig, &params)?;
+                    mb.annotations = self.annotations(&f.attrs)?;
+                    mb.body = f.default.as_ref().map(|b| self.block_body(b));
+                    tb.methods.push(mb);
+                }
+                syn::TraitItem::Type(t) => {
+                    if !t.attrs.is_empty() || !t.generics.params.is_empty() || t.default.is_some() {
+                        return None;
+                    }
+                    let ty = if t.bounds.is_empty() {
+                        t.ident.to_string()
+                    } else {
+                        format!("{}: {}", t.ident, self.text(&t.bounds))
+                    };
+                    tb.types.push(ty);
+                }
+                _ => return None,
+            }
+        }
+        Some(tb)
+    }
+
+    fn implementation(&self, item: &syn::ItemImpl) -> Option<ImplBuilder> {
+        if item.defaultness.is_some() || item.unsafety.is_some() {
+            return None;
+        }
+        let params = self.generics(&item.generics)?;
+        let implementor = match self.component_signature(&item.self_ty, &params)? {
+            ComponentSignature::Signature(s) => s,
+            ComponentSignature::Generic(_) => return None,
+        };
+        let mut ib = ImplBuilder::new(implementor.clone());
+        ib.annotations = self.annotations(&item.attrs)?;
+        let declared = if let Some((bang, path, _)) = &item.trait_ {
+            if bang.is_some() {
+                return None;
+            }
+            let implementing = self.path_signature(path, &params)?;
+            let union = implementor
+                .get_generics()
+                .union(&implementing.get_generics());
+            ib = ib.implement_for(implementing);
+            union
+        } else {
+            implementor.get_associated_generics()
+        };
+        if !same_aliases(&declared.get_generics(), &params) {
+            return None;
+        }
+        for impl_item in &item.items {
+            match impl_item {
+                syn::ImplItem::Fn(f) => {
+                    if f.defaultness.is_some() {
+                        return None;
+                    }
+                    let mut mb = self
+                        .method(&f.sig, &params)?
+                        .set_visibility(self.visibility(&f.vis)?)
+                        .set_body(self.block_body(&f.block));
+                    mb.annotations = self.annotations(&f.attrs)?;
+                    ib = ib.add_method(mb);
+                }
+                syn::ImplItem::Type(t) => {
+                    if !t.attrs.is_empty() || !t.generics.params.is_empty() {
+                        return None;
+                    }
+                    ib = ib.add_type_def(TypeDef::TraitImpl(NamedComponentSignature::new(
+                        t.ident.to_string(),
+                        self.component_signature(&t.ty, &params)?,
+                    )));
+                }
+                syn::ImplItem::Const(c) => {
+                    if !c.generics.params.is_empty() || mentions(&c.ty, &params) {
+                        return None;
+                    }
+                    let mut cb = ConstantBuilder::const_builder(
+                        c.ident.to_string(),
+                        self.rust_type(&c.ty),
+                        self.text(&c.expr),
+                    )
+                    .set_visibility(self.visibility(&c.vis)?);
+                    cb.annotations = self.annotations(&c.attrs)?;
+                    ib = ib.add_const(cb);
+                }
+                _ => return None,
+            }
+        }
+        Some(ib)
+    }
+
+    fn method(&self, sig: &syn::Signature, container: &[Generic]) -> Option<MethodBuilder> {
+        if !self.plain_signature(sig) {
+            return None;
+        }
+        let own = self.generics(&sig.generics)?;
+        if own
+            .iter()
+            .any(|g| container.iter().any(|c| c.alias == g.alias))
+        {
+            return None;
+        }
+        let params = container
+            .iter()
+            .chain(own.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut mb = MethodBuilder::new(sig.ident.to_string());
+        if sig.asyncness.is_some() {
+            mb = mb.set_async();
+        }
+        for input in &sig.inputs {
+            match input {
+                syn::FnArg::Receiver(r) => {
+                    if r.colon_token.is_some() {
+                        return None;
+                    }
+                    let ownership = match (&r.reference, r.mutability.is_some()) {
+                        (Some((_, Some(_))), _) => return None,
+                        (Some(_), true) => Ownership::MutRef,
+                        (Some(_), false) => Ownership::Ref,
+                        (None, true) => Ownership::OwnedMut,
+                        (None, false) => Ownership::Owned,
+                    };
+                    mb = mb.set_self_ownership(ownership);
+                }
+                syn::FnArg::Typed(pt) => mb.args.push(self.argument(pt, &params)?),
+            }
+        }
+        if !same_aliases(&args_generics(&mb.args, container), &own) {
+            return None;
+        }
+        if let syn::ReturnType::Type(_, ty) = &sig.output {
+            mb.return_type = Some(self.component_signature(ty, &params)?);
+        }
+        Some(mb)
+    }
+
+    fn plain_signature(&self, sig: &syn::Signature) -> bool {
+        sig.constness.is_none()
+            && sig.unsafety.is_none()
+            && sig.abi.is_none()
+            && sig.variadic.is_none()
+    }
+
+    fn argument(&self, pt: &syn::PatType, params: &[Generic]) -> Option<Argument> {
+        if !pt.attrs.is_empty() {
+            return None;
+        }
+        let syn::Pat::Ident(pi) = &*pt.pat else {
+            return None;
+        };
+        if pi.by_ref.is_some() || pi.subpat.is_some() || !pi.attrs.is_empty() {
+            return None;
+        }
+        let (ownership, ty) = match &*pt.ty {
+            syn::Type::Reference(r) if r.lifetime.is_none() && pi.mutability.is_none() => (
+                if r.mutability.is_some() {
+                    Ownership::MutRef
+                } else {
+                    Ownership::Ref
+                },
+                &*r.elem,
+            ),
+            ty if pi.mutability.is_some() => (Ownership::OwnedMut, ty),
+            ty => (Ownership::Owned, ty),
+        };
+        Some(Argument::new(
+            ownership,
+            NamedComponentSignature::new(
+                pi.ident.to_string(),
+                self.component_signature(ty, params)?,
+            ),
+        ))
+    }
+
+    fn block_body(&self, block: &syn::Block) -> String {
+        let start = block.brace_token.span.open().byte_range().end;
+        let end = block.brace_token.span.close().byte_range().start;
+        self.source[start..end]
+            .trim_start_matches(['\n', '\r'])
+            .trim_end()
+            .to_string()
+    }
+
+    fn visibility(&self, vis: &syn::Visibility) -> Option<Visibility> {
+        match vis {
+            syn::Visibility::Public(_) => Some(Visibility::Public),
+            syn::Visibility::Restricted(r) if r.in_token.is_none() && r.path.is_ident("crate") => {
+                Some(Visibility::PublicCrate)
+            }
+            syn::Visibility::Inherited => Some(Visibility::Private),
+            syn::Visibility::Restricted(_) => None,
+        }
+    }
+
+    fn annotations(&self, attrs: &[syn::Attribute]) -> Option<Annotations> {
+        let mut annotations = vec![];
+        for attr in attrs {
+            if !matches!(attr.style, syn::AttrStyle::Outer) {
+                return None;
+            }
+            let content = if attr.path().is_ident("doc") {
+                // Sugared doc comments span the comment itself, not a `#[doc = ...]`
+                attr.meta.to_token_stream().to_string()
+            } else {
+                self.text(&attr.meta)
+            };
+            annotations.push(Annotation::new(content));
+        }
+        Some(Annotations::new(annotations))
+    }
+
+    fn annotations_and_derives(&self, attrs: &[syn::Attribute]) -> Option<(Annotations, Derives)> {
+        let mut derives = vec![];
+        let mut rest = vec![];
+        for attr in attrs {
+            if attr.path().is_ident("derive") {
+                let paths = attr
+                    .parse_args_with(Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)
+                    .ok()?;
+                derives.extend(paths.iter().map(|p| self.path_type(p)));
+            } else {
+                rest.push(attr.clone());
+            }
+        }
+        Some((self.annotations(&rest)?, Derives::new(derives)))
+    }
+
+    fn generics(&self, generics: &syn::Generics) -> Option<Vec<Generic>> {
+        let mut declared: Vec<(String, Vec<Bound>)> = vec![];
+        for param in &generics.params {
+            let syn::GenericParam::Type(tp) = param else {
+                return None;
+            };
+            if tp.default.is_some() || !tp.attrs.is_empty() {
+                return None;
+            }
+            declared.push((tp.ident.to_string(), self.bounds(&tp.bounds)?));
+        }
+        if let Some(wc) = &generics.where_clause {
+            for predicate in &wc.predicates {
+                let syn::WherePredicate::Type(pt) = predicate else {
+                    return None;
+                };
+                if pt.lifetimes.is_some() {
+                    return None;
+                }
+                let ident = bare_ident(&pt.bounded_ty)?;
+                let (_, bounds) = declared.iter_mut().find(|(alias, _)| ident == *alias)?;
+                bounds.extend(self.bounds(&pt.bounds)?);
+            }
+        }
+        Some(
+            declared
+                .into_iter()
+                .map(|(alias, bounds)| Generic::bounded(alias, Bounds::multiple(bounds)))
+                .collect(),
+        )
+    }
+
+    fn bounds(
+        &self,
+        bounds: &Punctuated<syn::TypeParamBound, syn::Token![+]>,
+    ) -> Option<Vec<Bound>> {
+        let mut out = vec![];
+        for bound in bounds {
+            let syn::TypeParamBound::Trait(tb) = bound else {
+                return None;
+            };
+            if tb.lifetimes.is_some() || tb.paren_token.is_some() {
+                return None;
+            }
+            let rust_type = RustType::in_scope(self.text(&tb.path));
+            out.push(match tb.modifier {
+                syn::TraitBoundModifier::None => Bound::required(rust_type),
+                syn::TraitBoundModifier::Maybe(_) => Bound::optional(rust_type),
+            });
+        }
+        Some(out)
+    }
+
+    fn rust_type(&self, ty: &syn::Type) -> RustType {
+        match ty {
+            syn::Type::Path(tp) if tp.qself.is_none() => self.path_type(&tp.path),
+            _ => RustType::in_scope(self.text(ty)),
+        }
+    }
+
+    fn path_type(&self, path: &syn::Path) -> RustType {
+        let plain = path.leading_colon.is_none()
+            && path
+                .segments
+                .iter()
+                .all(|s| matches!(s.arguments, syn::PathArguments::None));
+        match path.segments.last() {
+            Some(last) if plain && path.segments.len() > 1 => {
+                let package = path
+                    .segments
+                    .iter()
+                    .take(path.segments.len() - 1)
+                    .map(|s| s.ident.to_string())
+                    .collect::<Vec<String>>()
+                    .join("::");
+                RustType::from_package(package, last.ident.to_string())
+            }
+            _ => RustType::in_scope(self.text(path)),
+        }
+    }
+
+    fn component_signature(
+        &self,
+        ty: &syn::Type,
+        params: &[Generic],
+    ) -> Option<ComponentSignature> {
+        if !mentions(ty, params) {
+            return Some(ComponentSignature::Signature(Signature::simple(
+                self.rust_type(ty),
+            )));
+        }
+        if let Some(ident) = bare_ident(ty) {
+            return params
+                .iter()
+                .find(|g| g.alias == ident)
+                .map(|g| ComponentSignature::Generic(g.clone()));
+        }
+        match ty {
+            syn::Type::Path(tp) if tp.qself.is_none() => self
+                .path_signature(&tp.path, params)
+                .map(ComponentSignature::Signature),
+            _ => None,
+        }
+    }
+
+    /// Only paths like `a::B<T, R>` where all arguments are declared generics can be
+    /// expressed as a signature
+    fn path_signature(&self, path: &syn::Path, params: &[Generic]) -> Option<Signature> {
+        let segments = path.segments.iter().collect::<Vec<_>>();
+        let (last, prefix) = segments.split_last()?;
+        if path.leading_colon.is_some()
+            || prefix
+                .iter()
+                .any(|s| !matches!(s.arguments, syn::PathArguments::None))
+        {
+            return None;
+        }
+        let rust_type = if prefix.is_empty() {
+            RustType::in_scope(last.ident.to_string())
+        } else {
+            RustType::from_package(
+                prefix
+                    .iter()
+                    .map(|s| s.ident.to_string())
+                    .collect::<Vec<String>>()
+                    .join("::"),
+                last.ident.to_string(),
+            )
+        };
+        let mut generics = vec![];
+        match &last.arguments {
+            syn::PathArguments::None => {}
+            syn::PathArguments::AngleBracketed(ab) => {
+                for arg in &ab.args {
+                    let syn::GenericArgument::Type(ty) = arg else {
+                        return None;
+                    };
+                    let ident = bare_ident(ty)?;
+                    generics.push(params.iter().find(|g| g.alias == ident)?.clone());
+                }
+            }
+            syn::PathArguments::Parenthesized(_) => {
+                if mentions_tokens(last.arguments.to_token_stream(), params) {
+                    return None;
+                }
+                return Some(Signature::simple(RustType::in_scope(self.text(path))));
+            }
+        }
+        Some(Signature::generic_container(
+            rust_type,
+            Generics::multiple(generics),
+        ))
+    }
+}
+
+fn bare_ident(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(tp) if tp.qself.is_none() => tp.path.get_ident().map(|i| i.to_string()),
+        _ => None,
+    }
+}
+
+fn mentions(ty: &syn::Type, params: &[Generic]) -> bool {
+    mentions_tokens(ty.to_token_stream(), params)
+}
+
+fn mentions_tokens(tokens: TokenStream, params: &[Generic]) -> bool {
+    tokens.into_iter().any(|tt| match tt {
+        TokenTree::Ident(i) => params.iter().any(|g| i == g.alias),
+        TokenTree::Group(g) => mentions_tokens(g.stream(), params),
+        _ => false,
+    })
+}
+
+fn component_generics(cs: &ComponentSignature) -> Generics {
+    match cs {
+        ComponentSignature::Signature(s) => s.get_generics(),
+        ComponentSignature::Generic(g) => Generics::multiple(vec![g.clone()]),
+    }
+}
+
+fn args_generics(args: &[Argument], container: &[Generic]) -> Vec<Generic> {
+    let mut union = Generics::default();
+    for arg in args {
+        union = union.union(&component_generics(&arg.named_sign.component_signature));
+    }
+    union
+        .get_generics()
+        .into_iter()
+        .filter(|g| !container.iter().any(|c| c.alias == g.alias))
+        .collect()
+}
+
+fn same_aliases(used: &[Generic], declared: &[Generic]) -> bool {
+    used.len() == declared.len() && used.iter().zip(declared).all(|(a, b)| a.alias == b.alias)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::{parse_file, ParsedItem};
+    use crate::structures::gen_enum::NamedComponentSignature;
+    use crate::structures::visibility::Visibility;
+    use crate::structures::RustType;
+    use quote::ToTokens;
+
+    const SOURCE: &str = r#"use std::collections::HashMap;
+use std::fmt::Debug;
+
+pub const MAX: usize = 5;
+pub type Map = HashMap<String, i32>;
+
+/// A documented struct
+#[derive(Debug, Clone)]
+pub struct MyStruct<T> where T: Debug {
+    #[allow(dead_code)]
+    pub inner: Vec<T>,
+    pub(crate) name: String,
+}
+
+pub struct Wrapper(pub i32, String);
+
+#[derive(Debug)]
+pub enum MyEnum<T> where T: Clone {
+    First(T),
+    Second { a: i32, b: String },
+    Third = 3,
+}
+
+pub trait Greeter: Debug {
+    type Output;
+    fn greet(&self, name: &str) -> String;
+    fn loud(&self) -> bool {
+        false
+    }
+}
+
+impl<T> MyStruct<T> where T: Debug {
+    pub fn new(inner: Vec<T>) -> Self {
+        // Keeps this comment
+        Self { inner, name: String::new() }
+    }
+
+    pub fn push(&mut self, val: T) {
+        self.inner.push(val);
+    }
+}
+
+impl<T> Greeter for MyStruct<T> where T: Debug {
+    type Output = String;
+    fn greet(&self, name: &str) -> String {
+        format!("Hello {name}")
+    }
+}
+
+pub async fn run<R>(mut arg: R, other: &mut i32) -> i32 where R: Debug {
+    *other
+}
+
+macro_rules! my_macro {
+    () => {};
+}
+
+struct Unit;
+
+fn with_lifetime<'a>(s: &'a str) -> &'a str {
+    s
+}
+"#;
+
+    fn tokens(source: &str) -> String {
+        syn::parse_file(source)
+            .unwrap()
+            .to_token_stream()
+            .to_string()
+    }
+
+    #[test]
+    fn round_trips() {
+        let parsed = parse_file("my_file", SOURCE).unwrap();
+        let verbatim = parsed
+            .items
+            .iter()
+            .filter(|i| matches!(i, ParsedItem::Verbatim(_)))
+            .count();
+        // Macro, unit struct and lifetimed function
+        assert_eq!(3, verbatim);
+        let formatted = parsed.into_file_builder().format_file();
+        assert_eq!(tokens(SOURCE), tokens(&formatted));
+        assert!(formatted.contains("// Keeps this comment"));
+    }
+
+    #[test]
+    fn modify_and_format() {
+        let parsed = parse_file(
+            "my_file",
+            "pub struct Point {\n    pub x: i32,\n}\n\nfn keep() {}\n",
+        )
+        .unwrap()
+        .map_struct("Point", |sb| {
+            sb.add_field(
+                Visibility::Public,
+                NamedComponentSignature::new_simple_type("y", RustType::in_scope("i32")),
+            )
+        });
+        assert_eq!(
+            tokens("pub struct Point { pub x: i32, pub y: i32, } fn keep() {}"),
+            tokens(&parsed.into_file_builder().format_file())
+        );
+    }
+
+    #[test]
+    fn bad_source_errors() {
+        assert!(parse_file("bad", "fn broken( {").is_err());
+    }
+}
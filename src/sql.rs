@@ -0,0 +1,827 @@
+use crate::errors::{Error, Result};
+use crate::model::{unique_names, unique_names_in, RESERVED_NAMES};
+use crate::structures::gen_enum::NamedComponentSignature;
+use crate::structures::visibility::Visibility;
+use crate::structures::{RustType, Signature};
+use crate::util::casing::RustCase;
+use crate::{ConstantBuilder, ContainerStructBuilder, FileBuilder, ImplBuilder, StructBuilder};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Decides the placeholders of the generated queries and how loosely named types map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// `$1` placeholders
+    Postgres,
+    /// `?1` placeholders, types map by SQLite's affinity rules, ex: anything containing `INT` is
+    /// an `i64`
+    Sqlite,
+}
+
+/// Generates a row struct per `CREATE TABLE` statement, other statements are skipped.
+/// Nullable columns are `Option`s and a single column primary key gets a newtype, which columns
+/// referencing it use as well.
+///
+/// Every struct gets an `INSERT` and, if it has a primary key, a `SELECT_BY_PK` query constant.
+/// Columns the database fills in, serials, identities and generated columns, are left out of
+/// the insert. Placeholders are numbered in column order.
+/// Names that would clash, with each other or with types the code uses, get a counter suffix
+pub struct Sql {
+    tables: Vec<Table>,
+    dialect: Dialect,
+    mapped: BTreeMap<String, String>,
+}
+
+impl Sql {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        Self::parse_named(&path.display().to_string(), &std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(source: &str) -> Result<Self> {
+        Self::parse_named("input.sql", source)
+    }
+
+    fn parse_named(name: &str, source: &str) -> Result<Self> {
+        Ok(Self {
+            tables: Parser {
+                name,
+                tokens: tokenize(name, source)?,
+                pos: 0,
+            }
+            .tables()?,
+            dialect: Dialect::Postgres,
+            mapped: BTreeMap::new(),
+        })
+    }
+
+    /// Defaults to `Dialect::Postgres`
+    pub fn set_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Uses a Rust type for a column type, ex: `uuid` to `uuid::Uuid`, case-insensitive and
+    /// without parameters, ex: `numeric` also maps `NUMERIC(10, 2)`.
+    /// Types without a lossless std equivalent, like `numeric`, `uuid` or `timestamp`, are
+    /// `String`s unless mapped. Primary key newtypes derive `Eq` and `Hash` so the type must too
+    pub fn map_type(mut self, sql_type: impl Into<String>, rust_type: impl Into<String>) -> Self {
+        self.mapped
+            .insert(normalize_type(&sql_type.into()), rust_type.into());
+        self
+    }
+
+    pub fn into_file_builder(self, name: impl Into<String>) -> Result<FileBuilder> {
+        let mut taken = BTreeSet::from(RESERVED_NAMES.map(String::from));
+        let keyed = self
+            .tables
+            .iter()
+            .filter_map(|table| match table.primary_key.as_slice() {
+                [key] => Some((table, key)),
+                _ => None,
+            })
+            .collect::<Vec<(&Table, &String)>>();
+        let key_names = unique_names_in(
+            &mut taken,
+            keyed.iter().map(|(table, _)| {
+                format!(
+                    "{}Id",
+                    RustCase::convert_lossy(&table.ident, RustCase::Pascal)
+                )
+            }),
+        );
+        // Key newtypes by table, with the key column
+        let keys = keyed
+            .into_iter()
+            .zip(key_names)
+            .map(|((table, key), key_name)| (table.ident.clone(), (key.clone(), key_name)))
+            .collect::<BTreeMap<String, (String, String)>>();
+        let struct_names = unique_names_in(
+            &mut taken,
+            self.tables
+                .iter()
+                .map(|table| RustCase::convert_lossy(&table.ident, RustCase::Pascal)),
+        );
+        let mut fb = FileBuilder::new(name);
+        for (table, struct_name) in self.tables.iter().zip(struct_names) {
+            fb = self.add_table(fb, table, &struct_name, &keys)?;
+        }
+        Ok(fb)
+    }
+
+    fn add_table(
+        &self,
+        mut fb: FileBuilder,
+        table: &Table,
+        struct_name: &str,
+        keys: &BTreeMap<String, (String, String)>,
+    ) -> Result<FileBuilder> {
+        let field_names = unique_names(
+            table
+                .columns
+                .iter()
+                .map(|c| RustCase::convert_lossy(&c.ident, RustCase::Snake)),
+        );
+        let mut sb = StructBuilder::new(struct_name).set_visibility(Visibility::Public);
+        for derive in ["Debug", "Clone", "PartialEq"] {
+            sb = sb.add_derive_in_scope(derive);
+        }
+        for (column, field_name) in table.columns.iter().zip(field_names) {
+            let referenced = column
+                .references
+                .as_ref()
+                .and_then(|(target, target_column)| {
+                    keys.get(target)
+                        .filter(|(key, _)| target_column.as_ref().is_none_or(|c| c == key))
+                });
+            let mut ty = match referenced {
+                Some((_, key_name)) => key_name.clone(),
+                None if table.primary_key == [column.ident.clone()] => {
+                    let (rust_type, builtin) = self.column_type(table, column)?;
+                    let key_name = keys[&table.ident].1.clone();
+                    fb = fb.add_container_struct(key_newtype(&key_name, rust_type, builtin));
+                    key_name
+                }
+                None => self.column_type(table, column)?.0,
+            };
+            if !column.not_null && !table.primary_key.contains(&column.ident) {
+                ty = format!("Option<{ty}>");
+            }
+            sb = sb.add_field(
+                Visibility::Public,
+                NamedComponentSignature::new_simple_type(field_name, RustType::in_scope(ty)),
+            );
+        }
+        let inserted = table
+            .columns
+            .iter()
+            .filter(|c| !c.generated)
+            .collect::<Vec<&Column>>();
+        let insert = if inserted.is_empty() {
+            format!("INSERT INTO {} DEFAULT VALUES", table.name)
+        } else {
+            format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table.name,
+                inserted
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+                (1..=inserted.len())
+                    .map(|n| self.placeholder(n))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        };
+        let mut ib = ImplBuilder::new(Signature::simple(RustType::in_scope(struct_name)))
+            .add_const(query_constant("INSERT", &insert));
+        if !table.primary_key.is_empty() {
+            let key_columns = table.primary_key.iter().map(|key| {
+                table
+                    .columns
+                    .iter()
+                    .find(|c| c.ident == *key)
+                    .map_or(key.as_str(), |c| c.name.as_str())
+            });
+            let select = format!(
+                "SELECT {} FROM {} WHERE {}",
+                table
+                    .columns
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+                table.name,
+                key_columns
+                    .enumerate()
+                    .map(|(i, column)| format!("{column} = {}", self.placeholder(i + 1)))
+                    .collect::<Vec<String>>()
+                    .join(" AND ")
+            );
+            ib = ib.add_const(query_constant("SELECT_BY_PK", &select));
+        }
+        Ok(fb.add_struct(sb).add_impl(ib))
+    }
+
+    fn placeholder(&self, n: usize) -> String {
+        match self.dialect {
+            Dialect::Postgres => format!("${n}"),
+            Dialect::Sqlite => format!("?{n}"),
+        }
+    }
+
+    /// The Rust type and whether it's a builtin one the derives of a newtype can rely on
+    fn column_type(&self, table: &Table, column: &Column) -> Result<(String, bool)> {
+        let (rust_type, builtin) = match self.mapped.get(&column.sql_type) {
+            Some(mapped) => (mapped.clone(), false),
+            None => {
+                let builtin = match self.dialect {
+                    Dialect::Postgres => postgres_type(&column.sql_type),
+                    Dialect::Sqlite => Some(sqlite_type(&column.sql_type)),
+                };
+                let builtin = builtin.ok_or_else(|| {
+                    Error::Schema(
+                        format!("{}.{}", table.ident, column.ident),
+                        format!("unknown type {}, map it with map_type", column.sql_type),
+                    )
+                })?;
+                (builtin.to_string(), true)
+            }
+        };
+        Ok(match column.array {
+            true => (format!("Vec<{rust_type}>"), false),
+            false => (rust_type, builtin),
+        })
+    }
+}
+
+fn key_newtype(name: &str, rust_type: String, builtin: bool) -> ContainerStructBuilder {
+    let float = builtin && rust_type.starts_with('f');
+    let copy = builtin && rust_type != "String" && rust_type != "Vec<u8>";
+    let mut newtype = ContainerStructBuilder::new(name)
+        .set_visibility(Visibility::Public)
+        .add_contained(Visibility::Public, RustType::in_scope(rust_type));
+    for (derive, enabled) in [
+        ("Debug", true),
+        ("Clone", true),
+        ("Copy", copy),
+        ("PartialEq", true),
+        ("Eq", !float),
+        ("Hash", !float),
+        ("PartialOrd", builtin && !float),
+        ("Ord", builtin && !float),
+    ] {
+        if enabled {
+            newtype = newtype.add_derive_in_scope(derive);
+        }
+    }
+    newtype
+}
+
+fn query_constant(name: &str, query: &str) -> ConstantBuilder {
+    ConstantBuilder::const_builder(name, RustType::in_scope("&str"), format!("{query:?}"))
+        .set_visibility(Visibility::Public)
+}
+
+fn postgres_type(sql_type: &str) -> Option<&'static str> {
+    Some(match sql_type {
+        "smallint" | "int2" | "smallserial" | "serial2" => "i16",
+        "integer" | "int" | "int4" | "serial" | "serial4" => "i32",
+        "bigint" | "int8" | "bigserial" | "serial8" => "i64",
+        "real" | "float4" => "f32",
+        "double precision" | "float8" | "float" => "f64",
+        "boolean" | "bool" => "bool",
+        "bytea" => "Vec<u8>",
+        "text" | "varchar" | "character varying" | "char" | "character" | "bpchar" | "citext"
+        | "name" | "numeric" | "decimal" | "uuid" | "json" | "jsonb" | "xml" | "date" | "time"
+        | "timetz" | "timestamp" | "timestamptz" | "interval" | "inet" | "cidr" | "macaddr"
+        | "money" => "String",
+        t if t.starts_with("time") => "String",
+        _ => return None,
+    })
+}
+
+/// SQLite accepts any type name and decides the affinity by what it contains
+fn sqlite_type(sql_type: &str) -> &'static str {
+    if sql_type.contains("int") {
+        "i64"
+    } else if ["char", "clob", "text"]
+        .iter()
+        .any(|t| sql_type.contains(t))
+    {
+        "String"
+    } else if sql_type.is_empty() || sql_type.contains("blob") {
+        "Vec<u8>"
+    } else if ["real", "floa", "doub"]
+        .iter()
+        .any(|t| sql_type.contains(t))
+    {
+        "f64"
+    } else if sql_type.starts_with("bool") {
+        "bool"
+    } else {
+        "String"
+    }
+}
+
+fn normalize_type(sql_type: &str) -> String {
+    sql_type
+        .split('(')
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+struct Table {
+    /// As written, ex: `public."Users"`
+    name: String,
+    /// Unquoted, without the schema
+    ident: String,
+    columns: Vec<Column>,
+    /// Unquoted column names
+    primary_key: Vec<String>,
+}
+
+struct Column {
+    /// As written
+    name: String,
+    ident: String,
+    /// Normalized, ex: `character varying`
+    sql_type: String,
+    array: bool,
+    not_null: bool,
+    /// Filled in by the database
+    generated: bool,
+    /// The referenced table and column, unquoted
+    references: Option<(String, Option<String>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// The text as written and the name it stands for, unquoted names are case-insensitive and
+    /// folded to lowercase
+    Ident(String, String),
+    Literal,
+    Symbol(char),
+}
+
+const COLUMN_KEYWORDS: [&str; 14] = [
+    "constraint",
+    "not",
+    "null",
+    "primary",
+    "unique",
+    "check",
+    "default",
+    "references",
+    "generated",
+    "collate",
+    "autoincrement",
+    "as",
+    "on",
+    "identity",
+];
+
+fn is_quoted(text: &str) -> bool {
+    text.starts_with('"')
+}
+
+fn tokenize(name: &str, source: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut chars = source.chars().peekable();
+    let unterminated = |what: &str| Error::Schema(name.to_string(), format!("unterminated {what}"));
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '-' if chars.peek() == Some(&'-') => while chars.next_if(|c| *c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(c) => previous = c,
+                        None => return Err(unterminated("comment")),
+                    }
+                }
+            }
+            '\'' => {
+                loop {
+                    match chars.next() {
+                        // Quotes are escaped by doubling them
+                        Some('\'') if chars.next_if_eq(&'\'').is_none() => break,
+                        Some(_) => {}
+                        None => return Err(unterminated("string")),
+                    }
+                }
+                tokens.push(Token::Literal);
+            }
+            '"' | '`' | '[' => {
+                let end = match c {
+                    '[' => ']',
+                    c => c,
+                };
+                let mut ident = String::new();
+                loop {
+                    match chars.next() {
+                        Some(c) if c == end && chars.next_if_eq(&end).is_none() => break,
+                        Some(c) => ident.push(c),
+                        None => return Err(unterminated("identifier")),
+                    }
+                }
+                // `[]` is an array type in Postgres
+                if c == '[' && ident.is_empty() {
+                    tokens.push(Token::Symbol('['));
+                    tokens.push(Token::Symbol(']'));
+                } else if c == '[' && ident.chars().all(|c| c.is_ascii_digit()) {
+                    tokens.push(Token::Symbol('['));
+                    tokens.push(Token::Literal);
+                    tokens.push(Token::Symbol(']'));
+                } else {
+                    let quoted = format!("\"{}\"", ident.replace('"', "\"\""));
+                    tokens.push(Token::Ident(quoted, ident));
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || "_$".contains(*c)) {
+                    ident.push(c);
+                }
+                let folded = ident.to_lowercase();
+                tokens.push(Token::Ident(ident, folded));
+            }
+            c if c.is_ascii_digit() => {
+                while chars
+                    .next_if(|c| c.is_ascii_alphanumeric() || *c == '.')
+                    .is_some()
+                {}
+                tokens.push(Token::Literal);
+            }
+            c => tokens.push(Token::Symbol(c)),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    name: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: impl Into<String>) -> Error {
+        Error::Schema(self.name.to_string(), reason.into())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Unquoted words are compared case-insensitively
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(text, ident))
+            if !is_quoted(text) && ident == keyword)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<()> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{symbol}`, found {:?}", self.peek())))
+        }
+    }
+
+    /// The name as written and unquoted
+    fn ident(&mut self) -> Result<(String, String)> {
+        match self.peek().cloned() {
+            Some(Token::Ident(text, ident)) => {
+                self.pos += 1;
+                Ok((text, ident))
+            }
+            token => Err(self.error(format!("expected a name, found {token:?}"))),
+        }
+    }
+
+    /// Schema qualified names keep their schema as written
+    fn qualified_ident(&mut self) -> Result<(String, String)> {
+        let (mut text, mut ident) = self.ident()?;
+        while self.eat('.') {
+            let (next_text, next_ident) = self.ident()?;
+            text = format!("{text}.{next_text}");
+            ident = next_ident;
+        }
+        Ok((text, ident))
+    }
+
+    fn idents_in_parens(&mut self) -> Result<Vec<String>> {
+        self.expect('(')?;
+        let mut idents = vec![self.ident()?.1];
+        while self.eat(',') {
+            idents.push(self.ident()?.1);
+        }
+        self.expect(')')?;
+        Ok(idents)
+    }
+
+    /// Skips a parenthesized group if there's one, ex: a check's expression
+    fn skip_parens(&mut self) -> Result<()> {
+        if !self.eat('(') {
+            return Ok(());
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            match self.peek() {
+                Some(Token::Symbol('(')) => depth += 1,
+                Some(Token::Symbol(')')) => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error("unbalanced parentheses")),
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    fn at_definition_end(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Symbol(',' | ')')))
+    }
+
+    fn skip_statement(&mut self) {
+        while let Some(token) = self.peek() {
+            let end = *token == Token::Symbol(';');
+            self.pos += 1;
+            if end {
+                return;
+            }
+        }
+    }
+
+    fn tables(mut self) -> Result<Vec<Table>> {
+        let mut tables = vec![];
+        while self.peek().is_some() {
+            if self.eat_keyword("create") {
+                for modifier in ["global", "local", "temp", "temporary", "unlogged"] {
+                    self.eat_keyword(modifier);
+                }
+                if self.eat_keyword("table") {
+                    if self.eat_keyword("if") {
+                        self.eat_keyword("not");
+                        self.eat_keyword("exists");
+                    }
+                    let (name, ident) = self.qualified_ident()?;
+                    // `CREATE TABLE .. AS SELECT` has no columns to read
+                    if self.peek() == Some(&Token::Symbol('(')) {
+                        tables.push(self.table(name, ident)?);
+                    }
+                }
+            }
+            self.skip_statement();
+        }
+        Ok(tables)
+    }
+
+    fn table(&mut self, name: String, ident: String) -> Result<Table> {
+        let mut table = Table {
+            name,
+            ident,
+            columns: vec![],
+            primary_key: vec![],
+        };
+        self.expect('(')?;
+        loop {
+            if self.eat_keyword("constraint") {
+                self.ident()?;
+            }
+            if self.eat_keyword("primary") {
+                self.eat_keyword("key");
+                table.primary_key = self.idents_in_parens()?;
+            } else if self.eat_keyword("foreign") {
+                self.eat_keyword("key");
+                let columns = self.idents_in_parens()?;
+                self.eat_keyword("references");
+                let target = self.qualified_ident()?.1;
+                let target_columns = match self.peek() {
+                    Some(Token::Symbol('(')) => Some(self.idents_in_parens()?),
+                    _ => None,
+                };
+                if let [column] = columns.as_slice() {
+                    if let Some(c) = table.columns.iter_mut().find(|c| c.ident == *column) {
+                        let target_column =
+                            target_columns.and_then(|t| (t.len() == 1).then(|| t[0].clone()));
+                        c.references = Some((target, target_column));
+                    }
+                }
+            } else if ["unique", "check", "exclude"]
+                .iter()
+                .any(|k| self.peek_keyword(k))
+            {
+                while !self.at_definition_end() {
+                    self.pos += 1;
+                    self.skip_parens()?;
+                }
+            } else {
+                let (column, primary_key) = self.column()?;
+                if primary_key {
+                    table.primary_key = vec![column.ident.clone()];
+                }
+                table.columns.push(column);
+            }
+            if !self.eat(',') {
+                break;
+            }
+        }
+        self.expect(')')?;
+        Ok(table)
+    }
+
+    /// The column and whether it's the primary key
+    fn column(&mut self) -> Result<(Column, bool)> {
+        let (name, ident) = self.ident()?;
+        let mut words = vec![];
+        let mut array = false;
+        while let Some(Token::Ident(text, word)) = self.peek().cloned() {
+            if is_quoted(&text) || COLUMN_KEYWORDS.contains(&word.as_str()) {
+                break;
+            }
+            self.pos += 1;
+            if word == "array" {
+                array = true;
+            } else {
+                words.push(word);
+            }
+            self.skip_parens()?;
+            while self.eat('[') {
+                array = true;
+                while !self.eat(']') {
+                    self.pos += 1;
+                }
+            }
+        }
+        let sql_type = normalize_type(&words.join(" "));
+        let mut column = Column {
+            name,
+            ident,
+            generated: sql_type.contains("serial"),
+            sql_type,
+            array,
+            not_null: false,
+            references: None,
+        };
+        let mut primary_key = false;
+        while !self.at_definition_end() {
+            if self.eat_keyword("not") {
+                column.not_null |= self.eat_keyword("null");
+            } else if self.eat_keyword("primary") {
+                primary_key = true;
+            } else if self.eat_keyword("generated") || self.eat_keyword("autoincrement") {
+                column.generated = true;
+            } else if self.eat_keyword("as") {
+                // SQLite's short form of generated columns
+                column.generated |= self.peek() == Some(&Token::Symbol('('));
+            } else if self.eat_keyword("references") {
+                let target = self.qualified_ident()?.1;
+                let target_column = match self.peek() {
+                    Some(Token::Symbol('(')) => self.idents_in_parens()?.into_iter().next(),
+                    _ => None,
+                };
+                column.references = Some((target, target_column));
+                continue;
+            } else {
+                self.pos += 1;
+            }
+            self.skip_parens()?;
+        }
+        Ok((column, primary_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sql::{Dialect, Sql};
+
+    const SCHEMA: &str = r#"
+-- Users of the app
+CREATE TABLE IF NOT EXISTS users (
+    id BIGSERIAL PRIMARY KEY,
+    "Email" VARCHAR(255) NOT NULL UNIQUE,
+    display_name text,
+    tags text[] NOT NULL DEFAULT '{}',
+    balance NUMERIC(10, 2) NOT NULL CHECK (balance >= 0)
+);
+
+CREATE INDEX users_email ON users ("Email");
+
+CREATE TABLE posts (
+    id uuid NOT NULL,
+    author_id bigint NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    title text NOT NULL,
+    CONSTRAINT posts_pk PRIMARY KEY (id)
+);
+"#;
+
+    #[test]
+    fn generates_rows() {
+        let file = Sql::parse(SCHEMA)
+            .unwrap()
+            .map_type("uuid", "uuid::Uuid")
+            .into_file_builder("rows")
+            .unwrap()
+            .format_file();
+        let expected = r#"#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UsersId(pub i64);
+#[derive(Debug, Clone, PartialEq)]
+pub struct Users {
+pub id: UsersId,
+pub email: String,
+pub display_name: Option<String>,
+pub tags: Vec<String>,
+pub balance: String,
+}
+impl Users {
+pub const INSERT: &str = "INSERT INTO users (\"Email\", display_name, tags, balance) VALUES ($1, $2, $3, $4)";
+pub const SELECT_BY_PK: &str = "SELECT id, \"Email\", display_name, tags, balance FROM users WHERE id = $1";
+}
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PostsId(pub uuid::Uuid);
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posts {
+pub id: PostsId,
+pub author_id: UsersId,
+pub title: String,
+}
+impl Posts {
+pub const INSERT: &str = "INSERT INTO posts (id, author_id, title) VALUES ($1, $2, $3)";
+pub const SELECT_BY_PK: &str = "SELECT id, author_id, title FROM posts WHERE id = $1";
+}
+"#;
+        assert_eq!(file, expected);
+    }
+
+    #[test]
+    fn follows_sqlite_affinity() {
+        let schema = "CREATE TABLE tag_links (post INTEGER, tag VARCHAR(20), \
+            weight DOUBLE, PRIMARY KEY (post, tag));";
+        let file = Sql::parse(schema)
+            .unwrap()
+            .set_dialect(Dialect::Sqlite)
+            .into_file_builder("rows")
+            .unwrap()
+            .format_file();
+        assert!(file.contains("pub post: i64,\npub tag: String,\npub weight: Option<f64>,\n"));
+        assert!(file.contains("WHERE post = ?1 AND tag = ?2\";\n"));
+        assert!(!file.contains("TagLinksId"));
+
+        let unknown = Sql::parse("CREATE TABLE t (c geometry);")
+            .unwrap()
+            .into_file_builder("rows");
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn folds_unquoted_names() {
+        let schema =
+            "CREATE TABLE Accounts (ID BIGINT NOT NULL, \"Name\" TEXT, PRIMARY KEY (id));\n\
+            CREATE TABLE grants (account BIGINT REFERENCES ACCOUNTS (Id), \"name\" TEXT);";
+        let file = Sql::parse(schema)
+            .unwrap()
+            .into_file_builder("rows")
+            .unwrap()
+            .format_file();
+        assert!(file.contains("pub struct AccountsId(pub i64);\n"));
+        assert!(file.contains("pub id: AccountsId,\npub name: Option<String>,\n"));
+        assert!(file.contains("pub account: Option<AccountsId>,\n"));
+        assert!(file.contains("SELECT ID, \\\"Name\\\" FROM Accounts WHERE ID = $1\";\n"));
+
+        let quoted = Sql::parse("CREATE TABLE t (\"Id\" integer, PRIMARY KEY (id));")
+            .unwrap()
+            .into_file_builder("rows")
+            .unwrap()
+            .format_file();
+        assert!(!quoted.contains("TId"));
+    }
+
+    #[test]
+    fn keeps_names_apart() {
+        let schema = "CREATE TABLE option (id BIGINT PRIMARY KEY, note TEXT);\n\
+            CREATE TABLE users (id BIGINT PRIMARY KEY, user_id BIGINT, \"userId\" BIGINT);\n\
+            CREATE TABLE users_id (owner BIGINT REFERENCES users);\n\
+            CREATE TABLE a (x INT);\n\
+            CREATE TABLE \"A\" (y INT);";
+        let file = Sql::parse(schema)
+            .unwrap()
+            .into_file_builder("rows")
+            .unwrap()
+            .format_file();
+        assert!(file
+            .contains("pub struct Option2 {\npub id: OptionId,\npub note: Option<String>,\n}\n"));
+        assert!(file.contains("impl Option2 {\n"));
+        assert!(file.contains("pub struct UsersId(pub i64);\n"));
+        assert!(file.contains(
+            "pub struct Users {\npub id: UsersId,\npub user_id: Option<i64>,\npub user_id2: Option<i64>,\n}\n"
+        ));
+        assert!(file.contains("SELECT id, user_id, \\\"userId\\\" FROM users"));
+        assert!(file.contains("pub struct UsersId2 {\npub owner: Option<UsersId>,\n}\n"));
+        assert!(file.contains("pub struct A {\npub x: Option<i32>,\n}\n"));
+        assert!(file.contains("pub struct A2 {\npub y: Option<i32>,\n}\n"));
+    }
+}
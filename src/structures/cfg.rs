@@ -0,0 +1,151 @@
+use crate::structures::Annotation;
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+
+/// A `cfg` predicate, rendered as `#[cfg(..)]` on whatever it's attached to
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Cfg {
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+    Feature(String),
+    TargetOs(String),
+    Test,
+    /// Anything else, written as is, ex: `debug_assertions` or `target_pointer_width = "64"`
+    Raw(String),
+}
+
+impl Cfg {
+    pub fn feature(name: impl Into<String>) -> Self {
+        Cfg::Feature(name.into())
+    }
+
+    pub fn target_os(os: impl Into<String>) -> Self {
+        Cfg::TargetOs(os.into())
+    }
+
+    pub fn test() -> Self {
+        Cfg::Test
+    }
+
+    pub fn raw(predicate: impl Into<String>) -> Self {
+        Cfg::Raw(predicate.into())
+    }
+
+    pub fn all(predicates: impl IntoIterator<Item = Cfg>) -> Self {
+        Cfg::All(predicates.into_iter().collect())
+    }
+
+    pub fn any(predicates: impl IntoIterator<Item = Cfg>) -> Self {
+        Cfg::Any(predicates.into_iter().collect())
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(predicate: Cfg) -> Self {
+        Cfg::Not(Box::new(predicate))
+    }
+
+    /// Both have to hold, nested `all`s are flattened
+    pub fn and(self, other: Cfg) -> Self {
+        match (self, other) {
+            (Cfg::All(mut left), Cfg::All(right)) => {
+                left.extend(right);
+                Cfg::All(left)
+            }
+            (Cfg::All(mut left), right) => {
+                left.push(right);
+                Cfg::All(left)
+            }
+            (left, Cfg::All(mut right)) => {
+                right.insert(0, left);
+                Cfg::All(right)
+            }
+            (left, right) => Cfg::All(vec![left, right]),
+        }
+    }
+
+    pub fn to_annotation(&self) -> Annotation {
+        Annotation::new(format!("cfg({self})"))
+    }
+
+    /// Every feature the predicate refers to, negated or not
+    pub fn features(&self) -> BTreeSet<String> {
+        let mut features = BTreeSet::new();
+        self.collect_features(&mut features);
+        features
+    }
+
+    pub(crate) fn collect_features(&self, features: &mut BTreeSet<String>) {
+        match self {
+            Cfg::All(predicates) | Cfg::Any(predicates) => {
+                for predicate in predicates {
+                    predicate.collect_features(features);
+                }
+            }
+            Cfg::Not(predicate) => predicate.collect_features(features),
+            Cfg::Feature(name) => {
+                features.insert(name.clone());
+            }
+            Cfg::TargetOs(_) | Cfg::Test | Cfg::Raw(_) => {}
+        }
+    }
+}
+
+impl Display for Cfg {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let list = |predicates: &[Cfg]| {
+            predicates
+                .iter()
+                .map(Cfg::to_string)
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        match self {
+            Cfg::All(predicates) => write!(f, "all({})", list(predicates)),
+            Cfg::Any(predicates) => write!(f, "any({})", list(predicates)),
+            Cfg::Not(predicate) => write!(f, "not({predicate})"),
+            Cfg::Feature(name) => write!(f, "feature = {name:?}"),
+            Cfg::TargetOs(os) => write!(f, "target_os = {os:?}"),
+            Cfg::Test => f.write_str("test"),
+            Cfg::Raw(predicate) => f.write_str(predicate),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::cfg::Cfg;
+
+    #[test]
+    fn formats_predicates() {
+        let cfg = Cfg::all([
+            Cfg::feature("serde"),
+            Cfg::any([Cfg::target_os("linux"), Cfg::not(Cfg::test())]),
+        ]);
+        assert_eq!(
+            "all(feature = \"serde\", any(target_os = \"linux\", not(test)))",
+            cfg.to_string()
+        );
+        assert_eq!(
+            "#[cfg(all(feature = \"serde\", any(target_os = \"linux\", not(test))))]\n",
+            cfg.to_annotation().format()
+        );
+        assert_eq!(
+            Cfg::all([Cfg::feature("a"), Cfg::feature("b"), Cfg::feature("c")]),
+            Cfg::feature("a")
+                .and(Cfg::feature("b"))
+                .and(Cfg::feature("c"))
+        );
+        assert_eq!(
+            Cfg::all([Cfg::feature("a"), Cfg::feature("b"), Cfg::feature("c")]),
+            Cfg::feature("a").and(Cfg::all([Cfg::feature("b"), Cfg::feature("c")]))
+        );
+        assert_eq!(
+            vec!["a".to_string(), "b".to_string()],
+            Cfg::any([Cfg::feature("b"), Cfg::not(Cfg::feature("a"))])
+                .features()
+                .into_iter()
+                .collect::<Vec<String>>()
+        );
+    }
+}
@@ -71,9 +71,9 @@ impl EnumEntity {
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct EnumMember {
-    name: String,
-    member_type: MemberType,
-    annotations: Annotations,
+    pub(crate) name: String,
+    pub(crate) member_type: MemberType,
+    pub(crate) annotations: Annotations,
 }
 
 impl EnumMember {
@@ -88,13 +88,23 @@ impl StructEntity {
 
 #[derive(Debug, Clone)]
 pub struct Field {
+    pub(crate) annotations: Annotations,
     visibility: Visibility,
     pub named_component_signature: NamedComponentSignature,
 }
 
 impl Field {
     pub fn new(visibility: Visibility, named_component_signature: NamedComponentSignature) -> Self {
+        Self::new_with_annotations(visibility, named_component_signature, Annotations::empty())
+    }
+
+    pub fn new_with_annotations(
+        visibility: Visibility,
+        named_component_signature: NamedComponentSignature,
+        annotations: Annotations,
+    ) -> Self {
         Self {
+            annotations,
             visibility,
             named_component_signature,
         }
@@ -102,7 +112,8 @@ impl Field {
 
     pub fn format_line(&self) -> String {
         format!(
-            "{}{}: {},\n",
+            "{}{}{}: {},\n",
+            self.annotations.format(),
             self.visibility,
             self.named_component_signature.name,
             self.named_component_signature.component_signature
@@ -1,4 +1,4 @@
-use crate::{Annotations, Method, Signature, Visibility};
+use crate::{Annotations, Method, RustType, Signature, Visibility};
 use std::fmt::Write;
 
 #[derive(Debug)]
@@ -13,16 +13,19 @@ pub struct TraitEntity {
 
 impl TraitEntity {
     pub fn format(&self) -> String {
-        let diamond = self.trait_type.get_generics().format();
+        let diamond = self.trait_type.get_associated_generics().format();
         let container_owned = self.trait_type.get_generics().clone();
         let mut base = format!(
             "{}{}trait {}{diamond}",
-            self.visibility.format(),
             self.annotations.format(),
-            self.trait_type.rust_type().as_ref().unwrap().format(),
+            self.visibility.format(),
+            self.trait_type
+                .rust_type()
+                .map(RustType::format)
+                .unwrap_or_else(|| self.trait_type.get_any_alias()),
         );
         if !self.super_traits.is_empty() {
-            base.push_str(":");
+            base.push(':');
         }
         for (ind, sig) in self.super_traits.iter().enumerate() {
             let _ = base.write_fmt(format_args!(" {}", sig.format()));
@@ -122,7 +122,7 @@ impl Generics {
             let generic = Generic::bounded(signature, Bounds::multiple(b));
             generics.push((bounds.0, generic));
         }
-        generics.sort_by(|a, b| a.0.cmp(&b.0));
+        generics.sort_by_key(|a| a.0);
         Generics::multiple(generics.into_iter().map(|(_, g)| g).collect())
     }
 }
@@ -130,7 +130,7 @@ impl Generics {
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Generic {
     pub(crate) alias: String,
-    bounds: Bounds,
+    pub(crate) bounds: Bounds,
 }
 
 impl Generic {
@@ -150,7 +150,7 @@ impl Method {
         );
         if let Some(body) = self.body.as_ref() {
             method_base.push_str("{\n");
-            method_base.push_str(&body);
+            method_base.push_str(body);
             method_base.push_str("\n}\n");
         } else {
             method_base.push_str(";\n");
@@ -188,7 +188,7 @@ impl Method {
 #[derive(Debug, Clone)]
 pub struct Argument {
     ownership: Ownership,
-    named_sign: NamedComponentSignature,
+    pub(crate) named_sign: NamedComponentSignature,
 }
 
 impl Argument {
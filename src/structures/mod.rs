@@ -1,8 +1,11 @@
+use crate::structures::cfg::Cfg;
 use crate::structures::gen_enum::NamedComponentSignature;
 use crate::structures::generics::{Generic, Generics};
 use crate::structures::visibility::Visibility;
+use crate::util::casing::Edition;
 use std::fmt::{Display, Formatter};
 
+pub mod cfg;
 pub mod gen_const;
 pub mod gen_enum;
 pub mod gen_impl;
@@ -16,6 +19,11 @@ pub const BASE_INDENT: usize = 4;
 
 pub trait ToSourceFilePart {
     fn format_source_file_part(&self) -> String;
+
+    /// Parts containing names that may need escaping override this
+    fn format_source_file_part_for_edition(&self, _edition: Edition) -> String {
+        self.format_source_file_part()
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -202,11 +210,14 @@ impl TypeDef {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Import {
     /// Import a type like std::collections::HashMap;
     FullType(RustType),
     /// Any freetext import like `"use crate::structures::*;\n"`
     Spec(String),
+    /// An import only present when the cfg holds
+    Gated(Cfg, Box<Import>),
 }
 
 impl ToSourceFilePart for Import {
@@ -219,10 +230,21 @@ impl Import {
     pub fn spec(spec: impl Into<String>) -> Self {
         Import::Spec(spec.into())
     }
+
+    pub fn with_cfg(self, cfg: Cfg) -> Self {
+        match self {
+            Import::Gated(existing, import) => Import::Gated(existing.and(cfg), import),
+            import => Import::Gated(cfg, Box::new(import)),
+        }
+    }
+
     pub fn format(&self) -> String {
         match self {
             Import::FullType(rt) => format!("use {};\n", rt.format()),
             Import::Spec(s) => format!("use {s};\n"),
+            Import::Gated(cfg, import) => {
+                format!("{}{}", cfg.to_annotation().format(), import.format())
+            }
         }
     }
 }
@@ -283,6 +305,7 @@ pub struct Derives {
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Annotations {
     pub(crate) annotations: Vec<Annotation>,
+    pub(crate) cfg: Option<Cfg>,
 }
 
 impl ToSourceFilePart for Annotations {
@@ -293,25 +316,44 @@ impl ToSourceFilePart for Annotations {
 
 impl Annotations {
     pub fn new(annotations: Vec<Annotation>) -> Self {
-        Self { annotations }
+        Self {
+            annotations,
+            cfg: None,
+        }
     }
 
     pub fn empty() -> Self {
         Self {
             annotations: vec![],
+            cfg: None,
         }
     }
 
+    /// Gates whatever the annotations are on, a second cfg is combined with `all`
+    pub fn with_cfg(mut self, cfg: Cfg) -> Self {
+        self.add_cfg(cfg);
+        self
+    }
+
+    pub(crate) fn add_cfg(&mut self, cfg: Cfg) {
+        self.cfg = Some(match self.cfg.take() {
+            Some(existing) => existing.and(cfg),
+            None => cfg,
+        });
+    }
+
+    pub fn cfg(&self) -> Option<&Cfg> {
+        self.cfg.as_ref()
+    }
+
     pub fn format(&self) -> String {
-        if self.annotations.is_empty() {
-            String::new()
-        } else {
-            self.annotations
-                .iter()
-                .map(Annotation::format)
-                .collect::<Vec<String>>()
-                .join("")
-        }
+        self.cfg
+            .iter()
+            .map(Cfg::to_annotation)
+            .chain(self.annotations.iter().cloned())
+            .map(|a| a.format())
+            .collect::<Vec<String>>()
+            .join("")
     }
 }
 
@@ -361,6 +403,7 @@ pub struct Module {
     visibility: Visibility,
     annotations: Annotations,
     name: String,
+    body: Option<String>,
 }
 
 impl Module {
@@ -369,16 +412,39 @@ impl Module {
             visibility,
             name: name.into(),
             annotations,
+            body: None,
+        }
+    }
+
+    pub fn new_inline(
+        visibility: Visibility,
+        name: impl Into<String>,
+        annotations: Annotations,
+        body: impl Into<String>,
+    ) -> Self {
+        Self {
+            visibility,
+            name: name.into(),
+            annotations,
+            body: Some(body.into()),
         }
     }
 
     pub fn format(&self) -> String {
-        format!(
-            "{}{}mod {};\n",
-            self.annotations.format(),
-            self.visibility,
-            self.name
-        )
+        match &self.body {
+            Some(body) => format!(
+                "{}{}mod {} {{\n{body}}}\n",
+                self.annotations.format(),
+                self.visibility,
+                self.name
+            ),
+            None => format!(
+                "{}{}mod {};\n",
+                self.annotations.format(),
+                self.visibility,
+                self.name
+            ),
+        }
     }
 }
 #[cfg(test)]
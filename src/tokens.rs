@@ -0,0 +1,139 @@
+use crate::structures::{ComponentSignature, RustType, Signature, ToSourceFilePart};
+use crate::{
+    ConstantBuilder, ContainerStructBuilder, Edition, EnumBuilder, FileBuilder, FunctionBuilder,
+    ImplBuilder, MethodBuilder, StructBuilder, TraitBuilder,
+};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use std::str::FromStr;
+
+/// Lexes generated source into a `TokenStream`.
+/// If the source doesn't lex (unbalanced delimiters in a body string for example) a
+/// `compile_error!` is emitted instead, which is what a proc-macro would want to do anyway.
+pub fn source_to_tokens(source: &str) -> TokenStream {
+    match TokenStream::from_str(source) {
+        Ok(ts) => ts,
+        Err(e) => {
+            let msg = format!("codegen-rs produced source that could not be lexed: {e}");
+            quote!(compile_error!(#msg);)
+        }
+    }
+}
+
+macro_rules! impl_to_tokens {
+    ($ty: ty, $fmt: expr) => {
+        impl ToTokens for $ty {
+            fn to_tokens(&self, tokens: &mut TokenStream) {
+                let format: fn(&$ty) -> String = $fmt;
+                tokens.extend(source_to_tokens(&format(self)));
+            }
+        }
+
+        impl From<$ty> for TokenStream {
+            fn from(value: $ty) -> Self {
+                value.into_token_stream()
+            }
+        }
+    };
+}
+
+impl_to_tokens!(FileBuilder, FileBuilder::format_file);
+impl_to_tokens!(ConstantBuilder, ConstantBuilder::format_source_file_part);
+impl_to_tokens!(FunctionBuilder, FunctionBuilder::format_source_file_part);
+impl_to_tokens!(EnumBuilder, EnumBuilder::format_source_file_part);
+impl_to_tokens!(TraitBuilder, TraitBuilder::format_source_file_part);
+impl_to_tokens!(
+    ContainerStructBuilder,
+    ContainerStructBuilder::format_source_file_part
+);
+impl_to_tokens!(StructBuilder, StructBuilder::format_source_file_part);
+impl_to_tokens!(ImplBuilder, ImplBuilder::format_source_file_part);
+impl_to_tokens!(MethodBuilder, |mb| mb
+    .clone()
+    .build(Edition::default())
+    .format());
+impl_to_tokens!(RustType, RustType::format);
+impl_to_tokens!(Signature, Signature::format);
+impl_to_tokens!(ComponentSignature, |cs| cs.to_string());
+
+impl FunctionBuilder {
+    pub fn set_body_tokens(mut self, body: impl ToTokens) -> Self {
+        self.body = body.to_token_stream().to_string();
+        self
+    }
+}
+
+impl MethodBuilder {
+    pub fn set_body_tokens(mut self, body: impl ToTokens) -> Self {
+        self.body = Some(body.to_token_stream().to_string());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::visibility::Visibility;
+    use crate::structures::{ComponentSignature, Ownership, RustType, Signature};
+    use crate::tokens::source_to_tokens;
+    use crate::{FileBuilder, FunctionBuilder, ImplBuilder, MethodBuilder, StructBuilder};
+    use quote::{quote, ToTokens};
+
+    #[test]
+    fn file_to_tokens() {
+        let my_struct = Signature::simple(RustType::in_scope("MyStruct"));
+        let fb = FileBuilder::new("my_file")
+            .add_struct(
+                StructBuilder::new_from_signature(&my_struct)
+                    .set_visibility(Visibility::Public)
+                    .add_field_in_scope_simple_type(Visibility::Public, "val", "i32"),
+            )
+            .add_impl(
+                ImplBuilder::new(my_struct.clone()).add_method(
+                    MethodBuilder::new("val")
+                        .set_self_ownership(Ownership::Ref)
+                        .set_return_type(ComponentSignature::Signature(Signature::simple(
+                            RustType::in_scope("i32"),
+                        )))
+                        .set_body_tokens(quote!(self.val)),
+                ),
+            );
+        let expect = quote! {
+            pub struct MyStruct {
+                pub val: i32,
+            }
+            impl MyStruct {
+                fn val(&self) -> i32 {
+                    self.val
+                }
+            }
+        };
+        assert_eq!(expect.to_string(), fb.to_token_stream().to_string());
+    }
+
+    #[test]
+    fn interpolates_into_quote() {
+        let rt = RustType::from_package("std::collections", "HashMap");
+        let func = FunctionBuilder::new("make")
+            .set_return_type(ComponentSignature::Signature(Signature::simple(
+                RustType::in_scope("usize"),
+            )))
+            .set_body("5");
+        let ts = quote!(type Map = #rt; #func);
+        assert_eq!(
+            quote!(
+                type Map = std::collections::HashMap;
+                fn make() -> usize {
+                    5
+                }
+            )
+            .to_string(),
+            ts.to_string()
+        );
+    }
+
+    #[test]
+    fn unlexable_becomes_compile_error() {
+        let ts = source_to_tokens("fn broken() {");
+        assert!(ts.to_string().starts_with("compile_error !"));
+    }
+}
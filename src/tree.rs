@@ -0,0 +1,211 @@
+use crate::structures::visibility::Visibility;
+use crate::structures::{Import, TypeDef};
+use crate::{
+    Annotations, ConstantBuilder, ContainerStructBuilder, EnumBuilder, FileBuilder,
+    FunctionBuilder, ImplBuilder, ModuleBuilder, ModuleFile, StructBuilder, Submodule,
+    TraitBuilder,
+};
+
+/// A file somewhere in a module tree, see `ModuleBuilder::file`
+pub struct FileRef<'a> {
+    file: &'a mut FileBuilder,
+}
+
+macro_rules! delegate_add {
+    ($($name: ident($arg: ty)),* $(,)?) => {
+        $(
+            pub fn $name(self, value: $arg) -> Self {
+                self.update(|fb| fb.$name(value))
+            }
+        )*
+    };
+}
+
+impl<'a> FileRef<'a> {
+    /// Applies any by-value `FileBuilder` method to the file in place
+    pub fn update(self, f: impl FnOnce(FileBuilder) -> FileBuilder) -> Self {
+        let taken = std::mem::replace(self.file, FileBuilder::new(""));
+        *self.file = f(taken);
+        self
+    }
+
+    pub fn builder(&mut self) -> &mut FileBuilder {
+        self.file
+    }
+
+    delegate_add!(
+        add_import(Import),
+        add_const(ConstantBuilder),
+        add_type_def(TypeDef),
+        add_struct(StructBuilder),
+        add_container_struct(ContainerStructBuilder),
+        add_function(FunctionBuilder),
+        add_enum(EnumBuilder),
+        add_trait(TraitBuilder),
+        add_impl(ImplBuilder),
+    );
+
+    pub fn add_any(self, any: impl Into<String>) -> Self {
+        self.update(|fb| fb.add_any(any))
+    }
+}
+
+impl ModuleBuilder {
+    /// Visibility of modules created by `file` and `module`, defaults to public
+    pub fn set_default_visibility(mut self, visibility: Visibility) -> Self {
+        self.default_visibility = visibility;
+        self
+    }
+
+    /// The file at a module path relative to this module, ex: `api::v1::models`.
+    /// Missing modules on the way are created, a file that gets children is turned into a
+    /// submodule. An empty path is this module's own file
+    pub fn file(&mut self, path: &str) -> FileRef<'_> {
+        let mut segments = split_path(path);
+        let Some(last) = segments.pop() else {
+            return FileRef {
+                file: &mut self.mod_file,
+            };
+        };
+        let parent = self.descend(&segments);
+        if let Some(ind) = parent
+            .submodules
+            .iter()
+            .position(|s| s.builder.mod_file.name == last)
+        {
+            return FileRef {
+                file: &mut parent.submodules[ind].builder.mod_file,
+            };
+        }
+        let ind = match parent
+            .module_files
+            .iter()
+            .position(|f| f.builder.name == last)
+        {
+            Some(ind) => ind,
+            None => {
+                parent.module_files.push(ModuleFile {
+                    visibility: parent.default_visibility,
+                    builder: FileBuilder::new(last),
+                    annotations: Annotations::empty(),
+                });
+                parent.module_files.len() - 1
+            }
+        };
+        FileRef {
+            file: &mut parent.module_files[ind].builder,
+        }
+    }
+
+    /// The module at a path relative to this one, created like in `file`
+    pub fn module(&mut self, path: &str) -> &mut ModuleBuilder {
+        self.descend(&split_path(path))
+    }
+
+    fn descend(&mut self, segments: &[&str]) -> &mut ModuleBuilder {
+        let Some((first, rest)) = segments.split_first() else {
+            return self;
+        };
+        let ind = match self
+            .submodules
+            .iter()
+            .position(|s| s.builder.mod_file.name == *first)
+        {
+            Some(ind) => ind,
+            None => {
+                let submodule = match self
+                    .module_files
+                    .iter()
+                    .position(|f| f.builder.name == *first)
+                {
+                    Some(file_ind) => {
+                        let file = self.module_files.remove(file_ind);
+                        Submodule {
+                            visibility: file.visibility,
+                            annotations: file.annotations,
+                            builder: ModuleBuilder::new(file.builder),
+                        }
+                    }
+                    None => Submodule {
+                        visibility: self.default_visibility,
+                        annotations: Annotations::empty(),
+                        builder: ModuleBuilder::new(FileBuilder::new(*first)),
+                    },
+                };
+                let mut submodule = submodule;
+                submodule.builder.default_visibility = self.default_visibility;
+                self.submodules.push(submodule);
+                self.submodules.len() - 1
+            }
+        };
+        self.submodules[ind].builder.descend(rest)
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split("::")
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::visibility::Visibility;
+    use crate::{EnumBuilder, FileBuilder, ModuleBuilder, StructBuilder};
+    use std::path::Path;
+
+    #[test]
+    fn inserts_by_path() {
+        let mut tree = ModuleBuilder::new(FileBuilder::new("lib"));
+        tree.file("api::v1::models")
+            .add_struct(StructBuilder::new("User"));
+        tree.file("api").add_enum(EnumBuilder::new("Version"));
+        // Revisiting from a later pass appends to the same file
+        tree.file("api::v1::models")
+            .add_struct(StructBuilder::new("Group"));
+        let mut tree = tree.set_default_visibility(Visibility::PublicCrate);
+        tree.file("util").add_any("const X: u8 = 1;\n");
+        let rendered = tree.render();
+        let paths = rendered
+            .keys()
+            .map(|p| p.to_str().unwrap())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            vec![
+                "api/mod.rs",
+                "api/v1/mod.rs",
+                "api/v1/models.rs",
+                "lib.rs",
+                "util.rs"
+            ],
+            paths
+        );
+        assert_eq!(
+            "pub(crate) mod util;\npub mod api;\n",
+            rendered[Path::new("lib.rs")]
+        );
+        assert_eq!(
+            "pub mod v1;\nenum Version {\n}\n",
+            rendered[Path::new("api/mod.rs")]
+        );
+        assert_eq!(
+            "struct User {\n}\nstruct Group {\n}\n",
+            rendered[Path::new("api/v1/models.rs")]
+        );
+    }
+
+    #[test]
+    fn promotes_files_to_modules() {
+        let mut tree = ModuleBuilder::new(FileBuilder::new("lib"));
+        tree.file("models").add_struct(StructBuilder::new("A"));
+        tree.file("models::nested")
+            .add_struct(StructBuilder::new("B"));
+        let rendered = tree.render();
+        assert_eq!(
+            "pub mod nested;\nstruct A {\n}\n",
+            rendered[Path::new("models/mod.rs")]
+        );
+        assert_eq!("pub mod models;\n", rendered[Path::new("lib.rs")]);
+    }
+}
@@ -60,7 +60,6 @@ impl InferCase {
             } else if char.is_alphanumeric() && !char.is_numeric() && !char.is_uppercase() {
                 all_chars_uppercased = false;
             }
-            if char.is_numeric() && ind == 0 {}
             if !char.is_numeric() && char.is_uppercase() {
                 num_uppercases += 1;
                 if ind == 0 {
@@ -290,8 +289,34 @@ impl RustCase {
     }
 
     pub fn convert_to_valid_rust(input: &str, to: Self) -> Result<String> {
+        Self::convert_to_valid_rust_for_edition(input, to, Edition::default())
+    }
+
+    pub fn convert_to_valid_rust_for_edition(
+        input: &str,
+        to: Self,
+        edition: Edition,
+    ) -> Result<String> {
         let s = Self::convert(input, to)?;
-        Ok(fix_keyword(&s))
+        Ok(fix_keyword_for_edition(&s, edition))
+    }
+
+    /// Converts any name, ex: from a schema, into a valid name of the given case.
+    /// Anything that isn't alphanumeric separates words and names that can't start an identifier
+    /// get a `value` prefix
+    pub fn convert_lossy(input: &str, to: Self) -> String {
+        let joined = input
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| Self::convert(w, RustCase::Snake).unwrap_or_else(|_| w.to_lowercase()))
+            .collect::<Vec<String>>()
+            .join("_");
+        let joined = match joined.chars().next() {
+            Some(first) if first.is_alphabetic() => joined,
+            Some(_) => format!("value_{joined}"),
+            None => "value".to_string(),
+        };
+        Self::convert_to_valid_rust(&joined, to).unwrap_or(joined)
     }
 }
 
@@ -528,23 +553,110 @@ fn scream_to_cobol(input: &str) -> String {
     input.replace('_', "-")
 }
 
-const KEYWORDS: [&str; 51] = [
+/// The edition generated code targets, decides which words are reserved
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+pub enum Edition {
+    E2015,
+    E2018,
+    #[default]
+    E2021,
+    E2024,
+}
+
+impl Edition {
+    /// As written in `Cargo.toml`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        }
+    }
+
+    pub fn is_keyword(self, input: &str) -> bool {
+        KEYWORDS.contains(&input)
+            || (self >= Edition::E2018 && KEYWORDS_2018.contains(&input))
+            || (self >= Edition::E2024 && KEYWORDS_2024.contains(&input))
+    }
+}
+
+// Strict and reserved keywords of every edition
+const KEYWORDS: [&str; 47] = [
     "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
     "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
     "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
-    "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro",
-    "override", "priv", "typeof", "unsized", "virtual", "yield", "try",
+    "while", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "typeof",
+    "unsized", "virtual", "yield",
 ];
 
+const KEYWORDS_2018: [&str; 4] = ["async", "await", "dyn", "try"];
+
+const KEYWORDS_2024: [&str; 1] = ["gen"];
+
+// These can't be raw identifiers, `r#self` is illegal
+const NON_RAW_KEYWORDS: [&str; 4] = ["self", "Self", "super", "crate"];
+
+/// Fixes keywords for the default edition, see `fix_keyword_for_edition`
 pub fn fix_keyword(input: &str) -> String {
+    fix_keyword_for_edition(input, Edition::default())
+}
+
+/// Keywords become raw identifiers, except the ones that can't be raw which get a `_` suffix
+pub fn fix_keyword_for_edition(input: &str, edition: Edition) -> String {
     // inefficient but whatever i'm not pulling in lazy_static
-    if KEYWORDS.contains(&input) {
+    if escape_renames(input) {
+        format!("{input}_")
+    } else if edition.is_keyword(input) {
         format!("r#{input}")
     } else {
         input.to_string()
     }
 }
 
+/// Whether fixing the keyword changes the name (`self` -> `self_`) rather than making it raw,
+/// if so the original name should be recorded somewhere, like a serde rename
+pub fn escape_renames(input: &str) -> bool {
+    NON_RAW_KEYWORDS.contains(&input)
+}
+
+/// Checks that the input can be used as a name, raw identifiers are accepted
+pub(crate) fn is_valid_identifier(input: &str, edition: Edition) -> bool {
+    let (raw, ident) = match input.strip_prefix("r#") {
+        Some(stripped) => (true, stripped),
+        None => (false, input),
+    };
+    let mut chars = ident.chars();
+    let valid_chars = match chars.next() {
+        Some(first) if first == '_' || first.is_alphabetic() => {
+            chars.all(|c| c == '_' || c.is_alphanumeric())
+        }
+        _ => false,
+    };
+    if !valid_chars || ident == "_" {
+        false
+    } else if raw {
+        !escape_renames(ident)
+    } else {
+        !edition.is_keyword(ident)
+    }
+}
+
+/// Names the items of an array, ex: `addresses` holds `Address`es
+#[cfg(any(feature = "json-schema", feature = "config"))]
+pub(crate) fn singular(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies").filter(|s| !s.is_empty()) {
+        format!("{stem}y")
+    } else if let Some(stem) = name
+        .strip_suffix('s')
+        .filter(|s| !s.is_empty() && !s.ends_with('s'))
+    {
+        stem.to_string()
+    } else {
+        format!("{name}Item")
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -646,6 +758,52 @@ mod tests {
         assert!(InferCase::infer("hello_").is_err());
     }
 
+    #[test]
+    fn keywords() {
+        assert_eq!("r#type", fix_keyword("type"));
+        assert_eq!("self_", fix_keyword("self"));
+        assert_eq!("Self_", fix_keyword("Self"));
+        assert_eq!("my_field", fix_keyword("my_field"));
+        assert_eq!("r#type", fix_keyword("r#type"));
+        assert!(escape_renames("crate"));
+        assert!(!escape_renames("type"));
+    }
+
+    #[test]
+    fn edition_keywords() {
+        assert_eq!("dyn", fix_keyword_for_edition("dyn", Edition::E2015));
+        assert_eq!("r#dyn", fix_keyword_for_edition("dyn", Edition::E2018));
+        assert_eq!("gen", fix_keyword_for_edition("gen", Edition::E2021));
+        assert_eq!("r#gen", fix_keyword_for_edition("gen", Edition::E2024));
+        assert_eq!("self_", fix_keyword_for_edition("self", Edition::E2015));
+        assert_eq!(
+            "r#try",
+            RustCase::convert_to_valid_rust_for_edition("Try", RustCase::Snake, Edition::E2021)
+                .unwrap()
+        );
+        assert_eq!(
+            "try",
+            RustCase::convert_to_valid_rust_for_edition("Try", RustCase::Snake, Edition::E2015)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        let edition = Edition::default();
+        assert!(is_valid_identifier("my_field", edition));
+        assert!(is_valid_identifier("_private", edition));
+        assert!(is_valid_identifier("r#type", edition));
+        assert!(!is_valid_identifier("type", edition));
+        assert!(!is_valid_identifier("r#self", edition));
+        assert!(!is_valid_identifier("my field", edition));
+        assert!(!is_valid_identifier("1st", edition));
+        assert!(!is_valid_identifier("_", edition));
+        assert!(!is_valid_identifier("", edition));
+        assert!(is_valid_identifier("async", Edition::E2015));
+        assert!(!is_valid_identifier("async", Edition::E2018));
+    }
+
     #[test]
     fn test_spaced() {
         let input = "my struct";
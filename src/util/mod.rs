@@ -15,6 +15,16 @@ macro_rules! add_annotation {
     };
 }
 
+macro_rules! add_cfg {
+    () => {
+        /// Renders `#[cfg(..)]` on the item, calling it again combines the predicates with `all`
+        pub fn add_cfg(mut self, cfg: crate::structures::cfg::Cfg) -> Self {
+            self.annotations.add_cfg(cfg);
+            self
+        }
+    };
+}
+
 macro_rules! add_derive {
     () => {
         pub fn add_derive_in_scope(mut self, type_name: impl Into<String>) -> Self {
@@ -104,6 +114,49 @@ macro_rules! add_argument {
     };
 }
 
+macro_rules! set_keyword_escaping {
+    () => {
+        /// Names are escaped when rendered by default, `type` becomes `r#type` and `self` becomes `self_`
+        pub fn disable_keyword_escaping(mut self) -> Self {
+            self.escape_keywords = false;
+            self
+        }
+
+        pub(crate) fn escape_name(
+            &self,
+            name: &str,
+            edition: crate::util::casing::Edition,
+        ) -> String {
+            if self.escape_keywords {
+                crate::util::casing::fix_keyword_for_edition(name, edition)
+            } else {
+                name.to_string()
+            }
+        }
+    };
+}
+
+macro_rules! set_rename_annotation {
+    () => {
+        /// Annotation added to members renamed by keyword escaping, `{}` is replaced by the
+        /// original name, ex: `serde(rename = "{}")`
+        pub fn set_rename_annotation(mut self, template: impl Into<String>) -> Self {
+            self.rename_annotation = Some(template.into());
+            self
+        }
+
+        fn rename_annotation_for(&self, name: &str) -> Option<crate::structures::Annotation> {
+            if self.escape_keywords && crate::util::casing::escape_renames(name) {
+                self.rename_annotation
+                    .as_ref()
+                    .map(|t| crate::structures::Annotation::new(t.replace("{}", name)))
+            } else {
+                None
+            }
+        }
+    };
+}
+
 macro_rules! set_return_type {
     () => {
         pub fn set_return_type(
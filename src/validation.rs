@@ -0,0 +1,567 @@
+use crate::errors::{Error, Result};
+use crate::structures::gen_enum::MemberType;
+use crate::structures::generics::Generic;
+use crate::structures::{ComponentSignature, Signature, ToSourceFilePart, TypeDef};
+use crate::util::casing::{is_valid_identifier, Edition};
+use crate::{
+    ConstantBuilder, ContainerStructBuilder, EnumBuilder, FileBuilder, FunctionBuilder,
+    ImplBuilder, MethodBuilder, ModuleBuilder, StructBuilder, TraitBuilder,
+};
+use std::collections::HashSet;
+
+/// Checks that a builder will produce code that compiles as far as names and signatures go,
+/// bodies and free text types are not checked
+pub trait Validate: ToSourceFilePart {
+    /// Names are checked against the keywords of the given edition
+    fn validate_for_edition(&self, edition: Edition) -> Result<()>;
+
+    fn validate(&self) -> Result<()> {
+        self.validate_for_edition(Edition::default())
+    }
+
+    fn try_format(&self) -> Result<String> {
+        self.try_format_for_edition(Edition::default())
+    }
+
+    fn try_format_for_edition(&self, edition: Edition) -> Result<String> {
+        self.validate_for_edition(edition)?;
+        Ok(self.format_source_file_part_for_edition(edition))
+    }
+}
+
+fn check_identifier(name: &str, context: &str, edition: Edition) -> Result<()> {
+    if is_valid_identifier(name, edition) {
+        Ok(())
+    } else {
+        Err(Error::InvalidIdentifier(
+            name.to_string(),
+            context.to_string(),
+        ))
+    }
+}
+
+fn check_unique<'a>(
+    kind: &str,
+    names: impl IntoIterator<Item = &'a str>,
+    context: &str,
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    for name in names {
+        if !seen.insert(name) {
+            return Err(Error::Duplicate(
+                kind.to_string(),
+                name.to_string(),
+                context.to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn component_generics(component_signature: &ComponentSignature) -> Vec<Generic> {
+    match component_signature {
+        ComponentSignature::Signature(s) => s.get_generics().get_generics(),
+        ComponentSignature::Generic(g) => vec![g.clone()],
+    }
+}
+
+fn check_generics<'a>(
+    generics: impl IntoIterator<Item = &'a Generic>,
+    owner_name: &str,
+    context: &str,
+    edition: Edition,
+) -> Result<()> {
+    for generic in generics {
+        check_identifier(&generic.alias, context, edition)?;
+        if generic.alias == owner_name {
+            return Err(Error::ConflictingGenerics(
+                generic.alias.clone(),
+                context.to_string(),
+                "alias shadows the item name".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Validate for StructBuilder {
+    fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        let context = format!("struct {}", self.name);
+        if let Some(signature) = &self.unsupported_signature {
+            return Err(Error::UnsupportedSignature(signature.format(), context));
+        }
+        check_identifier(&self.name, &context, edition)?;
+        let names = self
+            .fields
+            .iter()
+            .map(|f| self.escape_name(&f.named_component_signature.name, edition))
+            .collect::<Vec<String>>();
+        for (field, name) in self.fields.iter().zip(&names) {
+            let ncs = &field.named_component_signature;
+            check_identifier(name, &context, edition)?;
+            check_generics(
+                &component_generics(&ncs.component_signature),
+                &self.name,
+                &context,
+                edition,
+            )?;
+        }
+        check_unique("field", names.iter().map(String::as_str), &context)
+    }
+}
+
+impl Validate for ContainerStructBuilder {
+    fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        let context = format!("struct {}", self.name);
+        if let Some(signature) = &self.unsupported_signature {
+            return Err(Error::UnsupportedSignature(signature.format(), context));
+        }
+        check_identifier(&self.name, &context, edition)
+    }
+}
+
+impl Validate for EnumBuilder {
+    fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        let context = format!("enum {}", self.name);
+        check_identifier(&self.name, &context, edition)?;
+        let names = self
+            .members
+            .iter()
+            .map(|m| self.escape_name(&m.name, edition))
+            .collect::<Vec<String>>();
+        for (member, name) in self.members.iter().zip(&names) {
+            check_identifier(name, &context, edition)?;
+            match &member.member_type {
+                MemberType::Empty(_) => {}
+                MemberType::Type(s) => check_generics(
+                    &s.get_generics().get_generics(),
+                    &self.name,
+                    &context,
+                    edition,
+                )?,
+                MemberType::Pattern(patterns) => {
+                    let member_context = format!("{context} variant {}", member.name);
+                    let field_names = patterns
+                        .iter()
+                        .map(|ncs| self.escape_name(&ncs.name, edition))
+                        .collect::<Vec<String>>();
+                    for (ncs, field_name) in patterns.iter().zip(&field_names) {
+                        check_identifier(field_name, &member_context, edition)?;
+                        check_generics(
+                            &component_generics(&ncs.component_signature),
+                            &self.name,
+                            &context,
+                            edition,
+                        )?;
+                    }
+                    check_unique(
+                        "field",
+                        field_names.iter().map(String::as_str),
+                        &member_context,
+                    )?;
+                }
+            }
+        }
+        check_unique("variant", names.iter().map(String::as_str), &context)
+    }
+}
+
+impl Validate for FunctionBuilder {
+    fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        let context = format!("fn {}", self.name);
+        check_identifier(&self.escape_name(&self.name, edition), &context, edition)?;
+        let names = self
+            .args
+            .iter()
+            .map(|a| self.escape_name(&a.named_sign.name, edition))
+            .collect::<Vec<String>>();
+        for (arg, name) in self.args.iter().zip(&names) {
+            check_identifier(name, &context, edition)?;
+            check_generics(
+                &component_generics(&arg.named_sign.component_signature),
+                &self.name,
+                &context,
+                edition,
+            )?;
+        }
+        check_unique("argument", names.iter().map(String::as_str), &context)
+    }
+}
+
+impl Validate for ConstantBuilder {
+    fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        check_identifier(
+            &self.name,
+            &format!("{} {}", self.constant_type, self.name),
+            edition,
+        )
+    }
+}
+
+impl MethodBuilder {
+    /// Generics declared by the container are pruned from the method signature,
+    /// so an argument using the same alias with extra bounds would silently lose them
+    fn validate_in(
+        &self,
+        container_generics: &[Generic],
+        container: &str,
+        edition: Edition,
+    ) -> Result<()> {
+        let context = format!("{container} method {}", self.name);
+        check_identifier(&self.escape_name(&self.name, edition), &context, edition)?;
+        let names = self
+            .args
+            .iter()
+            .map(|a| self.escape_name(&a.named_sign.name, edition))
+            .collect::<Vec<String>>();
+        for (arg, name) in self.args.iter().zip(&names) {
+            check_identifier(name, &context, edition)?;
+            for generic in component_generics(&arg.named_sign.component_signature) {
+                check_identifier(&generic.alias, &context, edition)?;
+                if let Some(declared) = container_generics.iter().find(|g| g.alias == generic.alias)
+                {
+                    if generic
+                        .bounds
+                        .bounds
+                        .iter()
+                        .any(|b| !declared.bounds.bounds.contains(b))
+                    {
+                        return Err(Error::ConflictingGenerics(
+                            generic.alias,
+                            context,
+                            format!(
+                                "bounds {} are not declared on the container",
+                                generic.bounds.format()
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        check_unique("argument", names.iter().map(String::as_str), &context)
+    }
+}
+
+impl Validate for TraitBuilder {
+    fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        let rust_type = self.trait_type.rust_type().ok_or_else(|| {
+            Error::UnsupportedSignature(self.trait_type.format(), "trait".to_string())
+        })?;
+        let context = format!("trait {}", rust_type.name);
+        check_identifier(&rust_type.name, &context, edition)?;
+        let generics = self.trait_type.get_generics().get_generics();
+        check_generics(&generics, &rust_type.name, &context, edition)?;
+        for method in &self.methods {
+            method.validate_in(&generics, &context, edition)?;
+        }
+        check_unique(
+            "method",
+            self.methods
+                .iter()
+                .map(|m| m.escape_name(&m.name, edition))
+                .collect::<Vec<String>>()
+                .iter()
+                .map(String::as_str),
+            &context,
+        )?;
+        check_unique(
+            "associated type",
+            self.types
+                .iter()
+                .map(|t| t.split(':').next().unwrap_or_default().trim()),
+            &context,
+        )
+    }
+}
+
+impl Validate for ImplBuilder {
+    fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        let context = format!("impl {}", self.implementor.format());
+        let generics = match (&self.implementor, &self.implementing) {
+            (Signature::SingleGeneric(_), None) => {
+                return Err(Error::UnsupportedSignature(
+                    self.implementor.format(),
+                    "inherent impl".to_string(),
+                ))
+            }
+            (implementor, Some(implementing)) => implementor
+                .get_generics()
+                .union(&implementing.get_generics())
+                .get_generics(),
+            (implementor, None) => implementor.get_generics().get_generics(),
+        };
+        for generic in &generics {
+            check_identifier(&generic.alias, &context, edition)?;
+        }
+        for method in &self.methods {
+            method.validate_in(&generics, &context, edition)?;
+        }
+        for constant in &self.consts {
+            constant.validate_for_edition(edition)?;
+        }
+        let type_def_names = self
+            .type_defs
+            .iter()
+            .map(|td| match td {
+                TypeDef::TraitImpl(ncs) => ncs.name.as_str(),
+                TypeDef::Const(tdd) => tdd.named_component_signature.name.as_str(),
+            })
+            .collect::<Vec<&str>>();
+        for name in &type_def_names {
+            check_identifier(name, &context, edition)?;
+        }
+        check_unique(
+            "method",
+            self.methods
+                .iter()
+                .map(|m| m.escape_name(&m.name, edition))
+                .collect::<Vec<String>>()
+                .iter()
+                .map(String::as_str),
+            &context,
+        )?;
+        check_unique(
+            "const",
+            self.consts.iter().map(|c| c.name.as_str()),
+            &context,
+        )?;
+        check_unique("associated type", type_def_names, &context)
+    }
+}
+
+impl FileBuilder {
+    /// Validates all items in the file and that no two items share a name in the same namespace
+    pub fn validate(&self) -> Result<()> {
+        self.validate_for_edition(self.edition)
+    }
+
+    pub(crate) fn validate_for_edition(&self, edition: Edition) -> Result<()> {
+        let context = format!("file {}", self.name);
+        let mut types = vec![];
+        let mut values = vec![];
+        for part in &self.structs {
+            part.value.validate_for_edition(edition)?;
+            types.push(part.value.name.as_str());
+        }
+        for part in &self.container_structs {
+            part.value.validate_for_edition(edition)?;
+            types.push(part.value.name.as_str());
+        }
+        for part in &self.enums {
+            part.value.validate_for_edition(edition)?;
+            types.push(part.value.name.as_str());
+        }
+        for part in &self.traits {
+            part.value.validate_for_edition(edition)?;
+            if let Some(rt) = part.value.trait_type.rust_type() {
+                types.push(rt.name.as_str());
+            }
+        }
+        for part in &self.type_defs {
+            let name = match &part.value {
+                TypeDef::TraitImpl(ncs) => &ncs.name,
+                TypeDef::Const(tdd) => &tdd.named_component_signature.name,
+            };
+            check_identifier(name, &context, edition)?;
+            types.push(name.as_str());
+        }
+        for part in &self.functions {
+            part.value.validate_for_edition(edition)?;
+            values.push(part.value.escape_name(&part.value.name, edition));
+        }
+        for part in &self.constants {
+            part.value.validate_for_edition(edition)?;
+            values.push(part.value.name.clone());
+        }
+        for part in &self.implementations {
+            part.value.validate_for_edition(edition)?;
+        }
+        check_unique("type", types, &context)?;
+        check_unique("value", values.iter().map(String::as_str), &context)
+    }
+
+    pub fn try_format_file(&self) -> Result<String> {
+        self.validate()?;
+        Ok(self.format_file())
+    }
+}
+
+impl ModuleBuilder {
+    /// Validates every file in the tree, module names have to be valid identifiers
+    pub fn validate(&self) -> Result<()> {
+        self.validate_inheriting(None)
+    }
+
+    /// Mirrors `write_to_disk`, an edition set on a parent applies to submodules that have none
+    fn validate_inheriting(&self, inherited: Option<Edition>) -> Result<()> {
+        let forced = self.edition.or(inherited);
+        let edition = forced.unwrap_or(self.mod_file.edition);
+        self.mod_file.validate_for_edition(edition)?;
+        for file in &self.module_files {
+            file.builder
+                .validate_for_edition(forced.unwrap_or(file.builder.edition))?;
+        }
+        for submodule in &self.submodules {
+            submodule.builder.validate_inheriting(forced)?;
+        }
+        self.check_own_module_names(edition)
+    }
+
+    /// Module names end up in paths, anything that isn't an identifier could point outside
+    /// of the output root
+    pub(crate) fn check_module_names(&self) -> Result<()> {
+        check_identifier(&self.mod_file.name, "module root", self.edition())?;
+        self.check_module_names_inheriting(None)
+    }
+
+    fn check_module_names_inheriting(&self, inherited: Option<Edition>) -> Result<()> {
+        let forced = self.edition.or(inherited);
+        self.check_own_module_names(forced.unwrap_or(self.mod_file.edition))?;
+        for submodule in &self.submodules {
+            submodule.builder.check_module_names_inheriting(forced)?;
+        }
+        Ok(())
+    }
+
+    /// The modules declared in the root file, every other file of the tree is below one of them
+    pub(crate) fn child_module_names(&self, edition: Edition) -> Vec<String> {
+        self.module_files
+            .iter()
+            .map(|file| &file.builder.name)
+            .chain(self.submodules.iter().map(|sub| &sub.builder.mod_file.name))
+            .map(|name| self.escape_name(name, edition))
+            .collect()
+    }
+
+    fn check_own_module_names(&self, edition: Edition) -> Result<()> {
+        let context = format!("module {}", self.mod_file.name);
+        let names = self.child_module_names(edition);
+        for name in &names {
+            check_identifier(name, &context, edition)?;
+        }
+        check_unique("module", names.iter().map(String::as_str), &context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::structures::gen_enum::NamedComponentSignature;
+    use crate::structures::generics::{Bound, Bounds, Generic};
+    use crate::structures::visibility::Visibility;
+    use crate::structures::{Ownership, RustType, Signature};
+    use crate::{
+        ContainerStructBuilder, EnumBuilder, FileBuilder, ImplBuilder, MethodBuilder,
+        ModuleBuilder, StructBuilder, TraitBuilder, Validate,
+    };
+
+    #[test]
+    fn invalid_identifiers() {
+        let sb = StructBuilder::new("My Struct");
+        assert!(matches!(sb.validate(), Err(Error::InvalidIdentifier(..))));
+        let sb = StructBuilder::new("MyStruct")
+            .add_field_in_scope_simple_type(Visibility::Public, "type", "String")
+            .disable_keyword_escaping();
+        assert!(matches!(sb.try_format(), Err(Error::InvalidIdentifier(..))));
+        let sb = StructBuilder::new("MyStruct").add_field_in_scope_simple_type(
+            Visibility::Public,
+            "r#type",
+            "String",
+        );
+        assert!(sb.try_format().is_ok());
+    }
+
+    #[test]
+    fn duplicates() {
+        let sb = StructBuilder::new("MyStruct")
+            .add_field_in_scope_simple_type(Visibility::Public, "a", "String")
+            .add_field_in_scope_simple_type(Visibility::Public, "a", "i32");
+        assert!(matches!(sb.validate(), Err(Error::Duplicate(..))));
+        let eb = EnumBuilder::new("MyEnum")
+            .add_tag_member("A")
+            .add_tag_member("A");
+        assert!(matches!(eb.validate(), Err(Error::Duplicate(..))));
+        let fb = FileBuilder::new("my_file")
+            .add_struct(StructBuilder::new("Same"))
+            .add_enum(EnumBuilder::new("Same"));
+        assert!(matches!(fb.validate(), Err(Error::Duplicate(..))));
+        let mb = ModuleBuilder::new(FileBuilder::new("lib"))
+            .add_submodule(
+                Visibility::Public,
+                ModuleBuilder::new(FileBuilder::new("a")),
+            )
+            .add_module_file(
+                Visibility::Public,
+                FileBuilder::new("a"),
+                crate::Annotations::empty(),
+            );
+        assert!(matches!(mb.validate(), Err(Error::Duplicate(..))));
+        assert!(matches!(mb.check_module_names(), Err(Error::Duplicate(..))));
+    }
+
+    #[test]
+    fn path_traversal() {
+        let mb = ModuleBuilder::new(FileBuilder::new("lib")).add_submodule(
+            Visibility::Public,
+            ModuleBuilder::new(FileBuilder::new("a")).add_module_file(
+                Visibility::Public,
+                FileBuilder::new("../../x"),
+                crate::Annotations::empty(),
+            ),
+        );
+        assert!(matches!(
+            mb.check_module_names(),
+            Err(Error::InvalidIdentifier(..))
+        ));
+        let mb = ModuleBuilder::new(FileBuilder::new("/etc/lib"));
+        assert!(matches!(
+            mb.check_module_names(),
+            Err(Error::InvalidIdentifier(..))
+        ));
+    }
+
+    #[test]
+    fn single_generic_signatures_dont_panic() {
+        let generic = Signature::simple_generic(Generic::unbounded("T"));
+        let tb = TraitBuilder::new(generic.clone());
+        assert!(matches!(
+            tb.try_format(),
+            Err(Error::UnsupportedSignature(..))
+        ));
+        let csb = ContainerStructBuilder::new_from_signature(&generic);
+        assert!(matches!(
+            csb.validate(),
+            Err(Error::UnsupportedSignature(..))
+        ));
+        assert!(StructBuilder::try_new_from_signature(&generic).is_err());
+        let ib = ImplBuilder::new(generic);
+        assert!(matches!(
+            ib.validate(),
+            Err(Error::UnsupportedSignature(..))
+        ));
+    }
+
+    #[test]
+    fn conflicting_generics() {
+        let my_struct = Signature::generic_container(
+            RustType::in_scope("MyStruct"),
+            Generic::unbounded("T").into_generics(),
+        );
+        let ib = ImplBuilder::new(my_struct).add_method(
+            MethodBuilder::new("do_thing")
+                .set_self_ownership(Ownership::Ref)
+                .add_argument_bounded_generic(
+                    Ownership::Owned,
+                    "arg",
+                    "T",
+                    Bounds::single(Bound::required(RustType::in_scope("Debug"))),
+                ),
+        );
+        assert!(matches!(ib.validate(), Err(Error::ConflictingGenerics(..))));
+        let sb = StructBuilder::new("T").add_field(
+            Visibility::Public,
+            NamedComponentSignature::new_unbounded_generic("inner", "T"),
+        );
+        assert!(matches!(sb.validate(), Err(Error::ConflictingGenerics(..))));
+    }
+}
@@ -0,0 +1,1120 @@
+use crate::errors::{Error, Result};
+use crate::model::{Model, ModelField, ModelKind, ModelType, TypeRef, RESERVED_NAMES};
+use crate::util::casing::RustCase;
+use crate::{FileBuilder, ModuleBuilder};
+use roxmltree::{Document, Node};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+const XS: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// Generates serde types from a subset of XML Schema, for a serde XML backend following
+/// quick-xml's conventions: attributes are fields renamed with a leading `@`, the text of an
+/// element with simple content is `$text` and a `choice` is an enum in a `$value` field.
+///
+/// Complex types and elements declaring their type inline become structs, enumerations become
+/// enums and other simple types aliases. Every target namespace becomes a module, ex:
+/// `http://example.com/orders/v1` is `example_com::orders::v1`, see `map_namespace`.
+/// Groups and attribute groups are not supported, wildcards are skipped
+pub struct Xsd {
+    sources: Vec<Source>,
+    namespaces: BTreeMap<String, Vec<String>>,
+    mapped: BTreeMap<String, String>,
+}
+
+enum Source {
+    File(PathBuf),
+    Text(String, String),
+}
+
+/// A namespace and a local name
+type QName = (String, String);
+
+impl Default for Xsd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Xsd {
+    pub fn new() -> Self {
+        Self {
+            sources: vec![],
+            namespaces: BTreeMap::new(),
+            mapped: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Self {
+        Self::new().add_file(path)
+    }
+
+    pub fn parse(source: impl Into<String>) -> Self {
+        Self::new().add_source("input.xsd", source)
+    }
+
+    /// Includes and imports with a `schemaLocation` are read relative to the file
+    pub fn add_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Includes and imports of the source are looked up in the other sources by name
+    pub fn add_source(mut self, name: impl Into<String>, source: impl Into<String>) -> Self {
+        self.sources.push(Source::Text(name.into(), source.into()));
+        self
+    }
+
+    /// Puts the types of a namespace in another module than the one derived from its URI,
+    /// ex: `orders::v1`, an empty path is the root module
+    pub fn map_namespace(mut self, uri: impl Into<String>, module: &str) -> Self {
+        let path = module
+            .split("::")
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect();
+        self.namespaces.insert(uri.into(), path);
+        self
+    }
+
+    /// Uses a Rust type for a built-in type, ex: `decimal` to `rust_decimal::Decimal`.
+    /// Built-ins without an exact Rust counterpart like `decimal` and `dateTime` are `String`s
+    pub fn map_type(mut self, xsd_name: impl Into<String>, rust_type: impl Into<String>) -> Self {
+        self.mapped.insert(xsd_name.into(), rust_type.into());
+        self
+    }
+
+    /// `name` is the root module, types outside of a namespace go in its file
+    pub fn into_module(self, name: impl Into<String>) -> Result<ModuleBuilder> {
+        let name = name.into();
+        let sources = self.load()?;
+        let docs = sources
+            .iter()
+            .map(|(location, text)| parse(location, text))
+            .collect::<Result<Vec<_>>>()?;
+        let mut generator = Generator {
+            xsd: &self,
+            types: BTreeMap::new(),
+            complex: BTreeMap::new(),
+            elements: BTreeMap::new(),
+            attributes: BTreeMap::new(),
+            element_types: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            modules: BTreeMap::new(),
+            taken: BTreeMap::new(),
+        };
+        for doc in &docs {
+            generator.declare(doc.root_element());
+        }
+        generator.declare_elements();
+        for doc in &docs {
+            generator.generate(doc.root_element())?;
+        }
+        box_cycles(&mut generator.modules);
+        let mut files = generator
+            .modules
+            .into_iter()
+            .map(|(path, types)| {
+                let file = path.last().unwrap_or(&name).clone();
+                let model = Model {
+                    types,
+                    rename_all: false,
+                };
+                (path, model.into_file_builder(file))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let root = files
+            .remove(&vec![])
+            .unwrap_or_else(|| FileBuilder::new(&name));
+        Ok(ModuleBuilder::nested(root, files))
+    }
+
+    fn load(&self) -> Result<Vec<(String, String)>> {
+        let mut loaded = vec![];
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![];
+        for source in &self.sources {
+            match source {
+                Source::File(path) => pending.push(path.clone()),
+                Source::Text(name, text) => {
+                    for location in schema_locations(&parse(name, text)?) {
+                        if let Some(path) = self.find_schema(None, location)? {
+                            pending.push(path);
+                        }
+                    }
+                    loaded.push((name.clone(), text.clone()));
+                }
+            }
+        }
+        while let Some(path) = pending.pop() {
+            let path = path.canonicalize()?;
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            let name = path.display().to_string();
+            let text = std::fs::read_to_string(&path)?;
+            for location in schema_locations(&parse(&name, &text)?) {
+                if let Some(found) = self.find_schema(path.parent(), location)? {
+                    pending.push(found);
+                }
+            }
+            loaded.push((name, text));
+        }
+        Ok(loaded)
+    }
+
+    fn find_schema(&self, dir: Option<&Path>, location: &str) -> Result<Option<PathBuf>> {
+        let added = self
+            .sources
+            .iter()
+            .any(|source| matches!(source, Source::Text(name, _) if name == location));
+        if added {
+            return Ok(None);
+        }
+        dir.map(|dir| dir.join(location))
+            .filter(|path| path.is_file())
+            .map(Some)
+            .ok_or_else(|| Error::Schema(location.to_string(), "schema not found".to_string()))
+    }
+
+    fn module_path(&self, namespace: &str) -> Vec<String> {
+        match self.namespaces.get(namespace) {
+            Some(path) => path.clone(),
+            None => namespace_path(namespace),
+        }
+    }
+
+    fn builtin(&self, name: &str) -> Option<TypeRef> {
+        if let Some(rust_type) = self.mapped.get(name) {
+            return Some(TypeRef::builtin(rust_type));
+        }
+        Some(TypeRef::builtin(match name {
+            "boolean" => "bool",
+            "float" => "f32",
+            "double" => "f64",
+            "byte" => "i8",
+            "short" => "i16",
+            "int" => "i32",
+            "long" | "integer" | "negativeInteger" | "nonPositiveInteger" => "i64",
+            "unsignedByte" => "u8",
+            "unsignedShort" => "u16",
+            "unsignedInt" => "u32",
+            "unsignedLong" | "positiveInteger" | "nonNegativeInteger" => "u64",
+            "NMTOKENS" | "IDREFS" | "ENTITIES" => "Vec<String>",
+            "string" | "normalizedString" | "token" | "language" | "Name" | "NCName"
+            | "NMTOKEN" | "ID" | "IDREF" | "ENTITY" | "anyURI" | "QName" | "NOTATION"
+            | "decimal" | "date" | "dateTime" | "time" | "duration" | "gYear" | "gYearMonth"
+            | "gMonth" | "gMonthDay" | "gDay" | "base64Binary" | "hexBinary" | "anyType"
+            | "anySimpleType" => "String",
+            _ => return None,
+        }))
+    }
+}
+
+fn parse<'a>(location: &str, text: &'a str) -> Result<Document<'a>> {
+    let doc =
+        Document::parse(text).map_err(|e| Error::Schema(location.to_string(), e.to_string()))?;
+    let root = doc.root_element();
+    if root.tag_name().namespace() != Some(XS) || root.tag_name().name() != "schema" {
+        return Err(Error::Schema(
+            location.to_string(),
+            "not an XML Schema".to_string(),
+        ));
+    }
+    Ok(doc)
+}
+
+/// Files the schema includes or imports
+fn schema_locations<'a>(doc: &'a Document) -> impl Iterator<Item = &'a str> {
+    xs_children(doc.root_element())
+        .filter(|(_, kind)| matches!(*kind, "include" | "import"))
+        .filter_map(|(node, _)| node.attribute("schemaLocation"))
+}
+
+/// The module of a namespace URI, ex: `urn:acme:billing` is `acme::billing`
+fn namespace_path(uri: &str) -> Vec<String> {
+    let path = match uri.split_once("://") {
+        Some((_, path)) => path.trim_start_matches("www."),
+        None => uri.strip_prefix("urn:").unwrap_or(uri),
+    };
+    path.split(['/', ':'])
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| RustCase::convert_lossy(segment, RustCase::Snake))
+        .collect()
+}
+
+fn xs_children<'a>(node: Node<'a, 'a>) -> impl Iterator<Item = (Node<'a, 'a>, &'a str)> {
+    node.children()
+        .filter(|child| child.is_element() && child.tag_name().namespace() == Some(XS))
+        .map(|child| (child, child.tag_name().name()))
+}
+
+fn documentation(node: Node) -> Option<String> {
+    let text = xs_children(node)
+        .filter(|(_, kind)| *kind == "annotation")
+        .flat_map(|(annotation, _)| xs_children(annotation))
+        .filter(|(_, kind)| *kind == "documentation")
+        .flat_map(|(doc, _)| doc.descendants().filter(|d| d.is_text()))
+        .filter_map(|text| text.text())
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(text).filter(|text| !text.is_empty())
+}
+
+fn unsupported(owner: &str, kind: &str) -> Error {
+    Error::Schema(owner.to_string(), format!("`{kind}` is not supported"))
+}
+
+/// A field replaces an earlier one of the same name, ex: a restriction restating an attribute
+fn add_field(fields: &mut Vec<ModelField>, field: ModelField) {
+    fields.retain(|f| f.name != field.name);
+    fields.push(field);
+}
+
+/// A type by its module and name
+type TypeKey = (Vec<String>, String);
+
+/// Boxes the references leading back to the type holding them, ex: an `Item` holding a `Wrap`
+/// holding an `Item`. A `Vec` has a size of its own, so only plain and optional ones count
+fn box_cycles(modules: &mut BTreeMap<Vec<String>, Vec<ModelType>>) {
+    let mut edges = BTreeMap::new();
+    for (module, types) in modules.iter() {
+        for model_type in types {
+            let mut refs = vec![];
+            let mut collect = |ty: &TypeRef| {
+                if let Some(path) = direct_name(ty) {
+                    refs.push(type_key(module, path));
+                }
+            };
+            match &model_type.kind {
+                ModelKind::Struct(fields) => fields.iter().for_each(|f| collect(&f.ty)),
+                ModelKind::Union(variants) | ModelKind::Tagged(_, variants) => {
+                    variants.iter().for_each(|(_, ty)| collect(ty))
+                }
+                ModelKind::Alias(ty) => collect(ty),
+                ModelKind::Enum(_) => {}
+            }
+            edges.insert((module.clone(), model_type.name.clone()), refs);
+        }
+    }
+    let reaches = |from: TypeKey, to: &TypeKey| {
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![from];
+        while let Some(current) = pending.pop() {
+            if &current == to {
+                return true;
+            }
+            if let Some(refs) = edges
+                .get(&current)
+                .filter(|_| visited.insert(current.clone()))
+            {
+                pending.extend(refs.iter().cloned());
+            }
+        }
+        false
+    };
+    for (module, types) in modules.iter_mut() {
+        for model_type in types {
+            let holder = (module.clone(), model_type.name.clone());
+            let boxed = |ty: &mut TypeRef| {
+                let cyclic =
+                    direct_name(ty).is_some_and(|path| reaches(type_key(module, path), &holder));
+                if cyclic {
+                    *ty = match std::mem::replace(ty, TypeRef::builtin("")) {
+                        TypeRef::Option(inner) => TypeRef::option(TypeRef::Boxed(inner)),
+                        other => TypeRef::boxed(other),
+                    };
+                }
+            };
+            match &mut model_type.kind {
+                ModelKind::Struct(fields) => fields.iter_mut().for_each(|f| boxed(&mut f.ty)),
+                ModelKind::Union(variants) | ModelKind::Tagged(_, variants) => {
+                    variants.iter_mut().for_each(|(_, ty)| boxed(ty))
+                }
+                ModelKind::Alias(_) | ModelKind::Enum(_) => {}
+            }
+        }
+    }
+}
+
+/// The named type held without indirection, ex: `T` in `Option<T>` but not in `Vec<T>`
+fn direct_name(ty: &TypeRef) -> Option<&str> {
+    match ty {
+        TypeRef::Named(path) => Some(path),
+        TypeRef::Option(inner) => direct_name(inner),
+        _ => None,
+    }
+}
+
+/// Resolves a path as seen from a module, ex: `super::acme::Status`
+fn type_key(module: &[String], path: &str) -> TypeKey {
+    let mut segments = path.split("::").collect::<Vec<_>>();
+    let name = segments.pop().unwrap_or_default().to_string();
+    let mut resolved = module.to_vec();
+    for segment in segments {
+        match segment {
+            "super" => {
+                resolved.pop();
+            }
+            segment => resolved.push(segment.to_string()),
+        }
+    }
+    (resolved, name)
+}
+
+#[derive(Debug, Clone)]
+struct Symbol {
+    module: Vec<String>,
+    rust_name: String,
+}
+
+/// How often a particle appears, nested particles multiply
+#[derive(Debug, Clone, Copy, Default)]
+struct Occurs {
+    optional: bool,
+    many: bool,
+}
+
+impl Occurs {
+    fn within(self, node: Node) -> Result<Self> {
+        let bound = |attribute: &str| -> Result<Option<u64>> {
+            match node.attribute(attribute) {
+                None | Some("unbounded") => Ok(None),
+                Some(value) => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| Error::Schema(value.to_string(), format!("invalid {attribute}"))),
+            }
+        };
+        let min = bound("minOccurs")?.unwrap_or(1);
+        let many = match bound("maxOccurs")? {
+            Some(max) => max > 1,
+            None => node.attribute("maxOccurs") == Some("unbounded"),
+        };
+        Ok(Self {
+            optional: self.optional || min == 0,
+            many: self.many || many,
+        })
+    }
+
+    fn field(self, name: String, ty: TypeRef, doc: Option<String>) -> ModelField {
+        ModelField {
+            name,
+            ty: match self.many {
+                true => TypeRef::vec(ty),
+                false => ty,
+            },
+            optional: self.optional && !self.many,
+            default: self.many,
+            doc,
+        }
+    }
+}
+
+struct Generator<'a> {
+    xsd: &'a Xsd,
+    /// Named complex and simple types
+    types: BTreeMap<QName, Symbol>,
+    complex: BTreeMap<QName, Node<'a, 'a>>,
+    elements: BTreeMap<QName, Node<'a, 'a>>,
+    attributes: BTreeMap<QName, Node<'a, 'a>>,
+    /// Global elements with a type of their own, declared inline or an alias of a named type
+    element_types: BTreeMap<QName, Symbol>,
+    /// Fields of the named complex types, for the types extending them
+    fields: BTreeMap<QName, Vec<ModelField>>,
+    modules: BTreeMap<Vec<String>, Vec<ModelType>>,
+    taken: BTreeMap<Vec<String>, BTreeSet<String>>,
+}
+
+impl<'a> Generator<'a> {
+    fn declare(&mut self, schema: Node<'a, 'a>) {
+        let namespace = schema.attribute("targetNamespace").unwrap_or_default();
+        let module = self.xsd.module_path(namespace);
+        for (node, kind) in xs_children(schema) {
+            let Some(name) = node.attribute("name") else {
+                continue;
+            };
+            let qname = (namespace.to_string(), name.to_string());
+            match kind {
+                "complexType" | "simpleType" => {
+                    if kind == "complexType" {
+                        self.complex.insert(qname.clone(), node);
+                    }
+                    let rust_name =
+                        self.reserve(&module, &RustCase::convert_lossy(name, RustCase::Pascal));
+                    self.types.insert(
+                        qname,
+                        Symbol {
+                            module: module.clone(),
+                            rust_name,
+                        },
+                    );
+                }
+                "element" => {
+                    self.elements.insert(qname, node);
+                }
+                "attribute" => {
+                    self.attributes.insert(qname, node);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// After the named types, which keep their names when an element is named the same
+    fn declare_elements(&mut self) {
+        for (qname, node) in self.elements.clone() {
+            let module = self.xsd.module_path(&qname.0);
+            let rust_name = RustCase::convert_lossy(&qname.1, RustCase::Pascal);
+            let inline = xs_children(node).any(|(_, k)| matches!(k, "complexType" | "simpleType"));
+            let free = !self
+                .taken
+                .get(&module)
+                .is_some_and(|t| t.contains(&rust_name));
+            if inline || (free && node.attribute("type").is_some()) {
+                let rust_name = self.reserve(&module, &rust_name);
+                self.element_types
+                    .insert(qname, Symbol { module, rust_name });
+            }
+        }
+    }
+
+    fn generate(&mut self, schema: Node<'a, 'a>) -> Result<()> {
+        let namespace = schema.attribute("targetNamespace").unwrap_or_default();
+        for (node, kind) in xs_children(schema) {
+            let Some(name) = node.attribute("name") else {
+                continue;
+            };
+            let qname = (namespace.to_string(), name.to_string());
+            match kind {
+                "complexType" => {
+                    self.complex_fields(&qname)?;
+                }
+                "simpleType" => {
+                    let symbol = self.types[&qname].clone();
+                    self.define(&symbol, documentation(node), |g| {
+                        g.simple_kind(node, &symbol.module)
+                    })?;
+                }
+                "element" => self.global_element(node, &qname)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn global_element(&mut self, node: Node<'a, 'a>, qname: &QName) -> Result<()> {
+        let Some(symbol) = self.element_types.get(qname).cloned() else {
+            return Ok(());
+        };
+        let module = &symbol.module;
+        for (child, kind) in xs_children(node) {
+            if matches!(kind, "complexType" | "simpleType") {
+                let doc = documentation(node).or_else(|| documentation(child));
+                return self.define(&symbol, doc, |g| g.inline_kind(child, &symbol));
+            }
+        }
+        match node.attribute("type") {
+            Some(ty) => self.define(&symbol, documentation(node), |g| {
+                Ok(ModelKind::Alias(g.resolve(node, ty, module)?))
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Generates the struct of a named complex type the first time it's asked for
+    fn complex_fields(&mut self, qname: &QName) -> Result<Vec<ModelField>> {
+        if let Some(fields) = self.fields.get(qname) {
+            return Ok(fields.clone());
+        }
+        let node = self.complex[qname];
+        let symbol = self.types[qname].clone();
+        // a type extending itself ends up with the fields it declares
+        self.fields.insert(qname.clone(), vec![]);
+        let mut fields = vec![];
+        self.define(&symbol, documentation(node), |g| {
+            fields = g.content(node, &symbol.module, &symbol.rust_name)?;
+            Ok(ModelKind::Struct(fields.clone()))
+        })?;
+        self.fields.insert(qname.clone(), fields.clone());
+        Ok(fields)
+    }
+
+    /// The fields of a complex type or of a derivation, `owner` is the struct they go in
+    fn content(
+        &mut self,
+        node: Node<'a, 'a>,
+        module: &[String],
+        owner: &str,
+    ) -> Result<Vec<ModelField>> {
+        let mut fields = vec![];
+        for (child, kind) in xs_children(node) {
+            match kind {
+                "sequence" | "all" | "choice" => {
+                    self.particle(child, module, owner, Occurs::default(), &mut fields)?
+                }
+                "attribute" => self.attribute(child, module, owner, &mut fields)?,
+                "simpleContent" | "complexContent" => {
+                    for (derivation, kind) in xs_children(child) {
+                        if matches!(kind, "extension" | "restriction") {
+                            self.derivation(derivation, module, owner, &mut fields)?;
+                        }
+                    }
+                }
+                "group" | "attributeGroup" => return Err(unsupported(owner, kind)),
+                _ => {}
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Extensions inherit the fields of their base, a simple base is the `$text` of the element.
+    /// A restriction of complex content restates the fields it keeps
+    fn derivation(
+        &mut self,
+        node: Node<'a, 'a>,
+        module: &[String],
+        owner: &str,
+        fields: &mut Vec<ModelField>,
+    ) -> Result<()> {
+        let base = node.attribute("base").ok_or_else(|| {
+            Error::Schema(owner.to_string(), "derivation without a base".to_string())
+        })?;
+        let qname = self.qname(node, base)?;
+        let simple = node
+            .parent()
+            .is_some_and(|p| p.tag_name().name() == "simpleContent");
+        if self.complex.contains_key(&qname) {
+            if self.types[&qname].module != module {
+                return Err(Error::Schema(
+                    owner.to_string(),
+                    "deriving from a type of another namespace is not supported".to_string(),
+                ));
+            }
+            if simple || node.tag_name().name() == "extension" {
+                fields.extend(self.complex_fields(&qname)?);
+            }
+        } else if simple {
+            let ty = self.resolve(node, base, module)?;
+            add_field(
+                fields,
+                Occurs::default().field("$text".to_string(), ty, None),
+            );
+        }
+        for field in self.content(node, module, owner)? {
+            add_field(fields, field);
+        }
+        Ok(())
+    }
+
+    fn particle(
+        &mut self,
+        node: Node<'a, 'a>,
+        module: &[String],
+        owner: &str,
+        occurs: Occurs,
+        fields: &mut Vec<ModelField>,
+    ) -> Result<()> {
+        let occurs = occurs.within(node)?;
+        if node.tag_name().name() == "choice" {
+            return self.choice(node, module, owner, occurs, fields);
+        }
+        for (child, kind) in xs_children(node) {
+            match kind {
+                "element" => {
+                    let field = self.element_field(child, module, owner, occurs)?;
+                    add_field(fields, field);
+                }
+                "sequence" | "all" | "choice" => {
+                    self.particle(child, module, owner, occurs, fields)?
+                }
+                "group" => return Err(unsupported(owner, kind)),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// An enum of the elements to choose from in the `$value` field, named after the struct
+    fn choice(
+        &mut self,
+        node: Node<'a, 'a>,
+        module: &[String],
+        owner: &str,
+        occurs: Occurs,
+        fields: &mut Vec<ModelField>,
+    ) -> Result<()> {
+        if fields.iter().any(|f| f.name == "$value") {
+            return Err(Error::Schema(
+                owner.to_string(),
+                "only one choice per type is supported".to_string(),
+            ));
+        }
+        let symbol = Symbol {
+            module: module.to_vec(),
+            rust_name: self.reserve(module, &format!("{owner}Choice")),
+        };
+        self.define(&symbol, documentation(node), |g| {
+            let mut variants = vec![];
+            for (child, kind) in xs_children(node) {
+                match kind {
+                    "element" => {
+                        let field = g.element_field(child, module, owner, Occurs::default())?;
+                        let ty = match field.optional {
+                            true => TypeRef::option(field.ty),
+                            false => field.ty,
+                        };
+                        variants.push((field.name, ty));
+                    }
+                    "sequence" | "choice" | "group" => {
+                        return Err(Error::Schema(
+                            owner.to_string(),
+                            format!("`{kind}` in a `choice` is not supported"),
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+            Ok(ModelKind::Tagged(None, variants))
+        })?;
+        let ty = TypeRef::Named(symbol.rust_name);
+        fields.push(occurs.field("$value".to_string(), ty, None));
+        Ok(())
+    }
+
+    fn element_field(
+        &mut self,
+        node: Node<'a, 'a>,
+        module: &[String],
+        owner: &str,
+        occurs: Occurs,
+    ) -> Result<ModelField> {
+        let occurs = occurs.within(node)?;
+        let (name, ty, doc) = match node.attribute("ref") {
+            Some(reference) => {
+                let qname = self.qname(node, reference)?;
+                let global = *self.elements.get(&qname).ok_or_else(|| {
+                    Error::Schema(reference.to_string(), "unknown element".to_string())
+                })?;
+                let ty = match self.element_types.get(&qname) {
+                    Some(symbol) => TypeRef::Named(self.path(symbol, module)),
+                    None => self.declared_type(global, module)?,
+                };
+                (
+                    qname.1,
+                    ty,
+                    documentation(node).or_else(|| documentation(global)),
+                )
+            }
+            None => {
+                let name = node.attribute("name").ok_or_else(|| {
+                    Error::Schema(owner.to_string(), "element without a name".to_string())
+                })?;
+                let ty = match self.inline_type(node, module, owner, name)? {
+                    Some(ty) => ty,
+                    None => self.declared_type(node, module)?,
+                };
+                (name.to_string(), ty, documentation(node))
+            }
+        };
+        Ok(occurs.field(name, ty, doc))
+    }
+
+    fn attribute(
+        &mut self,
+        node: Node<'a, 'a>,
+        module: &[String],
+        owner: &str,
+        fields: &mut Vec<ModelField>,
+    ) -> Result<()> {
+        if node.attribute("use") == Some("prohibited") {
+            return Ok(());
+        }
+        let optional = node.attribute("use") != Some("required");
+        let (name, ty) = match node.attribute("ref") {
+            Some(reference) => {
+                let qname = self.qname(node, reference)?;
+                match self.attributes.get(&qname).copied() {
+                    Some(global) => (qname.1, self.declared_type(global, module)?),
+                    // ex: `xml:lang`, matched with its prefix
+                    None => (reference.to_string(), TypeRef::builtin("String")),
+                }
+            }
+            None => {
+                let name = node.attribute("name").ok_or_else(|| {
+                    Error::Schema(owner.to_string(), "attribute without a name".to_string())
+                })?;
+                let ty = match self.inline_type(node, module, owner, name)? {
+                    Some(ty) => ty,
+                    None => self.declared_type(node, module)?,
+                };
+                (name.to_string(), ty)
+            }
+        };
+        let field = ModelField {
+            name: format!("@{name}"),
+            ty,
+            optional,
+            default: false,
+            doc: documentation(node),
+        };
+        add_field(fields, field);
+        Ok(())
+    }
+
+    /// A type declared inside an element or attribute, named after it and its owner
+    fn inline_type(
+        &mut self,
+        node: Node<'a, 'a>,
+        module: &[String],
+        owner: &str,
+        name: &str,
+    ) -> Result<Option<TypeRef>> {
+        for (child, kind) in xs_children(node) {
+            if !matches!(kind, "complexType" | "simpleType") {
+                continue;
+            }
+            let nested = RustCase::convert_lossy(name, RustCase::Pascal);
+            let symbol = Symbol {
+                module: module.to_vec(),
+                rust_name: self.reserve(module, &format!("{owner}{nested}")),
+            };
+            self.define(&symbol, documentation(child), |g| {
+                g.inline_kind(child, &symbol)
+            })?;
+            return Ok(Some(TypeRef::Named(symbol.rust_name)));
+        }
+        Ok(None)
+    }
+
+    fn inline_kind(&mut self, node: Node<'a, 'a>, symbol: &Symbol) -> Result<ModelKind> {
+        match node.tag_name().name() {
+            "complexType" => Ok(ModelKind::Struct(self.content(
+                node,
+                &symbol.module,
+                &symbol.rust_name,
+            )?)),
+            _ => self.simple_kind(node, &symbol.module),
+        }
+    }
+
+    /// The `type` of an element or attribute, `anyType` when there's none
+    fn declared_type(&self, node: Node, module: &[String]) -> Result<TypeRef> {
+        match node.attribute("type") {
+            Some(ty) => self.resolve(node, ty, module),
+            None => Ok(TypeRef::builtin("String")),
+        }
+    }
+
+    fn simple_kind(&mut self, node: Node<'a, 'a>, module: &[String]) -> Result<ModelKind> {
+        for (child, kind) in xs_children(node) {
+            match kind {
+                "restriction" => {
+                    let values = xs_children(child)
+                        .filter(|(_, kind)| *kind == "enumeration")
+                        .filter_map(|(facet, _)| facet.attribute("value"))
+                        .map(String::from)
+                        .collect::<Vec<_>>();
+                    if !values.is_empty() {
+                        return Ok(ModelKind::Enum(values));
+                    }
+                    return Ok(ModelKind::Alias(match child.attribute("base") {
+                        Some(base) => self.resolve(child, base, module)?,
+                        None => TypeRef::builtin("String"),
+                    }));
+                }
+                "list" => {
+                    let item = match child.attribute("itemType") {
+                        Some(item) => self.resolve(child, item, module)?,
+                        None => TypeRef::builtin("String"),
+                    };
+                    return Ok(ModelKind::Alias(TypeRef::vec(item)));
+                }
+                "union" => {
+                    let members = child.attribute("memberTypes").unwrap_or_default();
+                    let variants = members
+                        .split_whitespace()
+                        .map(|member| {
+                            let local = member.rsplit(':').next().unwrap_or(member);
+                            let name = RustCase::convert_lossy(local, RustCase::Pascal);
+                            Ok((name, self.resolve(child, member, module)?))
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    if !variants.is_empty() {
+                        return Ok(ModelKind::Union(variants));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(ModelKind::Alias(TypeRef::builtin("String")))
+    }
+
+    fn qname(&self, node: Node, raw: &str) -> Result<QName> {
+        let (prefix, local) = match raw.split_once(':') {
+            Some((prefix, local)) => (Some(prefix), local),
+            None => (None, raw),
+        };
+        let namespace = match node.lookup_namespace_uri(prefix) {
+            Some(namespace) => namespace,
+            None if prefix.is_none() => "",
+            None => {
+                return Err(Error::Schema(
+                    raw.to_string(),
+                    "undeclared namespace prefix".to_string(),
+                ))
+            }
+        };
+        Ok((namespace.to_string(), local.to_string()))
+    }
+
+    /// A type name as seen from a module
+    fn resolve(&self, node: Node, raw: &str, module: &[String]) -> Result<TypeRef> {
+        let qname = self.qname(node, raw)?;
+        if qname.0 == XS {
+            return self.xsd.builtin(&qname.1).ok_or_else(|| {
+                Error::Schema(raw.to_string(), "unknown built-in type".to_string())
+            });
+        }
+        let symbol = self
+            .types
+            .get(&qname)
+            .ok_or_else(|| Error::Schema(raw.to_string(), "unknown type".to_string()))?;
+        Ok(TypeRef::Named(self.path(symbol, module)))
+    }
+
+    fn path(&self, symbol: &Symbol, from: &[String]) -> String {
+        let common = from
+            .iter()
+            .zip(&symbol.module)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut path = vec!["super".to_string(); from.len() - common];
+        path.extend(symbol.module[common..].iter().cloned());
+        path.push(symbol.rust_name.clone());
+        path.join("::")
+    }
+
+    fn reserve(&mut self, module: &[String], name: &str) -> String {
+        let taken = self
+            .taken
+            .entry(module.to_vec())
+            .or_insert_with(|| BTreeSet::from(RESERVED_NAMES.map(String::from)));
+        let mut candidate = name.to_string();
+        let mut counter = 2;
+        while !taken.insert(candidate.clone()) {
+            candidate = format!("{name}{counter}");
+            counter += 1;
+        }
+        candidate
+    }
+
+    /// Types are listed before the ones declared inside them
+    fn define(
+        &mut self,
+        symbol: &Symbol,
+        doc: Option<String>,
+        build: impl FnOnce(&mut Self) -> Result<ModelKind>,
+    ) -> Result<()> {
+        let types = self.modules.entry(symbol.module.clone()).or_default();
+        let index = types.len();
+        types.push(ModelType {
+            name: symbol.rust_name.clone(),
+            doc,
+            kind: ModelKind::Struct(vec![]),
+        });
+        let kind = build(self)?;
+        self.modules.get_mut(&symbol.module).expect("defined above")[index].kind = kind;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xsd::Xsd;
+    use std::path::Path;
+
+    const ORDERS: &str = r#"<?xml version="1.0"?>
+<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+           xmlns:o="http://example.com/orders/v1"
+           targetNamespace="http://example.com/orders/v1">
+  <xs:element name="order" type="o:Order"/>
+  <xs:complexType name="Order">
+    <xs:annotation><xs:documentation>A placed order</xs:documentation></xs:annotation>
+    <xs:sequence>
+      <xs:element name="customer" type="xs:string"/>
+      <xs:element name="note" type="xs:string" minOccurs="0"/>
+      <xs:element name="line" maxOccurs="unbounded">
+        <xs:complexType>
+          <xs:attribute name="sku" type="xs:string" use="required"/>
+          <xs:attribute name="qty" type="xs:unsignedInt"/>
+        </xs:complexType>
+      </xs:element>
+      <xs:choice>
+        <xs:element name="card" type="xs:string"/>
+        <xs:element name="invoice" type="o:Order"/>
+      </xs:choice>
+    </xs:sequence>
+    <xs:attribute name="status" type="o:Status" use="required"/>
+  </xs:complexType>
+  <xs:simpleType name="Status">
+    <xs:restriction base="xs:string">
+      <xs:enumeration value="placed"/>
+      <xs:enumeration value="in-transit"/>
+    </xs:restriction>
+  </xs:simpleType>
+</xs:schema>
+"#;
+
+    #[test]
+    fn generates_namespace_module() {
+        let rendered = Xsd::parse(ORDERS).into_module("api").unwrap().render();
+        assert_eq!(rendered[Path::new("api.rs")], "pub mod example_com;\n");
+        assert_eq!(
+            rendered[Path::new("example_com/mod.rs")],
+            "pub mod orders;\n"
+        );
+        assert_eq!(
+            rendered[Path::new("example_com/orders/mod.rs")],
+            "pub mod v1;\n"
+        );
+        let expected = r#"use serde::{Deserialize, Serialize};
+#[doc = "A placed order"]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Order {
+pub customer: String,
+#[serde(skip_serializing_if = "Option::is_none")]
+pub note: Option<String>,
+#[serde(default)]
+pub line: Vec<OrderLine>,
+#[serde(rename = "$value")]
+pub value: Box<OrderChoice>,
+#[serde(rename = "@status")]
+pub status: Status,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderLine {
+#[serde(rename = "@sku")]
+pub sku: String,
+#[serde(rename = "@qty")]
+#[serde(skip_serializing_if = "Option::is_none")]
+pub qty: Option<u32>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum OrderChoice {
+#[serde(rename = "card")]
+
+Card(String),
+#[serde(rename = "invoice")]
+
+Invoice(Box<Order>),
+}
+#[derive(Copy, Eq, Hash, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Status {
+#[serde(rename = "placed")]
+
+Placed,
+#[serde(rename = "in-transit")]
+
+InTransit,
+}
+"#;
+        assert_eq!(rendered[Path::new("example_com/orders/v1.rs")], expected);
+    }
+
+    #[test]
+    fn imports_other_namespaces() {
+        let common = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+           targetNamespace="urn:acme:common">
+  <xs:complexType name="Money">
+    <xs:simpleContent>
+      <xs:extension base="xs:decimal">
+        <xs:attribute name="currency" type="xs:string" use="required"/>
+      </xs:extension>
+    </xs:simpleContent>
+  </xs:complexType>
+</xs:schema>"#;
+        let billing = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+           xmlns:c="urn:acme:common" xmlns:b="urn:acme:billing"
+           targetNamespace="urn:acme:billing">
+  <xs:import namespace="urn:acme:common" schemaLocation="common.xsd"/>
+  <xs:element name="invoice" type="b:Invoice"/>
+  <xs:complexType name="Document">
+    <xs:attribute name="id" type="xs:ID" use="required"/>
+  </xs:complexType>
+  <xs:complexType name="Invoice">
+    <xs:complexContent>
+      <xs:extension base="b:Document">
+        <xs:sequence>
+          <xs:element name="total" type="c:Money"/>
+        </xs:sequence>
+      </xs:extension>
+    </xs:complexContent>
+  </xs:complexType>
+</xs:schema>"#;
+        let rendered = Xsd::new()
+            .add_source("billing.xsd", billing)
+            .add_source("common.xsd", common)
+            .map_namespace("urn:acme:billing", "billing")
+            .map_type("decimal", "rust_decimal::Decimal")
+            .into_module("api")
+            .unwrap()
+            .render();
+        assert_eq!(
+            rendered[Path::new("api.rs")],
+            "pub mod billing;\npub mod acme;\n"
+        );
+        let money = &rendered[Path::new("acme/common.rs")];
+        assert!(money.contains(
+            "pub struct Money {\n#[serde(rename = \"$text\")]\npub text: rust_decimal::Decimal,\n"
+        ));
+        let billing = &rendered[Path::new("billing.rs")];
+        assert!(billing.contains(
+            "pub struct Invoice {\n#[serde(rename = \"@id\")]\npub id: String,\npub total: super::acme::common::Money,\n}"
+        ));
+    }
+
+    #[test]
+    fn breaks_cycles_and_name_clashes() {
+        let schema = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:complexType name="Item">
+    <xs:sequence>
+      <xs:element name="id" type="xs:string"/>
+      <xs:element name="child" type="Wrap" minOccurs="0"/>
+    </xs:sequence>
+    <xs:attribute name="id" type="xs:string"/>
+  </xs:complexType>
+  <xs:complexType name="Wrap">
+    <xs:sequence>
+      <xs:element name="item" type="Item"/>
+      <xs:element name="items" type="Item" maxOccurs="unbounded"/>
+    </xs:sequence>
+  </xs:complexType>
+</xs:schema>"#;
+        let rendered = Xsd::parse(schema).into_module("api").unwrap().render();
+        let api = &rendered[Path::new("api.rs")];
+        assert!(api.contains(
+            "pub struct Item {\n\
+            pub id: String,\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub child: Option<Box<Wrap>>,\n\
+            #[serde(rename = \"@id\")]\n\
+            #[serde(skip_serializing_if = \"Option::is_none\")]\n\
+            pub id2: Option<String>,\n"
+        ));
+        assert!(api.contains("pub item: Box<Item>,\n#[serde(default)]\npub items: Vec<Item>,\n"));
+
+        let shadowing = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+  <xs:complexType name="String">
+    <xs:sequence>
+      <xs:element name="value" type="xs:string"/>
+    </xs:sequence>
+  </xs:complexType>
+  <xs:element name="text" type="String"/>
+</xs:schema>"#;
+        let rendered = Xsd::parse(shadowing).into_module("api").unwrap().render();
+        let api = &rendered[Path::new("api.rs")];
+        assert!(api.contains("pub struct String2 {\npub value: String,\n}\n"));
+    }
+}
@@ -0,0 +1,348 @@
+//! Compiles what the front-ends generate for inputs known to trip them up, the unit tests only
+//! compare the rendered text
+#![cfg(any(
+    feature = "json-schema",
+    feature = "proto",
+    feature = "config",
+    feature = "sql",
+    feature = "assets"
+))]
+
+#[cfg(any(
+    feature = "openapi",
+    feature = "xsd",
+    feature = "proto",
+    feature = "graphql"
+))]
+use codegen_rs::{ModuleBuilder, ModuleLayout};
+use std::path::PathBuf;
+
+/// Wraps the generated code in a module of a binary, so it's checked the way a user's crate
+/// would see it
+fn fixture(name: &str, generated: String) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("compile");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.rs"));
+    std::fs::write(
+        &path,
+        format!("#![allow(dead_code)]\n\nmod generated {{\n{generated}}}\n\nfn main() {{}}\n"),
+    )
+    .unwrap();
+    path
+}
+
+/// A module tree as a single file
+#[cfg(any(
+    feature = "openapi",
+    feature = "xsd",
+    feature = "proto",
+    feature = "graphql"
+))]
+fn inline(module: ModuleBuilder) -> String {
+    let rendered = module.set_layout(ModuleLayout::Inline).render();
+    rendered.into_values().next().unwrap()
+}
+
+#[test]
+fn generated_code_compiles() {
+    let cases = trybuild::TestCases::new();
+    #[cfg(feature = "json-schema")]
+    cases.pass(fixture("json_schema", json_schema()));
+    #[cfg(feature = "json-schema")]
+    cases.pass(fixture("json_sample", json_sample()));
+    #[cfg(feature = "openapi")]
+    cases.pass(fixture("openapi", openapi()));
+    #[cfg(feature = "xsd")]
+    cases.pass(fixture("xsd", xsd()));
+    #[cfg(feature = "proto")]
+    cases.pass(fixture("proto", proto()));
+    #[cfg(feature = "avro")]
+    cases.pass(fixture("avro", avro()));
+    #[cfg(feature = "config")]
+    cases.pass(fixture("config", config()));
+    #[cfg(feature = "sql")]
+    cases.pass(fixture("sql", sql()));
+    #[cfg(feature = "graphql")]
+    cases.pass(fixture("graphql", graphql()));
+    #[cfg(feature = "assets")]
+    for (name, dev) in [
+        ("assets", None),
+        ("assets_embedded", Some("any()")),
+        ("assets_dev", Some("all()")),
+    ] {
+        cases.pass(fixture(name, assets(dev)));
+    }
+}
+
+#[cfg(feature = "json-schema")]
+fn json_schema() -> String {
+    use codegen_rs::json_schema::JsonSchema;
+    JsonSchema::parse(
+        "user",
+        r##"{"type": "object", "required": ["userName", "user_name"], "properties": {
+            "userName": {"type": "string"},
+            "user_name": {"type": "string"},
+            "type": {"type": "string"},
+            "tags": {"type": "object", "additionalProperties": {"type": "integer"}},
+            "string": {"$ref": "#/definitions/String"}
+        }, "definitions": {"String": {"type": "object", "properties": {
+            "vec": {"type": "array", "items": {"type": "string"}}
+        }}}}"##,
+    )
+    .unwrap()
+    .into_file_builder("user")
+    .unwrap()
+    .format_file()
+}
+
+#[cfg(feature = "json-schema")]
+fn json_sample() -> String {
+    use codegen_rs::json_sample::JsonSamples;
+    JsonSamples::new("sample")
+        .add_sample(
+            r#"{"userName": "a", "user_name": "b", "self": 1, "1st": true, "a-b": 2, "": 3,
+            "kind": "open", "string": {"option": {"vec": [1]}}, "result": {"ok": 1},
+            "level": "Open", "mode": "", "Some": {"x": 1}}"#,
+        )
+        .unwrap()
+        .add_sample(
+            r#"{"userName": "c", "user_name": "d", "self": 2, "1st": false, "a-b": 3, "": 4,
+            "kind": "OPEN", "string": {"option": {"vec": []}}, "result": {"ok": 2},
+            "level": "open", "mode": "1", "Some": {"x": 2}}"#,
+        )
+        .unwrap()
+        .add_sample(
+            r#"{"userName": "e", "user_name": "f", "self": 3, "1st": true, "a-b": 4, "": 5,
+            "kind": "open", "string": {"option": {"vec": [2]}}, "result": {"ok": 3},
+            "level": "Open", "mode": "", "Some": {"x": 3}}"#,
+        )
+        .unwrap()
+        .set_enum_threshold(4)
+        .into_file_builder("sample")
+        .unwrap()
+        .format_file()
+}
+
+#[cfg(feature = "openapi")]
+fn openapi() -> String {
+    use codegen_rs::openapi::OpenApi;
+    let spec = r##"
+openapi: 3.0.3
+info: {title: Search, version: "1"}
+paths:
+  /items/{request}:
+    post:
+      operationId: search
+      parameters:
+        - {name: request, in: path, required: true, schema: {type: string}}
+        - {name: response, in: query, required: true, schema: {type: integer}}
+        - {name: body, in: query, schema: {type: string}}
+        - {name: param_string, in: header, required: true, schema: {type: string}}
+      requestBody:
+        content: {application/json: {schema: {$ref: "#/components/schemas/Query"}}}
+      responses:
+        200:
+          description: ok
+          content: {application/json: {schema: {type: array, items: {$ref: "#/components/schemas/Query"}}}}
+  /transport:
+    get:
+      operationId: transport
+      responses:
+        204: {description: done}
+        500: {description: failed}
+    put:
+      operationId: new
+      responses:
+        204: {description: done}
+components:
+  schemas:
+    Query:
+      type: object
+      required: [text]
+      properties:
+        text: {type: string}
+        type: {type: string}
+"##;
+    inline(OpenApi::parse(spec).unwrap().into_module("api").unwrap())
+}
+
+#[cfg(feature = "xsd")]
+fn xsd() -> String {
+    use codegen_rs::xsd::Xsd;
+    let schema = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+           xmlns:o="http://example.com/orders/v1"
+           targetNamespace="http://example.com/orders/v1">
+  <xs:complexType name="Item">
+    <xs:sequence>
+      <xs:element name="id" type="xs:string"/>
+      <xs:element name="child" type="o:Wrap" minOccurs="0"/>
+      <xs:choice>
+        <xs:element name="note" type="xs:string"/>
+        <xs:element name="nested" type="o:Item"/>
+      </xs:choice>
+    </xs:sequence>
+    <xs:attribute name="id" type="xs:string"/>
+  </xs:complexType>
+  <xs:complexType name="Wrap">
+    <xs:sequence>
+      <xs:element name="item" type="o:Item"/>
+      <xs:element name="items" type="o:Item" maxOccurs="unbounded"/>
+      <xs:element name="label" type="o:String"/>
+    </xs:sequence>
+  </xs:complexType>
+  <xs:complexType name="String">
+    <xs:sequence>
+      <xs:element name="value" type="xs:string"/>
+    </xs:sequence>
+  </xs:complexType>
+</xs:schema>"#;
+    inline(Xsd::parse(schema).into_module("api").unwrap())
+}
+
+#[cfg(feature = "proto")]
+fn proto() -> String {
+    use codegen_rs::proto::Proto;
+    let source = r#"
+syntax = "proto3";
+package acme.v1;
+message Outer {
+  message Inner { string id = 1; Outer parent = 2; }
+  Inner inner = 1;
+  oneof kind { string name = 2; Inner other = 3; }
+  message Kind { bool set = 1; }
+  enum State { STATE_UNSPECIFIED = 0; STATE_DONE = 1; }
+}
+message OuterInner { Outer.Inner nested = 1; Outer.State state = 2; }
+message Option { string user_name = 1; string userName = 2; oneof user { bool a_b = 3; int32 aB = 4; } }
+message Default { Color color = 1; }
+enum Color { COLOR_RED = 0; RED = 1; }
+"#;
+    inline(Proto::parse(source).into_module("api").unwrap())
+}
+
+#[cfg(feature = "avro")]
+fn avro() -> String {
+    use codegen_rs::avro::Avro;
+    let schema = r#"{"type": "record", "name": "Payment", "namespace": "com.acme", "fields": [
+        {"name": "amount", "type": [
+            {"type": "record", "name": "a.Money", "fields": [{"name": "units", "type": "long"}]},
+            {"type": "record", "name": "b.Money", "fields": [{"name": "cents", "type": "long"}]}
+        ]},
+        {"name": "at", "type": {"type": "long", "logicalType": "timestamp-millis"}, "default": 0},
+        {"name": "count", "type": "int", "default": 0},
+        {"name": "previous", "type": ["null", "Payment"], "default": null},
+        {"name": "notes", "type": {"type": "record", "name": "Vec", "fields": [
+            {"name": "items", "type": {"type": "array", "items": "string"}},
+            {"name": "label", "type": ["null", "string"]}
+        ]}}
+    ]}"#;
+    Avro::parse(schema)
+        .unwrap()
+        .map_logical_type("timestamp-millis", "std::time::SystemTime")
+        .into_file_builder("payments")
+        .unwrap()
+        .format_file()
+}
+
+#[cfg(feature = "config")]
+fn config() -> String {
+    use codegen_rs::config::ConfigSample;
+    let sample = r#"
+name = "api"
+hosts = ["a", "b"]
+
+[default]
+level = "info"
+
+[[servers]]
+host = "alpha"
+weight = 1
+[servers.tls]
+cert = "a.pem"
+ca = "ca.pem"
+
+[[servers]]
+host = "beta"
+weight = 0.5
+[servers.tls]
+key = "b.key"
+ca = "ca.pem"
+"#;
+    ConfigSample::toml("app", sample)
+        .into_file_builder("config")
+        .unwrap()
+        .format_file()
+}
+
+#[cfg(feature = "sql")]
+fn sql() -> String {
+    use codegen_rs::sql::Sql;
+    let schema = r#"
+CREATE TABLE Accounts (ID BIGSERIAL, "Name" TEXT NOT NULL, type TEXT, PRIMARY KEY (id));
+CREATE TABLE grants (
+    id BIGINT PRIMARY KEY,
+    account BIGINT NOT NULL REFERENCES ACCOUNTS (Id),
+    "name" TEXT
+);
+CREATE TABLE option (id BIGINT PRIMARY KEY, account_id BIGINT, "accountId" BIGINT);
+CREATE TABLE accounts_id (owner BIGINT REFERENCES accounts);
+CREATE TABLE "GRANTS" (note TEXT);
+"#;
+    Sql::parse(schema)
+        .unwrap()
+        .into_file_builder("rows")
+        .unwrap()
+        .format_file()
+}
+
+#[cfg(feature = "graphql")]
+fn graphql() -> String {
+    use codegen_rs::graphql::GraphQl;
+    let schema = r#"
+type Query { item(id: ID!): Item, options: [Option!]! }
+type Option { id: ID!, label: String }
+type Item { id: ID!, self: String, type: String, option: Option, parent: Item, related: [Box!] }
+type Box { size: Int }
+enum Vec { SMALL, LARGE, Self }
+"#;
+    let operations = r#"
+query getItem($id: ID!) { item(id: $id) { id self type option { label } parent { id } } }
+query GetItem($id: ID!) { item(id: $id) { related { size } } }
+query options { options { id label } }
+"#;
+    inline(
+        GraphQl::parse_schema(schema)
+            .unwrap()
+            .add_operations(operations)
+            .unwrap()
+            .into_module("schema")
+            .unwrap(),
+    )
+}
+
+/// `dev` is the predicate of builds reading from disk, `all()` always holds and `any()` never
+#[cfg(feature = "assets")]
+fn assets(dev: Option<&str>) -> String {
+    use codegen_rs::assets::Assets;
+    use codegen_rs::Cfg;
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("compile/static");
+    std::fs::create_dir_all(dir.join("sub/dir")).unwrap();
+    for (path, contents) in [
+        ("paths", &b"a"[..]),
+        ("404.html", b"<p>gone</p>"),
+        ("a-b.txt", b"dash"),
+        ("a_b.txt", b"underscore"),
+        ("-.css", b"p {}"),
+        ("logo.png", &[0x89, b'P', b'N', b'G', 0xff]),
+        (".hidden", b"skipped"),
+        ("sub/dir/data.json", b"{}"),
+    ] {
+        std::fs::write(dir.join(path), contents).unwrap();
+    }
+    let assets = Assets::new(dir);
+    let assets = match dev {
+        Some(predicate) => assets.load_from_disk_when(Cfg::raw(predicate)),
+        None => assets,
+    };
+    assets.into_file_builder("assets").unwrap().format_file()
+}